@@ -0,0 +1,401 @@
+use crate::alert::notifier::Notifier;
+use crate::alert::types::{alert_level, Alert, AlertLevel};
+use crate::shared::types::metric::Metric;
+use crate::shared::types::quality_profile::QualityProfile;
+use crate::shared::types::sensor_data::SensorData;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Turns the readings of the runner into alerts. A metric only changes its alert level when
+/// the new level has held for `min_duration`, and it only goes back to a better level when the
+/// value is clear of the band edge by the hysteresis margin. The alerts are sent by a
+/// background thread, so the runner doesn't wait for a command or a webhook.
+pub struct Alerter {
+    quality_profile: QualityProfile,
+    min_duration: chrono::Duration,
+    hysteresis: f32,
+    metrics: Vec<Metric>,
+    states: HashMap<(u32, Metric), MetricState>,
+    /// `None` once the alerter is dropped, which ends the notifier thread
+    sender: Option<Sender<Alert>>,
+    notifier_thread: Option<JoinHandle<()>>,
+}
+
+pub struct AlerterBuilder {
+    pub quality_profile: QualityProfile,
+    pub min_duration: Duration,
+    /// Margin in percent of the value, e.g. 5 means radon has to drop below 95 Bq/m3 to leave
+    /// a band that starts at 100 Bq/m3
+    pub hysteresis_percent: f32,
+    pub metrics: Vec<Metric>,
+    pub notifiers: Vec<Box<dyn Notifier>>,
+}
+
+impl From<AlerterBuilder> for Alerter {
+    fn from(builder: AlerterBuilder) -> Self {
+        Self::new(builder)
+    }
+}
+
+/// Alert level of one metric of one device
+#[derive(Debug, Default)]
+struct MetricState {
+    level: AlertLevel,
+    /// Level the metric is moving to and the timestamp of the first reading at that level
+    pending: Option<(AlertLevel, DateTime<Utc>)>,
+}
+
+impl Alerter {
+    pub fn new(builder: AlerterBuilder) -> Alerter {
+        let AlerterBuilder {
+            quality_profile,
+            min_duration,
+            hysteresis_percent,
+            metrics,
+            notifiers,
+        } = builder;
+
+        let (sender, receiver) = channel::<Alert>();
+        let notifier_thread = thread::spawn(move || {
+            // Ends when the alerter is dropped, after the alerts that are still queued
+            for alert in receiver {
+                for notifier in &notifiers {
+                    if let Err(error) = notifier.notify(&alert) {
+                        warn!(
+                            "[serial number: {}] The {} notifier failed: {}",
+                            alert.serial_number,
+                            notifier.name(),
+                            error
+                        );
+                    }
+                }
+            }
+        });
+
+        Alerter {
+            quality_profile,
+            min_duration: chrono::Duration::from_std(min_duration).unwrap_or(chrono::Duration::MAX),
+            hysteresis: hysteresis_percent.max(0.0) / 100.0,
+            metrics,
+            states: HashMap::new(),
+            sender: Some(sender),
+            notifier_thread: Some(notifier_thread),
+        }
+    }
+
+    /// Check a new reading of a device and queue the alerts it raises for every notifier,
+    /// without waiting for them to be sent
+    pub fn on_reading(&mut self, serial_number: u32, sensor_data: &SensorData) -> Vec<Alert> {
+        let alerts = self.check_reading(serial_number, sensor_data);
+
+        for alert in &alerts {
+            info!("[serial number: {}] Alert: {}", serial_number, alert);
+            let sent = self
+                .sender
+                .as_ref()
+                .is_some_and(|sender| sender.send(alert.clone()).is_ok());
+            if !sent {
+                warn!(
+                    "[serial number: {}] The notifier thread has stopped, the alert is not sent",
+                    serial_number
+                );
+            }
+        }
+
+        alerts
+    }
+
+    fn check_reading(&mut self, serial_number: u32, sensor_data: &SensorData) -> Vec<Alert> {
+        let timestamp = *sensor_data.timestamp();
+        let mut alerts = Vec::new();
+
+        for &metric in &self.metrics {
+            let value = match sensor_data.value(metric) {
+                Some(value) => value,
+                None => continue,
+            };
+            let state = self.states.entry((serial_number, metric)).or_default();
+            let level = level_with_hysteresis(
+                metric,
+                value,
+                state.level,
+                self.hysteresis,
+                &self.quality_profile,
+            );
+
+            if level == state.level {
+                state.pending = None;
+                continue;
+            }
+
+            let since = match state.pending {
+                // Keep counting while the metric keeps moving in the same direction, so a
+                // slow rise through Bad into Terrible doesn't restart the wait
+                Some((pending_level, since))
+                    if (pending_level > state.level) == (level > state.level) =>
+                {
+                    since
+                }
+                _ => timestamp,
+            };
+
+            if timestamp - since >= self.min_duration {
+                alerts.push(Alert {
+                    serial_number,
+                    metric,
+                    value,
+                    level,
+                    previous_level: state.level,
+                    timestamp,
+                });
+                state.level = level;
+                state.pending = None;
+            } else {
+                state.pending = Some((level, since));
+            }
+        }
+
+        alerts
+    }
+}
+
+impl Drop for Alerter {
+    /// Waits for the queued alerts, so the alerts of the last readings aren't lost when the
+    /// runner stops
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(notifier_thread) = self.notifier_thread.take() {
+            if notifier_thread.join().is_err() {
+                warn!("The notifier thread panicked");
+            }
+        }
+    }
+}
+
+/// The level of the value, but a better level than `current` only counts when the value is
+/// also at that level after moving it by the hysteresis margin in both directions
+fn level_with_hysteresis(
+    metric: Metric,
+    value: f32,
+    current: AlertLevel,
+    hysteresis: f32,
+    quality_profile: &QualityProfile,
+) -> AlertLevel {
+    let level = alert_level(metric, value, quality_profile);
+    if level >= current {
+        return level;
+    }
+
+    let margin = value.abs() * hysteresis;
+    let lower = alert_level(metric, value - margin, quality_profile);
+    let upper = alert_level(metric, value + margin, quality_profile);
+
+    level.max(lower).max(upper).min(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alert::error::NotifyErrorResult;
+    use std::sync::{Arc, Mutex};
+
+    type Recorded = Arc<Mutex<Vec<Alert>>>;
+
+    struct RecordingNotifier {
+        alerts: Recorded,
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn name(&self) -> &str {
+            "recording"
+        }
+
+        fn notify(&self, alert: &Alert) -> NotifyErrorResult<()> {
+            self.alerts.lock().unwrap().push(alert.clone());
+            Ok(())
+        }
+    }
+
+    fn alerter(min_duration_minutes: u64) -> (Alerter, Recorded) {
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        let alerter = AlerterBuilder {
+            quality_profile: QualityProfile::airthings(),
+            min_duration: Duration::from_secs(min_duration_minutes * 60),
+            hysteresis_percent: 5.0,
+            metrics: vec![Metric::RadonShortTermAverage],
+            notifiers: vec![Box::new(RecordingNotifier {
+                alerts: Arc::clone(&alerts),
+            })],
+        }
+        .into();
+
+        (alerter, alerts)
+    }
+
+    fn radon_reading(minute: i64, radon: f32) -> SensorData {
+        SensorData::test_reading(
+            SensorData::test_day_start() + chrono::Duration::minutes(12 * 60 + minute),
+        )
+        .with_value(Metric::RadonShortTermAverage, radon)
+        .with_value(Metric::RadonLongTermAverage, radon)
+    }
+
+    /// The alerts the notifier got, after the queued ones are sent
+    fn notified(alerter: Alerter, alerts: Recorded) -> Vec<Alert> {
+        drop(alerter);
+
+        Arc::try_unwrap(alerts).unwrap().into_inner().unwrap()
+    }
+
+    fn levels(alerts: &[Alert]) -> Vec<(AlertLevel, AlertLevel)> {
+        alerts
+            .iter()
+            .map(|alert| (alert.previous_level, alert.level))
+            .collect()
+    }
+
+    #[test]
+    fn test_single_spike_does_not_alert() {
+        let (mut alerter, alerts) = alerter(10);
+
+        for (minute, radon) in [(0, 50.0), (5, 200.0), (10, 50.0), (15, 60.0)] {
+            alerter.on_reading(2930027508, &radon_reading(minute, radon));
+        }
+
+        assert!(notified(alerter, alerts).is_empty());
+    }
+
+    #[test]
+    fn test_alert_after_min_duration_and_recovery() {
+        let (mut alerter, alerts) = alerter(10);
+
+        assert!(alerter.on_reading(1, &radon_reading(0, 120.0)).is_empty());
+        assert!(alerter.on_reading(1, &radon_reading(5, 130.0)).is_empty());
+        let raised = alerter.on_reading(1, &radon_reading(10, 125.0));
+        assert_eq!(levels(&raised), vec![(AlertLevel::Good, AlertLevel::Bad)]);
+        assert_eq!(raised[0].value, 125.0);
+
+        for minute in [15, 20, 25] {
+            alerter.on_reading(1, &radon_reading(minute, 40.0));
+        }
+
+        let alerts = notified(alerter, alerts);
+        assert_eq!(
+            levels(&alerts),
+            vec![
+                (AlertLevel::Good, AlertLevel::Bad),
+                (AlertLevel::Bad, AlertLevel::Good)
+            ]
+        );
+        assert!(alerts[1].is_recovery());
+    }
+
+    #[test]
+    fn test_rising_through_bad_into_terrible_keeps_waiting_time() {
+        let (mut alerter, alerts) = alerter(10);
+
+        for (minute, radon) in [(0, 120.0), (5, 160.0), (10, 170.0)] {
+            alerter.on_reading(1, &radon_reading(minute, radon));
+        }
+
+        assert_eq!(
+            levels(&notified(alerter, alerts)),
+            vec![(AlertLevel::Good, AlertLevel::Terrible)]
+        );
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        let (mut alerter, alerts) = alerter(0);
+
+        alerter.on_reading(1, &radon_reading(0, 110.0));
+        // Below 100 but within 5% of the band edge
+        assert!(alerter.on_reading(1, &radon_reading(5, 97.0)).is_empty());
+
+        alerter.on_reading(1, &radon_reading(10, 94.0));
+        assert_eq!(
+            levels(&notified(alerter, alerts)),
+            vec![
+                (AlertLevel::Good, AlertLevel::Bad),
+                (AlertLevel::Bad, AlertLevel::Good)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_band_edge_is_rounded_like_on_the_dashboard() {
+        let (mut alerter, _) = alerter(0);
+
+        // Shown as 100, the start of the bad band
+        let raised = alerter.on_reading(1, &radon_reading(0, 99.6));
+
+        assert_eq!(levels(&raised), vec![(AlertLevel::Good, AlertLevel::Bad)]);
+        assert_eq!(
+            AlertLevel::from(Metric::RadonShortTermAverage.quality(99.6)),
+            AlertLevel::Bad
+        );
+    }
+
+    #[test]
+    fn test_devices_are_tracked_separately() {
+        let (mut alerter, alerts) = alerter(0);
+
+        alerter.on_reading(1, &radon_reading(0, 200.0));
+        alerter.on_reading(2, &radon_reading(0, 50.0));
+        alerter.on_reading(2, &radon_reading(5, 200.0));
+
+        let serial_numbers: Vec<u32> = notified(alerter, alerts)
+            .iter()
+            .map(|alert| alert.serial_number)
+            .collect();
+        assert_eq!(serial_numbers, vec![1, 2]);
+    }
+
+    /// Blocks every alert until the test lets it through
+    struct BlockingNotifier {
+        release: Mutex<std::sync::mpsc::Receiver<()>>,
+        alerts: Recorded,
+    }
+
+    impl Notifier for BlockingNotifier {
+        fn name(&self) -> &str {
+            "blocking"
+        }
+
+        fn notify(&self, alert: &Alert) -> NotifyErrorResult<()> {
+            self.release.lock().unwrap().recv().unwrap();
+            self.alerts.lock().unwrap().push(alert.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_a_slow_notifier_does_not_hold_up_the_readings() {
+        let (release, release_receiver) = channel();
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        let mut alerter: Alerter = AlerterBuilder {
+            quality_profile: QualityProfile::airthings(),
+            min_duration: Duration::ZERO,
+            hysteresis_percent: 5.0,
+            metrics: vec![Metric::RadonShortTermAverage],
+            notifiers: vec![Box::new(BlockingNotifier {
+                release: Mutex::new(release_receiver),
+                alerts: Arc::clone(&alerts),
+            })],
+        }
+        .into();
+
+        // Both alerts are raised while the notifier is still stuck on the first one
+        assert_eq!(alerter.on_reading(1, &radon_reading(0, 200.0)).len(), 1);
+        assert_eq!(alerter.on_reading(1, &radon_reading(5, 40.0)).len(), 1);
+        assert!(alerts.lock().unwrap().is_empty());
+
+        release.send(()).unwrap();
+        release.send(()).unwrap();
+        assert_eq!(notified(alerter, alerts).len(), 2);
+    }
+}
@@ -0,0 +1,58 @@
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+pub type NotifyErrorResult<T> = Result<T, NotifyError>;
+
+#[derive(Debug)]
+pub enum NotifyError {
+    /// The command hook could not be started or waited for
+    Command {
+        command: String,
+        source: std::io::Error,
+    },
+    CommandStatus {
+        command: String,
+        status: std::process::ExitStatus,
+    },
+    CommandTimeout {
+        command: String,
+        timeout: Duration,
+    },
+    Webhook {
+        url: String,
+        source: Box<ureq::Error>,
+    },
+    Syslog(std::io::Error),
+}
+
+impl fmt::Display for NotifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotifyError::Command { command, .. } => write!(f, "Could not run `{}`", command),
+            NotifyError::CommandStatus { command, status } => {
+                write!(f, "`{}` failed with {}", command, status)
+            }
+            NotifyError::CommandTimeout { command, timeout } => write!(
+                f,
+                "`{}` was stopped after running for {}s",
+                command,
+                timeout.as_secs()
+            ),
+            NotifyError::Webhook { url, source } => {
+                write!(f, "POST to {} failed: {}", url, source)
+            }
+            NotifyError::Syslog(_) => write!(f, "Could not write to syslog"),
+        }
+    }
+}
+
+impl Error for NotifyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            NotifyError::Command { source, .. } | NotifyError::Syslog(source) => Some(source),
+            NotifyError::Webhook { source, .. } => Some(source.as_ref()),
+            NotifyError::CommandStatus { .. } | NotifyError::CommandTimeout { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod alerter;
+pub mod error;
+pub mod notifier;
+pub mod types;
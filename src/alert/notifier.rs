@@ -0,0 +1,292 @@
+use crate::alert::error::{NotifyError, NotifyErrorResult};
+use crate::alert::types::{Alert, AlertLevel};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// A hook command that runs longer than this is killed
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_SYSLOG_SOCKET: &str = "/dev/log";
+
+/// Sends alerts somewhere. A failing notifier doesn't stop the other notifiers or the runner.
+/// The notifiers run on the thread of the alerter, so a slow one doesn't hold up the runner.
+pub trait Notifier: Send {
+    /// Name for the log messages
+    fn name(&self) -> &str;
+    fn notify(&self, alert: &Alert) -> NotifyErrorResult<()>;
+}
+
+/// Runs a local command for every alert. The alert is passed in `AIR_QUALITY_*` environment
+/// variables.
+pub struct CommandNotifier {
+    command: Vec<String>,
+}
+
+impl CommandNotifier {
+    /// `command` is the program followed by its arguments
+    pub fn new(command: Vec<String>) -> Result<Self, String> {
+        if command.is_empty() {
+            return Err("the alert command is empty".to_owned());
+        }
+
+        Ok(Self { command })
+    }
+}
+
+impl Notifier for CommandNotifier {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    fn notify(&self, alert: &Alert) -> NotifyErrorResult<()> {
+        let command_line = self.command.join(" ");
+        let mut child = Command::new(&self.command[0])
+            .args(&self.command[1..])
+            .env("AIR_QUALITY_SERIAL_NUMBER", alert.serial_number.to_string())
+            .env("AIR_QUALITY_METRIC", alert.metric.quality_key())
+            .env("AIR_QUALITY_VALUE", alert.value.to_string())
+            .env("AIR_QUALITY_UNIT", alert.metric.unit())
+            .env("AIR_QUALITY_LEVEL", alert.level.name())
+            .env("AIR_QUALITY_PREVIOUS_LEVEL", alert.previous_level.name())
+            .env("AIR_QUALITY_TIMESTAMP", alert.timestamp.to_rfc3339())
+            .env("AIR_QUALITY_MESSAGE", alert.to_string())
+            .stdin(Stdio::null())
+            .spawn()
+            .map_err(|source| NotifyError::Command {
+                command: command_line.clone(),
+                source,
+            })?;
+
+        let started = Instant::now();
+        loop {
+            let status = child.try_wait().map_err(|source| NotifyError::Command {
+                command: command_line.clone(),
+                source,
+            })?;
+
+            match status {
+                Some(status) if status.success() => return Ok(()),
+                Some(status) => {
+                    return Err(NotifyError::CommandStatus {
+                        command: command_line,
+                        status,
+                    })
+                }
+                None if started.elapsed() >= COMMAND_TIMEOUT => {
+                    // The process may have exited in the meantime, then there is nothing to kill
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(NotifyError::CommandTimeout {
+                        command: command_line,
+                        timeout: COMMAND_TIMEOUT,
+                    });
+                }
+                None => sleep(Duration::from_millis(100)),
+            }
+        }
+    }
+}
+
+/// POSTs every alert as a JSON object to a URL
+pub struct WebhookNotifier {
+    url: String,
+    agent: ureq::Agent,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String) -> Self {
+        let agent = ureq::AgentBuilder::new().timeout(WEBHOOK_TIMEOUT).build();
+
+        Self { url, agent }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn notify(&self, alert: &Alert) -> NotifyErrorResult<()> {
+        let body = serde_json::json!({
+            "serial_number": alert.serial_number,
+            "metric": alert.metric.quality_key(),
+            "value": alert.value,
+            "unit": alert.metric.unit(),
+            "level": alert.level.name(),
+            "previous_level": alert.previous_level.name(),
+            "recovery": alert.is_recovery(),
+            "timestamp": alert.timestamp.to_rfc3339(),
+            "message": alert.to_string(),
+        });
+
+        self.agent
+            .post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&body.to_string())
+            .map(|_| ())
+            .map_err(|source| NotifyError::Webhook {
+                url: self.url.clone(),
+                source: Box::new(source),
+            })
+    }
+}
+
+/// Writes the alerts to the local syslog socket with the `user` facility
+pub struct SyslogNotifier {
+    socket_path: PathBuf,
+}
+
+impl SyslogNotifier {
+    pub fn new(socket_path: PathBuf) -> Self {
+        Self { socket_path }
+    }
+}
+
+impl Notifier for SyslogNotifier {
+    fn name(&self) -> &str {
+        "syslog"
+    }
+
+    fn notify(&self, alert: &Alert) -> NotifyErrorResult<()> {
+        send_to_syslog(&self.socket_path, &syslog_message(alert)).map_err(NotifyError::Syslog)
+    }
+}
+
+/// RFC 3164 message without timestamp and host name, which the local syslog daemon adds
+fn syslog_message(alert: &Alert) -> String {
+    const FACILITY_USER: u8 = 1;
+    let severity: u8 = match alert.level {
+        AlertLevel::Terrible => 3,
+        AlertLevel::Bad => 4,
+        AlertLevel::Good => 5,
+    };
+
+    format!(
+        "<{}>air-quality-cli[{}]: {}",
+        FACILITY_USER * 8 + severity,
+        std::process::id(),
+        alert
+    )
+}
+
+#[cfg(unix)]
+fn send_to_syslog(socket_path: &std::path::Path, message: &str) -> std::io::Result<()> {
+    let socket = std::os::unix::net::UnixDatagram::unbound()?;
+    socket.send_to(message.as_bytes(), socket_path)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn send_to_syslog(_socket_path: &std::path::Path, _message: &str) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "syslog is only available on Unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::types::metric::Metric;
+    use chrono::{TimeZone, Utc};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn radon_alert(level: AlertLevel, previous_level: AlertLevel) -> Alert {
+        Alert {
+            serial_number: 2930027508,
+            metric: Metric::RadonShortTermAverage,
+            value: 180.0,
+            level,
+            previous_level,
+            timestamp: Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_command_notifier_gets_alert_in_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("alert.txt");
+        let notifier = CommandNotifier::new(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            format!(
+                "echo \"$AIR_QUALITY_SERIAL_NUMBER $AIR_QUALITY_METRIC $AIR_QUALITY_LEVEL\" > {}",
+                output.to_string_lossy()
+            ),
+        ])
+        .unwrap();
+
+        notifier
+            .notify(&radon_alert(AlertLevel::Terrible, AlertLevel::Good))
+            .unwrap();
+
+        let content = std::fs::read_to_string(output).unwrap();
+        assert_eq!(content.trim(), "2930027508 radon terrible");
+    }
+
+    #[test]
+    fn test_command_notifier_failure() {
+        let notifier = CommandNotifier::new(vec!["false".to_owned()]).unwrap();
+
+        let result = notifier.notify(&radon_alert(AlertLevel::Bad, AlertLevel::Good));
+
+        assert!(matches!(result, Err(NotifyError::CommandStatus { .. })));
+        assert!(CommandNotifier::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_webhook_notifier_posts_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/alerts", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            // Read until the whole JSON body has arrived
+            while !request.ends_with(b"}") {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        WebhookNotifier::new(url)
+            .notify(&radon_alert(AlertLevel::Good, AlertLevel::Bad))
+            .unwrap();
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /alerts HTTP/1.1"));
+        let body: serde_json::Value =
+            serde_json::from_str(&request[request.find('{').unwrap()..]).unwrap();
+        assert_eq!(body["metric"], "radon");
+        assert_eq!(body["level"], "good");
+        assert_eq!(body["recovery"], true);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_syslog_notifier() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket_path = dir.path().join("log");
+        let socket = std::os::unix::net::UnixDatagram::bind(&socket_path).unwrap();
+
+        SyslogNotifier::new(socket_path)
+            .notify(&radon_alert(AlertLevel::Bad, AlertLevel::Good))
+            .unwrap();
+
+        let mut buffer = [0; 1024];
+        let length = socket.recv(&mut buffer).unwrap();
+        let message = String::from_utf8_lossy(&buffer[..length]);
+        assert!(message.starts_with("<12>air-quality-cli["));
+        assert!(message.ends_with(
+            "Radon short-term average of device 2930027508 is BAD: 180 Bq/m3 (was GOOD)"
+        ));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::shared::types::metric::Metric;
+use crate::shared::types::quality_profile::QualityProfile;
+use crate::shared::types::sensor_quality::SensorQuality;
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+/// The metrics that can raise alerts: one per `Metric::quality_key`, the short-term average for
+/// radon. They are named by their quality key in the config file and the notifications.
+pub fn alert_metrics() -> impl Iterator<Item = Metric> {
+    Metric::ALL.iter().copied().filter(|metric| {
+        Metric::ALL
+            .iter()
+            .find(|other| other.quality_key() == metric.quality_key())
+            == Some(metric)
+    })
+}
+
+pub fn alert_metric_from_name(name: &str) -> Option<Metric> {
+    alert_metrics().find(|metric| metric.quality_key() == name)
+}
+
+/// Alert level of a value with the ranges of the profile, the same quality as on the dashboard
+pub fn alert_level(metric: Metric, value: f32, profile: &QualityProfile) -> AlertLevel {
+    metric.quality_with(value, profile).into()
+}
+
+/// `SensorQuality` without the context dependent values, which never raise an alert
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum AlertLevel {
+    #[default]
+    Good,
+    Bad,
+    Terrible,
+}
+
+impl AlertLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            AlertLevel::Good => "good",
+            AlertLevel::Bad => "bad",
+            AlertLevel::Terrible => "terrible",
+        }
+    }
+}
+
+impl From<SensorQuality> for AlertLevel {
+    fn from(quality: SensorQuality) -> Self {
+        match quality {
+            SensorQuality::Good | SensorQuality::DependsOnContext => AlertLevel::Good,
+            SensorQuality::Bad => AlertLevel::Bad,
+            SensorQuality::Terrible => AlertLevel::Terrible,
+        }
+    }
+}
+
+/// A change of the alert level of one sensor of a device
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub serial_number: u32,
+    pub metric: Metric,
+    pub value: f32,
+    pub level: AlertLevel,
+    pub previous_level: AlertLevel,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Alert {
+    /// Back to good after a bad or terrible level
+    pub fn is_recovery(&self) -> bool {
+        self.level == AlertLevel::Good
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let change = if self.is_recovery() {
+            "is back to"
+        } else {
+            "is"
+        };
+
+        write!(
+            f,
+            "{} of device {} {} {}: {} {} (was {})",
+            self.metric.name(),
+            self.serial_number,
+            change,
+            self.level.name().to_uppercase(),
+            self.value,
+            self.metric.unit(),
+            self.previous_level.name().to_uppercase()
+        )
+    }
+}
@@ -0,0 +1,197 @@
+use crate::alert::alerter::{Alerter, AlerterBuilder};
+use crate::alert::notifier::{
+    CommandNotifier, Notifier, SyslogNotifier, WebhookNotifier, DEFAULT_SYSLOG_SOCKET,
+};
+use crate::alert::types::{alert_metric_from_name, alert_metrics};
+use crate::shared::types::quality_profile::QualityProfile;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_MIN_DURATION_MINUTES: u64 = 15;
+const DEFAULT_HYSTERESIS_PERCENT: f32 = 5.0;
+
+/// The `[alerts]` section. The runner only checks for alerts when at least one notifier is set.
+///
+/// ```toml
+/// [alerts]
+/// min_duration_minutes = 15
+/// hysteresis_percent = 5
+/// metrics = ["radon", "co2"]
+///
+/// [[alerts.notifiers]]
+/// type = "command"
+/// command = ["notify-send", "Air quality alert"]
+///
+/// [[alerts.notifiers]]
+/// type = "webhook"
+/// url = "https://example.com/hooks/air-quality"
+///
+/// [[alerts.notifiers]]
+/// type = "syslog"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    /// How long a level has to hold before it is reported
+    pub min_duration_minutes: Option<u64>,
+    pub hysteresis_percent: Option<f32>,
+    /// Checked metrics, all of them when left out
+    pub metrics: Option<Vec<String>>,
+    pub notifiers: Vec<NotifierConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum NotifierConfig {
+    /// Program and arguments, run without a shell
+    Command {
+        command: Vec<String>,
+    },
+    Webhook {
+        url: String,
+    },
+    Syslog {
+        socket_path: Option<PathBuf>,
+    },
+}
+
+impl NotifierConfig {
+    fn to_notifier(&self) -> Result<Box<dyn Notifier>, String> {
+        Ok(match self {
+            NotifierConfig::Command { command } => Box::new(CommandNotifier::new(command.clone())?),
+            NotifierConfig::Webhook { url } => Box::new(WebhookNotifier::new(url.clone())),
+            NotifierConfig::Syslog { socket_path } => Box::new(SyslogNotifier::new(
+                socket_path
+                    .clone()
+                    .unwrap_or_else(|| PathBuf::from(DEFAULT_SYSLOG_SOCKET)),
+            )),
+        })
+    }
+}
+
+impl AlertsConfig {
+    /// `None` when no notifier is configured
+    pub fn to_alerter(&self, quality_profile: &QualityProfile) -> Result<Option<Alerter>, String> {
+        if self.notifiers.is_empty() {
+            return Ok(None);
+        }
+
+        let metrics = match &self.metrics {
+            Some(names) => names
+                .iter()
+                .map(|name| {
+                    alert_metric_from_name(name).ok_or_else(|| {
+                        format!(
+                            "[alerts] unknown metric {:?}, expected one of {}",
+                            name,
+                            alert_metrics()
+                                .map(|metric| metric.quality_key())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => alert_metrics().collect(),
+        };
+
+        let hysteresis_percent = self
+            .hysteresis_percent
+            .unwrap_or(DEFAULT_HYSTERESIS_PERCENT);
+        if !(0.0..100.0).contains(&hysteresis_percent) {
+            return Err("[alerts] hysteresis_percent must be between 0 and 100".to_owned());
+        }
+
+        let notifiers = self
+            .notifiers
+            .iter()
+            .map(|notifier| {
+                notifier
+                    .to_notifier()
+                    .map_err(|message| format!("[alerts] {}", message))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let min_duration_minutes = self
+            .min_duration_minutes
+            .unwrap_or(DEFAULT_MIN_DURATION_MINUTES);
+
+        Ok(Some(
+            AlerterBuilder {
+                quality_profile: quality_profile.clone(),
+                min_duration: Duration::from_secs(min_duration_minutes * 60),
+                hysteresis_percent,
+                metrics,
+                notifiers,
+            }
+            .into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::shared::types::quality_profile::QualityProfile;
+
+    #[test]
+    fn test_no_notifiers_no_alerter() {
+        let config = Config::from_toml("[alerts]\nmetrics = [\"radon\"]").unwrap();
+
+        let alerter = config
+            .alerts
+            .to_alerter(&QualityProfile::airthings())
+            .unwrap();
+
+        assert!(alerter.is_none());
+    }
+
+    #[test]
+    fn test_notifiers() {
+        let config = Config::from_toml(
+            r#"
+            [alerts]
+            min_duration_minutes = 10
+            metrics = ["radon", "co2"]
+
+            [[alerts.notifiers]]
+            type = "command"
+            command = ["notify-send", "Air quality alert"]
+
+            [[alerts.notifiers]]
+            type = "webhook"
+            url = "http://localhost:8123/api/webhook/air-quality"
+
+            [[alerts.notifiers]]
+            type = "syslog"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.alerts.notifiers.len(), 3);
+        assert!(config
+            .alerts
+            .to_alerter(&QualityProfile::airthings())
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_invalid_alert_settings() {
+        let to_alerter = |content: &str| {
+            Config::from_toml(content).and_then(|config| {
+                config
+                    .alerts
+                    .to_alerter(&QualityProfile::airthings())
+                    .map(|_| ())
+            })
+        };
+
+        let syslog = "[[alerts.notifiers]]\ntype = \"syslog\"";
+        assert!(to_alerter(&format!("[alerts]\nmetrics = [\"dust\"]\n{}", syslog)).is_err());
+        assert!(to_alerter(&format!("[alerts]\nhysteresis_percent = 150\n{}", syslog)).is_err());
+        assert!(to_alerter("[[alerts.notifiers]]\ntype = \"command\"\ncommand = []").is_err());
+        assert!(to_alerter("[[alerts.notifiers]]\ntype = \"email\"").is_err());
+    }
+}
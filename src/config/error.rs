@@ -0,0 +1,30 @@
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+pub type ConfigErrorResult<T> = Result<T, ConfigError>;
+
+#[derive(Debug)]
+pub struct ConfigError {
+    path: PathBuf,
+    message: String,
+}
+
+impl ConfigError {
+    pub fn new(path: PathBuf, message: String) -> Self {
+        Self { path, message }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[ConfigError] {}: {}",
+            self.path.to_string_lossy(),
+            self.message
+        )
+    }
+}
+
+impl Error for ConfigError {}
@@ -0,0 +1,177 @@
+use crate::config::alerts::AlertsConfig;
+use crate::config::error::{ConfigError, ConfigErrorResult};
+use crate::config::mqtt::MqttConfig;
+use crate::config::quality::QualityConfig;
+use crate::shared::storage::day_timezone::DayTimezone;
+use crate::shared::storage::error::StorageResult;
+use crate::shared::storage::{open_store, SensorDataStore, StorageBackend};
+use serde::Deserialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+pub mod alerts;
+pub mod error;
+pub mod mqtt;
+pub mod quality;
+
+pub const DEFAULT_CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Settings read from the TOML config file. Every section is optional and command line flags
+/// take precedence over the values in the file.
+///
+/// ```toml
+/// [runner]
+/// serial_numbers = [2930027508, 2930012345]
+/// interval_seconds = 300
+/// read_timeout_seconds = 60
+/// max_retries = 3
+/// max_consecutive_failures = 12
+/// metrics_address = "0.0.0.0:9101"
+///
+/// [dashboard]
+/// stale_after_minutes = 30
+///
+/// [quality]
+/// profiles = ["who"]
+///
+/// [alerts]
+/// min_duration_minutes = 15
+///
+/// [[alerts.notifiers]]
+/// type = "syslog"
+///
+/// [mqtt]
+/// host = "localhost"
+///
+/// [storage]
+/// backend = "sqlite"
+/// timezone = "Europe/Oslo"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub runner: RunnerConfig,
+    pub dashboard: DashboardConfig,
+    pub quality: QualityConfig,
+    pub alerts: AlertsConfig,
+    pub mqtt: Option<MqttConfig>,
+    pub storage: StorageConfig,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunnerConfig {
+    pub serial_numbers: Vec<u32>,
+    pub interval_seconds: Option<u64>,
+    pub read_timeout_seconds: Option<u64>,
+    pub max_retries: Option<u8>,
+    pub max_consecutive_failures: Option<u32>,
+    /// Address of the Prometheus `/metrics` endpoint, e.g. "0.0.0.0:9101"
+    pub metrics_address: Option<SocketAddr>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DashboardConfig {
+    /// Readings older than this are shown as stale
+    pub stale_after_minutes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// `csv` (the default) or `sqlite`
+    pub backend: StorageBackend,
+    /// Defaults to `air-quality.sqlite3` in the data dir
+    pub sqlite_path: Option<PathBuf>,
+    /// Timezone of the days of the CSV files, `UTC` (the default) or an IANA timezone like
+    /// `Europe/Oslo`
+    pub timezone: DayTimezone,
+}
+
+impl StorageConfig {
+    pub fn open_store(&self, data_dir: &Path) -> StorageResult<Box<dyn SensorDataStore>> {
+        open_store(
+            self.backend,
+            data_dir,
+            self.sqlite_path.clone(),
+            self.timezone,
+        )
+    }
+}
+
+impl Config {
+    /// Load the config file at `path`. A missing file is only an error when the path was given
+    /// explicitly, otherwise the defaults are used.
+    pub fn load(path: &Path, explicitly_given: bool) -> ConfigErrorResult<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound && !explicitly_given => {
+                return Ok(Self::default())
+            }
+            Err(error) => return Err(ConfigError::new(path.to_path_buf(), error.to_string())),
+        };
+
+        Self::from_toml(&content).map_err(|message| ConfigError::new(path.to_path_buf(), message))
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|error| error.to_string())
+    }
+}
+
+pub fn default_config_path(app_dir: &Path) -> PathBuf {
+    app_dir.join(DEFAULT_CONFIG_FILE_NAME)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_config_uses_defaults() {
+        let config = Config::from_toml("").unwrap();
+
+        assert!(config.runner.serial_numbers.is_empty());
+    }
+
+    #[test]
+    fn test_runner_serial_numbers() {
+        let config = Config::from_toml(
+            r#"
+            [runner]
+            serial_numbers = [2930027508, 2930012345]
+            interval_seconds = 600
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.runner.serial_numbers, vec![2930027508, 2930012345]);
+        assert_eq!(config.runner.interval_seconds, Some(600));
+        assert_eq!(config.runner.max_retries, None);
+    }
+
+    #[test]
+    fn test_dashboard_stale_after() {
+        let config = Config::from_toml("[dashboard]\nstale_after_minutes = 45").unwrap();
+
+        assert_eq!(config.dashboard.stale_after_minutes, Some(45));
+        assert_eq!(config.runner.interval_seconds, None);
+    }
+
+    #[test]
+    fn test_storage_timezone() {
+        let config = Config::from_toml("[storage]\ntimezone = \"Europe/Oslo\"").unwrap();
+
+        assert_eq!(config.storage.timezone.to_string(), "Europe/Oslo");
+        assert_eq!(Config::default().storage.timezone, DayTimezone::Utc);
+        assert!(Config::from_toml("[storage]\ntimezone = \"Oslo\"").is_err());
+    }
+
+    #[test]
+    fn test_unknown_setting_is_rejected() {
+        assert!(Config::from_toml("[runner]\nserial_number = 1").is_err());
+    }
+}
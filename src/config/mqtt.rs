@@ -0,0 +1,100 @@
+use crate::runner::mqtt::MqttPublisherBuilder;
+use serde::Deserialize;
+
+const DEFAULT_PORT: u16 = 1883;
+const DEFAULT_CLIENT_ID: &str = "air-quality-cli";
+const DEFAULT_TOPIC_PREFIX: &str = "air-quality";
+const DEFAULT_DISCOVERY_PREFIX: &str = "homeassistant";
+const DEFAULT_BUFFER_SIZE: usize = 1000;
+
+/// The `[mqtt]` section. The runner publishes every reading to the broker when it is set.
+///
+/// ```toml
+/// [mqtt]
+/// host = "homeassistant.local"
+/// port = 1883
+/// username = "air-quality"
+/// password = "secret"
+/// topic_prefix = "air-quality"
+/// discovery_prefix = "homeassistant"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub client_id: Option<String>,
+    pub topic_prefix: Option<String>,
+    pub discovery_prefix: Option<String>,
+    /// Send the Home Assistant discovery payloads [default: true]
+    pub discovery: Option<bool>,
+    /// Number of messages kept while the broker can't be reached
+    pub buffer_size: Option<usize>,
+}
+
+impl MqttConfig {
+    pub fn to_publisher_builder(&self, serial_numbers: &[u32]) -> MqttPublisherBuilder {
+        let discovery_prefix = if self.discovery.unwrap_or(true) {
+            Some(
+                self.discovery_prefix
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_DISCOVERY_PREFIX.to_owned()),
+            )
+        } else {
+            None
+        };
+
+        MqttPublisherBuilder {
+            host: self.host.clone(),
+            port: self.port.unwrap_or(DEFAULT_PORT),
+            client_id: self
+                .client_id
+                .clone()
+                .unwrap_or_else(|| DEFAULT_CLIENT_ID.to_owned()),
+            credentials: self
+                .username
+                .clone()
+                .map(|username| (username, self.password.clone().unwrap_or_default())),
+            topic_prefix: self
+                .topic_prefix
+                .clone()
+                .unwrap_or_else(|| DEFAULT_TOPIC_PREFIX.to_owned()),
+            discovery_prefix,
+            serial_numbers: serial_numbers.to_vec(),
+            buffer_size: self.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+
+    #[test]
+    fn test_mqtt_defaults() {
+        let config = Config::from_toml("[mqtt]\nhost = \"localhost\"").unwrap();
+
+        let builder = config.mqtt.unwrap().to_publisher_builder(&[2930027508]);
+
+        assert_eq!(builder.port, 1883);
+        assert_eq!(builder.topic_prefix, "air-quality");
+        assert_eq!(builder.discovery_prefix.as_deref(), Some("homeassistant"));
+        assert!(builder.credentials.is_none());
+    }
+
+    #[test]
+    fn test_mqtt_without_discovery() {
+        let config = Config::from_toml(
+            "[mqtt]\nhost = \"localhost\"\ndiscovery = false\nusername = \"air\"",
+        )
+        .unwrap();
+
+        let builder = config.mqtt.unwrap().to_publisher_builder(&[2930027508]);
+
+        assert_eq!(builder.discovery_prefix, None);
+        assert_eq!(builder.credentials, Some(("air".to_owned(), String::new())));
+        assert!(Config::from_toml("[mqtt]\nport = 1883").is_err());
+    }
+}
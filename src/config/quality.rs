@@ -0,0 +1,157 @@
+use crate::shared::types::metric::Metric;
+use crate::shared::types::quality_profile::{QualityBands, QualityProfile};
+use crate::shared::types::sensor_quality::SensorQuality;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// The `[quality]` section. The built-in profiles are applied in order on top of the Airthings
+/// ranges, then the ranges given per sensor replace the ones of the profiles.
+///
+/// ```toml
+/// [quality]
+/// profiles = ["who", "ashrae"]
+/// co2 = [
+///     { quality = "good" },
+///     { from = 1000, quality = "bad" },
+///     { from = 1500, quality = "terrible" },
+/// ]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct QualityConfig {
+    pub profiles: Vec<String>,
+    /// The ranges per `Metric::quality_key`, e.g. `co2` or `radon`
+    #[serde(flatten)]
+    pub ranges: BTreeMap<String, Vec<QualityBandConfig>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QualityBandConfig {
+    /// Lower bound of the band. Left out for the first band, which covers all lower values.
+    pub from: Option<f64>,
+    pub quality: SensorQuality,
+}
+
+impl QualityConfig {
+    pub fn to_profile(&self) -> Result<QualityProfile, String> {
+        let mut profile = QualityProfile::airthings();
+        for name in &self.profiles {
+            profile = profile.with_builtin(name)?;
+        }
+
+        for (quality_key, band_configs) in &self.ranges {
+            let metric = Metric::ALL
+                .iter()
+                .copied()
+                .find(|metric| metric.quality_key() == quality_key)
+                .ok_or_else(|| {
+                    format!(
+                        "[quality] unknown sensor {:?}, expected one of {}",
+                        quality_key,
+                        quality_keys().join(", ")
+                    )
+                })?;
+            let bands = to_quality_bands(band_configs)
+                .map_err(|message| format!("[quality] {}: {}", quality_key, message))?;
+            profile.set_bands(metric, bands);
+        }
+
+        Ok(profile)
+    }
+}
+
+fn quality_keys() -> Vec<&'static str> {
+    let mut quality_keys: Vec<_> = Metric::ALL
+        .iter()
+        .map(|metric| metric.quality_key())
+        .collect();
+    quality_keys.dedup();
+
+    quality_keys
+}
+
+fn to_quality_bands(band_configs: &[QualityBandConfig]) -> Result<QualityBands, String> {
+    let (first, rest) = band_configs
+        .split_first()
+        .ok_or_else(|| "at least one band is needed".to_owned())?;
+
+    if first.from.is_some() {
+        return Err("the first band covers all lower values and has no `from`".to_owned());
+    }
+
+    let bands = rest
+        .iter()
+        .map(|band| {
+            band.from
+                .map(|from| (from, band.quality))
+                .ok_or_else(|| "every band after the first needs a `from`".to_owned())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    QualityBands::new(first.quality, bands)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::shared::types::metric::Metric;
+    use crate::shared::types::quality_profile::QualityProfile;
+    use crate::shared::types::sensor_quality::SensorQuality;
+
+    #[test]
+    fn test_default_is_airthings() {
+        let profile = Config::from_toml("").unwrap().quality.to_profile().unwrap();
+
+        assert_eq!(profile, QualityProfile::airthings());
+    }
+
+    #[test]
+    fn test_profiles_and_own_bands() {
+        let config = Config::from_toml(
+            r#"
+            [quality]
+            profiles = ["who"]
+            co2 = [
+                { quality = "good" },
+                { from = 1200, quality = "terrible" },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        let profile = config.quality.to_profile().unwrap();
+
+        assert_eq!(
+            profile.bands(Metric::RadonLongTermAverage).quality(150.0),
+            SensorQuality::Bad
+        );
+        assert_eq!(
+            profile.bands(Metric::Co2).quality(1100.0),
+            SensorQuality::Good
+        );
+        assert_eq!(
+            profile.bands(Metric::Co2).quality(1200.0),
+            SensorQuality::Terrible
+        );
+        assert_eq!(
+            profile.bands(Metric::Voc),
+            QualityProfile::airthings().bands(Metric::Voc)
+        );
+    }
+
+    #[test]
+    fn test_invalid_quality_settings() {
+        let to_profile =
+            |content: &str| Config::from_toml(content).and_then(|c| c.quality.to_profile());
+
+        assert!(to_profile("[quality]\nprofiles = [\"osha\"]").is_err());
+        assert!(to_profile("[quality]\nco2 = [{ from = 0, quality = \"good\" }]").is_err());
+        assert!(to_profile(
+            "[quality]\nco2 = [{ quality = \"good\" }, { from = 900, quality = \"bad\" }, { from = 800, quality = \"terrible\" }]"
+        )
+        .is_err());
+        assert!(to_profile("[quality]\nco2 = [{ quality = \"fine\" }]").is_err());
+        assert!(to_profile("[quality]\ndust = [{ quality = \"good\" }]").is_err());
+    }
+}
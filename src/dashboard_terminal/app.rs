@@ -1,10 +1,20 @@
+use crate::dashboard_terminal::chart::{next_chart_metric, TimeRange};
 use crate::dashboard_terminal::domain;
+use crate::dashboard_terminal::domain::DeviceSensorData;
+use crate::dashboard_terminal::freshness::FreshnessPolicy;
+use crate::dashboard_terminal::screen::into_io_error;
 use crate::dashboard_terminal::widgets::{
-    dashboard_error, dashboard_loading, dashboard_sensor_data,
+    dashboard_chart, dashboard_error, dashboard_loading, dashboard_sensor_data, help_popup,
 };
+use crate::device::metrics_of_serial_number;
+use crate::shared::aggregation::cache::AggregateCache;
+use crate::shared::storage::day_timezone::DayTimezone;
+use crate::shared::storage::SensorDataStore;
+use crate::shared::types::metric::Metric;
 use crate::shared::types::sensor_data::SensorData;
+use chrono::Utc;
 use crossterm::event;
-use crossterm::event::Event as CEvent;
+use crossterm::event::{Event as CEvent, KeyCode, KeyEvent, KeyModifiers};
 
 use std::path::PathBuf;
 use std::sync::mpsc;
@@ -12,14 +22,34 @@ use std::time::{Duration, Instant};
 use std::{io, thread};
 use tui::backend::Backend;
 
-use crate::dashboard_terminal::app_error::AppErrorResult;
+use crate::dashboard_terminal::app_error::{AppError, AppErrorResult};
 use tui::Terminal;
 
 #[derive(Debug)]
 pub enum AppState {
     Loading,
-    Dashboard(SensorData),
-    Error,
+    Dashboard(Vec<DeviceSensorData>),
+    Error(AppError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DashboardView {
+    CurrentValues,
+    Chart,
+}
+
+impl DashboardView {
+    pub fn next(self) -> Self {
+        match self {
+            DashboardView::CurrentValues => DashboardView::Chart,
+            DashboardView::Chart => DashboardView::CurrentValues,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        // Only two views so far
+        self.next()
+    }
 }
 
 #[derive(Debug)]
@@ -28,16 +58,44 @@ pub struct App<B: Backend> {
     state: AppState,
     tick_countdown_to_fetch_data: u32,
     output_dir_path: PathBuf,
+    store: Box<dyn SensorDataStore>,
+    aggregate_cache: AggregateCache,
+    view: DashboardView,
+    /// Index of the device shown in the detail view
+    selected_device: usize,
+    time_range: TimeRange,
+    chart_metric: Metric,
+    /// Readings of the selected time range. Only loaded in the chart view.
+    history: Vec<SensorData>,
+    freshness_policy: FreshnessPolicy,
+    show_help: bool,
+    should_quit: bool,
 }
 
 impl<B: Backend> App<B> {
-    pub fn new(backend: B, output_dir_path: PathBuf) -> AppErrorResult<Self> {
+    pub fn new(
+        backend: B,
+        output_dir_path: PathBuf,
+        store: Box<dyn SensorDataStore>,
+        freshness_policy: FreshnessPolicy,
+        day_timezone: DayTimezone,
+    ) -> AppErrorResult<Self> {
         let terminal = Terminal::new(backend)?;
         Ok(Self {
             terminal,
             state: AppState::Loading,
             tick_countdown_to_fetch_data: 0,
+            aggregate_cache: AggregateCache::new(&output_dir_path, day_timezone),
             output_dir_path,
+            store,
+            view: DashboardView::CurrentValues,
+            selected_device: 0,
+            time_range: TimeRange::Day,
+            chart_metric: Metric::RadonShortTermAverage,
+            history: Vec::new(),
+            freshness_policy,
+            show_help: false,
+            should_quit: false,
         })
     }
 
@@ -50,13 +108,32 @@ impl<B: Backend> App<B> {
             let mut last_tick = Instant::now();
             loop {
                 // poll for tick rate duration, if no events, sent tick event.
-                if event::poll(tick_rate - last_tick.elapsed()).unwrap() {
-                    if let CEvent::Key(key) = event::read().unwrap() {
-                        tx.send(Event::Input(key)).unwrap();
+                let timeout = tick_rate
+                    .checked_sub(last_tick.elapsed())
+                    .unwrap_or_default();
+                let event = match event::poll(timeout).and_then(|ready| {
+                    if ready {
+                        event::read().map(Some)
+                    } else {
+                        Ok(None)
+                    }
+                }) {
+                    Ok(Some(CEvent::Key(key))) => Some(Ok(key)),
+                    Ok(_) => None,
+                    Err(error) => Some(Err(into_io_error(error))),
+                };
+
+                let input_failed = matches!(event, Some(Err(_)));
+                if let Some(event) = event {
+                    // The receiver is gone when the app has quit
+                    if tx.send(Event::Input(event)).is_err() || input_failed {
+                        return;
                     }
                 }
                 if last_tick.elapsed() >= tick_rate {
-                    tx.send(Event::Tick).unwrap();
+                    if tx.send(Event::Tick).is_err() {
+                        return;
+                    }
                     last_tick = Instant::now();
                 }
             }
@@ -66,52 +143,185 @@ impl<B: Backend> App<B> {
         self.terminal.hide_cursor()?;
 
         loop {
-            match &self.state {
-                AppState::Loading => self
-                    .terminal
-                    .draw(|mut frame| dashboard_loading(&mut frame))?,
-                AppState::Dashboard(sensor_data) => self
-                    .terminal
-                    .draw(|mut frame| dashboard_sensor_data(&mut frame, sensor_data))?,
-                AppState::Error => self
-                    .terminal
-                    .draw(|mut frame| dashboard_error(&mut frame))?,
-            }
+            self.draw()?;
 
-            match rx.recv().unwrap() {
-                Event::Input(event) => match event.code {
-                    // KeyCode::Char('q') => {
-                    //     disable_raw_mode()?;
-                    //     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-                    //     terminal.show_cursor()?;
-                    //     break;
-                    // }
-                    // KeyCode::Char(c) => app.on_key(c),
-                    // KeyCode::Left => app.on_left(),
-                    // KeyCode::Up => app.on_up(),
-                    // KeyCode::Right => app.on_right(),
-                    // KeyCode::Down => app.on_down(),
-                    _ => {}
-                },
-                Event::Tick => {
+            match rx.recv() {
+                Ok(Event::Input(event)) => self.on_key(event?),
+                Ok(Event::Tick) => {
                     self.on_tick();
                 }
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::BrokenPipe,
+                        "The terminal input handling stopped",
+                    ))
+                }
+            }
+
+            if self.should_quit {
+                return Ok(());
             }
         }
     }
 
+    fn draw(&mut self) -> Result<(), io::Error> {
+        let state = &self.state;
+        let view = self.view;
+        let selected = self.selected_device;
+        let history = &self.history;
+        let (metric, time_range) = (self.chart_metric, self.time_range);
+        let show_help = self.show_help;
+        let freshness_policy = &self.freshness_policy;
+
+        self.terminal.draw(|mut frame| {
+            match state {
+                AppState::Loading => dashboard_loading(&mut frame),
+                AppState::Dashboard(devices) => match view {
+                    DashboardView::CurrentValues => {
+                        dashboard_sensor_data(&mut frame, devices, selected, freshness_policy)
+                    }
+                    DashboardView::Chart => dashboard_chart(
+                        &mut frame,
+                        devices,
+                        selected,
+                        freshness_policy,
+                        history,
+                        metric,
+                        time_range,
+                    ),
+                },
+                AppState::Error(error) => dashboard_error(&mut frame, error),
+            }
+
+            if show_help {
+                help_popup(&mut frame);
+            }
+        })
+    }
+
     fn update_data(&mut self) {
-        let data = domain::read_latest_sensor_data_from_directory(&self.output_dir_path);
-
-        if let Ok(sensor_data) = data {
-            self.state = AppState::Dashboard(sensor_data);
-        } else {
-            self.state = AppState::Error;
-            // let debug_sensor_data = SensorData::new(chrono::Utc::now(), 1., 2., 3., 4., 5., 6., 7.);
-            // self.state = AppState::Dashboard(debug_sensor_data)
+        let data = domain::read_latest_sensor_data(self.store.as_ref(), &self.output_dir_path);
+
+        // Keep the same device selected when devices appear or disappear
+        let selected_serial_number = self.selected_serial_number();
+        match data {
+            Ok(devices) => {
+                self.selected_device = devices
+                    .iter()
+                    .position(|device| Some(device.serial_number) == selected_serial_number)
+                    .unwrap_or(0);
+                self.state = AppState::Dashboard(devices);
+            }
+            Err(error) => self.state = AppState::Error(error),
+        }
+
+        if self.view == DashboardView::Chart {
+            self.update_history();
+        }
+    }
+
+    fn update_history(&mut self) {
+        let serial_number = match self.selected_serial_number() {
+            Some(serial_number) => serial_number,
+            None => return,
+        };
+        let since = Utc::now() - self.time_range.duration();
+
+        // Another device may be a model without the sensor of the chart
+        let metrics = metrics_of_serial_number(serial_number);
+        if !metrics.contains(&self.chart_metric) {
+            self.chart_metric = next_chart_metric(self.chart_metric, metrics);
+        }
+
+        match domain::read_sensor_history(
+            self.store.as_ref(),
+            &self.aggregate_cache,
+            serial_number,
+            since,
+            self.time_range.resolution(),
+        ) {
+            Ok(history) => self.history = history,
+            Err(error) => self.state = AppState::Error(error),
+        }
+    }
+
+    fn on_key(&mut self, event: KeyEvent) {
+        let is_ctrl_c =
+            event.code == KeyCode::Char('c') && event.modifiers.contains(KeyModifiers::CONTROL);
+
+        if is_ctrl_c || event.code == KeyCode::Char('q') {
+            self.should_quit = true;
+            return;
+        }
+
+        if self.show_help {
+            // Any key closes the help, Esc included
+            self.show_help = false;
+            return;
+        }
+
+        match event.code {
+            KeyCode::Esc => self.should_quit = true,
+            KeyCode::Char('?') => self.show_help = true,
+            KeyCode::Char('r') => self.refresh(),
+            KeyCode::Tab | KeyCode::Right => self.change_view(self.view.next()),
+            KeyCode::BackTab | KeyCode::Left => self.change_view(self.view.previous()),
+            KeyCode::Down => self.select_device(1),
+            KeyCode::Up => self.select_device(-1),
+            KeyCode::Char('t') if self.view == DashboardView::Chart => {
+                self.time_range = self.time_range.next();
+                self.update_history();
+            }
+            KeyCode::Char('m') if self.view == DashboardView::Chart => {
+                if let Some(serial_number) = self.selected_serial_number() {
+                    self.chart_metric = next_chart_metric(
+                        self.chart_metric,
+                        metrics_of_serial_number(serial_number),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn selected_serial_number(&self) -> Option<u32> {
+        match &self.state {
+            AppState::Dashboard(devices) => devices
+                .get(self.selected_device)
+                .map(|device| device.serial_number),
+            _ => None,
         }
     }
 
+    /// Move the selection by `step` devices, wrapping around at both ends
+    fn select_device(&mut self, step: isize) {
+        let device_count = match &self.state {
+            AppState::Dashboard(devices) if !devices.is_empty() => devices.len() as isize,
+            _ => return,
+        };
+
+        self.selected_device =
+            (self.selected_device as isize + step).rem_euclid(device_count) as usize;
+
+        if self.view == DashboardView::Chart {
+            self.update_history();
+        }
+    }
+
+    fn change_view(&mut self, view: DashboardView) {
+        self.view = view;
+
+        if self.view == DashboardView::Chart {
+            self.update_history();
+        }
+    }
+
+    /// Read the data files now instead of waiting for the next scheduled fetch
+    fn refresh(&mut self) {
+        self.tick_countdown_to_fetch_data = 0;
+        self.on_tick();
+    }
+
     fn on_tick(&mut self) {
         if self.tick_countdown_to_fetch_data == 0 {
             self.update_data();
@@ -1,17 +1,95 @@
-use std::io::Error;
+use crate::shared::storage::error::StorageError;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
 
 pub type AppErrorResult<T> = Result<T, AppError>;
 
-pub struct AppError {}
+#[derive(Debug)]
+pub enum AppError {
+    /// Drawing to or reading input from the terminal failed
+    Terminal(std::io::Error),
+    NoSensorData {
+        data_dir: PathBuf,
+    },
+    DataFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    ParseFailure {
+        path: PathBuf,
+    },
+    Database {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+}
+
+impl<T> From<AppError> for AppErrorResult<T> {
+    fn from(error: AppError) -> Self {
+        Err(error)
+    }
+}
+
+impl AppError {
+    /// Process exit code for the `gui` command
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            AppError::Terminal(_) => 20,
+            AppError::NoSensorData { .. } => 21,
+            AppError::DataFile { .. } => 22,
+            AppError::ParseFailure { .. } => 23,
+            AppError::Database { .. } => 24,
+        }
+    }
+}
+
+impl From<StorageError> for AppError {
+    fn from(error: StorageError) -> Self {
+        match error {
+            StorageError::Io { path, source } => AppError::DataFile { path, source },
+            StorageError::Unreadable { path } => AppError::ParseFailure { path },
+            StorageError::Sqlite { path, source } => AppError::Database { path, source },
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        AppError::Terminal(error)
+    }
+}
 
-impl<T> Into<AppErrorResult<T>> for AppError {
-    fn into(self) -> AppErrorResult<T> {
-        Err(self)
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Terminal(_) => write!(f, "The terminal GUI failed to start/run"),
+            AppError::NoSensorData { data_dir } => write!(
+                f,
+                "No sensor data found in {}. Is the runner writing to this dir?",
+                data_dir.to_string_lossy()
+            ),
+            AppError::DataFile { path, .. } => {
+                write!(f, "Could not read {}", path.to_string_lossy())
+            }
+            AppError::ParseFailure { path } => write!(
+                f,
+                "The latest entry in {} could not be parsed",
+                path.to_string_lossy()
+            ),
+            AppError::Database { path, .. } => {
+                write!(f, "Could not read the database {}", path.to_string_lossy())
+            }
+        }
     }
 }
 
-impl From<Error> for AppError {
-    fn from(_: Error) -> Self {
-        Self {}
+impl Error for AppError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AppError::Terminal(source) | AppError::DataFile { source, .. } => Some(source),
+            AppError::Database { source, .. } => Some(source),
+            AppError::NoSensorData { .. } | AppError::ParseFailure { .. } => None,
+        }
     }
 }
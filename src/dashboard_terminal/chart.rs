@@ -0,0 +1,298 @@
+use crate::shared::aggregation::Resolution;
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::SensorData;
+use crate::shared::types::sensor_quality::SensorQuality;
+use chrono::{DateTime, Duration, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeRange {
+    Day,
+    Week,
+    Month,
+}
+
+impl TimeRange {
+    pub fn duration(self) -> Duration {
+        match self {
+            TimeRange::Day => Duration::hours(24),
+            TimeRange::Week => Duration::days(7),
+            TimeRange::Month => Duration::days(30),
+        }
+    }
+
+    /// Longer ranges are drawn from the cached hourly or daily means, not from every reading
+    pub fn resolution(self) -> Option<Resolution> {
+        match self {
+            TimeRange::Day => None,
+            TimeRange::Week => Some(Resolution::Hourly),
+            TimeRange::Month => Some(Resolution::Daily),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeRange::Day => "24h",
+            TimeRange::Week => "7d",
+            TimeRange::Month => "30d",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            TimeRange::Day => TimeRange::Week,
+            TimeRange::Week => TimeRange::Month,
+            TimeRange::Month => TimeRange::Day,
+        }
+    }
+}
+
+/// The sensors on the dashboard, in the order of the columns. The long-term radon average is
+/// left out, it follows the short-term one.
+pub const DASHBOARD_METRICS: [Metric; 6] = [
+    Metric::RadonShortTermAverage,
+    Metric::Voc,
+    Metric::Co2,
+    Metric::Humidity,
+    Metric::Temperature,
+    Metric::AtmosphericPressure,
+];
+
+/// The next dashboard metric measured by a device with these metrics, the same one if there is
+/// no other
+pub fn next_chart_metric(metric: Metric, metrics: &[Metric]) -> Metric {
+    let position = DASHBOARD_METRICS
+        .iter()
+        .position(|dashboard_metric| *dashboard_metric == metric)
+        .unwrap_or(0);
+
+    DASHBOARD_METRICS
+        .iter()
+        .cycle()
+        .skip(position + 1)
+        .take(DASHBOARD_METRICS.len())
+        .find(|dashboard_metric| metrics.contains(dashboard_metric))
+        .copied()
+        .unwrap_or(metric)
+}
+
+/// A part of the line where all points have the same quality
+#[derive(Debug)]
+pub struct ChartSegment {
+    pub quality: SensorQuality,
+    /// (seconds since the start of the chart, value)
+    pub points: Vec<(f64, f64)>,
+}
+
+#[derive(Debug)]
+pub struct ChartSeries {
+    pub segments: Vec<ChartSegment>,
+    pub x_bounds: [f64; 2],
+    pub y_bounds: [f64; 2],
+}
+
+/// Gaps in the data longer than this (e.g. the runner was stopped) are not connected
+const MIN_GAP_SECONDS: f64 = 30.0 * 60.0;
+
+/// Number of points that fit a chart of this width. Braille markers have two dots per column
+/// and the y labels take 12 columns.
+pub fn max_chart_points(width: u16) -> usize {
+    (width as usize).saturating_sub(12) * 2
+}
+
+/// Turns the history into line segments coloured by quality. The readings are averaged into at
+/// most `max_points` buckets, so a month of readings stays cheap to draw. With a resolution the
+/// history holds the cached means, one per hour or day, and consecutive means are connected.
+pub fn chart_series(
+    history: &[SensorData],
+    metric: Metric,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    max_points: usize,
+    resolution: Option<Resolution>,
+) -> ChartSeries {
+    let range_seconds = (end - start).num_seconds().max(1) as f64;
+    let bucket_seconds = range_seconds / max_points.max(1) as f64;
+    let resolution_gap_seconds =
+        resolution.map_or(0.0, |resolution| 2.0 * resolution.seconds() as f64);
+    let gap_seconds = (bucket_seconds * 3.0)
+        .max(MIN_GAP_SECONDS)
+        .max(resolution_gap_seconds);
+
+    let mut buckets: Vec<(usize, f64, usize)> = Vec::new();
+    for sensor_data in history {
+        let x = (*sensor_data.timestamp() - start).num_seconds() as f64;
+        if x < 0.0 || x > range_seconds {
+            continue;
+        }
+
+        let value = match sensor_data.value(metric) {
+            Some(value) => value as f64,
+            None => continue,
+        };
+        let bucket = (x / bucket_seconds) as usize;
+        match buckets.last_mut() {
+            Some((last_bucket, sum, count)) if *last_bucket == bucket => {
+                *sum += value;
+                *count += 1;
+            }
+            _ => buckets.push((bucket, value, 1)),
+        }
+    }
+
+    let points = buckets
+        .into_iter()
+        .map(|(bucket, sum, count)| ((bucket as f64 + 0.5) * bucket_seconds, sum / count as f64));
+
+    let mut segments: Vec<ChartSegment> = Vec::new();
+    let mut y_bounds = [f64::MAX, f64::MIN];
+    for point in points {
+        y_bounds = [y_bounds[0].min(point.1), y_bounds[1].max(point.1)];
+        let quality = metric.quality(point.1 as f32);
+
+        match segments.last_mut() {
+            Some(segment) => {
+                let previous = *segment.points.last().expect("Segments are never empty");
+                if point.0 - previous.0 > gap_seconds {
+                    segments.push(ChartSegment {
+                        quality,
+                        points: vec![point],
+                    });
+                } else if segment.quality == quality {
+                    segment.points.push(point);
+                } else {
+                    // Start from the previous point so the line stays connected
+                    segments.push(ChartSegment {
+                        quality,
+                        points: vec![previous, point],
+                    });
+                }
+            }
+            None => segments.push(ChartSegment {
+                quality,
+                points: vec![point],
+            }),
+        }
+    }
+
+    ChartSeries {
+        segments,
+        x_bounds: [0.0, range_seconds],
+        y_bounds: padded_bounds(y_bounds),
+    }
+}
+
+fn padded_bounds([min, max]: [f64; 2]) -> [f64; 2] {
+    if min > max {
+        // No data
+        [0.0, 1.0]
+    } else if (max - min).abs() < f64::EPSILON {
+        [min - 1.0, max + 1.0]
+    } else {
+        let padding = (max - min) * 0.1;
+        [min - padding, max + padding]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sensor_data(minutes: i64, co2: f32) -> SensorData {
+        SensorData::test_reading(SensorData::test_day_start() + Duration::minutes(minutes))
+            .with_value(Metric::Co2, co2)
+    }
+
+    #[test]
+    fn test_segments_follow_the_quality() {
+        let start = SensorData::test_day_start();
+        let history = vec![
+            sensor_data(0, 500.0),
+            sensor_data(5, 600.0),
+            sensor_data(10, 900.0),
+            sensor_data(15, 1200.0),
+        ];
+
+        let series = chart_series(
+            &history,
+            Metric::Co2,
+            start,
+            start + Duration::hours(1),
+            12,
+            None,
+        );
+
+        let qualities: Vec<_> = series.segments.iter().map(|s| &s.quality).collect();
+        assert_eq!(
+            qualities,
+            vec![
+                &SensorQuality::Good,
+                &SensorQuality::Bad,
+                &SensorQuality::Terrible
+            ]
+        );
+        assert_eq!(series.segments[0].points.len(), 2);
+        // Connected to the last point of the previous segment
+        assert_eq!(series.segments[1].points[0], series.segments[0].points[1]);
+        assert!(series.y_bounds[0] < 500.0 && series.y_bounds[1] > 1200.0);
+    }
+
+    #[test]
+    fn test_readings_are_averaged_per_bucket_and_gaps_split_the_line() {
+        let start = SensorData::test_day_start();
+        let history = vec![
+            sensor_data(0, 400.0),
+            sensor_data(5, 500.0),
+            sensor_data(300, 600.0),
+        ];
+
+        let series = chart_series(
+            &history,
+            Metric::Co2,
+            start,
+            start + Duration::hours(24),
+            24,
+            None,
+        );
+
+        assert_eq!(series.segments.len(), 2);
+        assert_eq!(series.segments[0].points, vec![(1800.0, 450.0)]);
+        assert_eq!(series.segments[1].points, vec![(5.5 * 3600.0, 600.0)]);
+    }
+
+    #[test]
+    fn test_daily_means_of_a_month_are_connected() {
+        let start = SensorData::test_day_start();
+        let history: Vec<SensorData> = (0..30)
+            .map(|day| sensor_data(day * 24 * 60, 500.0 + day as f32))
+            .collect();
+
+        // An 80 column terminal
+        let series = chart_series(
+            &history,
+            Metric::Co2,
+            start,
+            start + TimeRange::Month.duration(),
+            max_chart_points(80),
+            TimeRange::Month.resolution(),
+        );
+
+        assert_eq!(series.segments.len(), 1);
+        assert_eq!(series.segments[0].points.len(), 30);
+    }
+
+    #[test]
+    fn test_next_metric_skips_the_sensors_of_other_models() {
+        let wave_mini = [Metric::Temperature, Metric::Humidity, Metric::Voc];
+
+        assert_eq!(next_chart_metric(Metric::Voc, &wave_mini), Metric::Humidity);
+        assert_eq!(
+            next_chart_metric(Metric::Temperature, &wave_mini),
+            Metric::Voc
+        );
+        assert_eq!(next_chart_metric(Metric::Voc, &[Metric::Voc]), Metric::Voc);
+        assert_eq!(
+            next_chart_metric(Metric::Co2, Metric::ALL),
+            Metric::Humidity
+        );
+    }
+}
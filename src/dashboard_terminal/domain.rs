@@ -1,35 +1,134 @@
-use crate::runner::error::{RunnerError, RunnerErrorResult};
-use crate::shared::types::sensor_data;
+use crate::dashboard_terminal::app_error::{AppError, AppErrorResult};
+use crate::runner::status::{read_status_file, StatusRecord};
+use crate::shared::aggregation::cache::AggregateCache;
+use crate::shared::aggregation::{Bucket, Resolution};
+use crate::shared::storage::error::StorageError;
+use crate::shared::storage::SensorDataStore;
 use crate::shared::types::sensor_data::SensorData;
-use std::fs::OpenOptions;
-use std::io::Read;
-use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use std::path::Path;
 
-pub fn read_latest_sensor_data_from_directory(data_dir: &PathBuf) -> RunnerErrorResult<SensorData> {
-    let mut paths: Vec<_> = data_dir
-        .read_dir()
-        .unwrap()
-        .map(|entry| entry.unwrap().path())
-        .collect();
+#[derive(Debug)]
+pub struct DeviceSensorData {
+    pub serial_number: u32,
+    pub sensor_data: SensorData,
+    /// The status file the runner writes next to the data files, if there is one
+    pub runner_status: Option<StatusRecord>,
+}
+
+/// The latest reading of every device in the store, ordered by serial number. Devices whose
+/// latest data file has no readable line are left out, unless none can be read.
+pub fn read_latest_sensor_data(
+    store: &dyn SensorDataStore,
+    data_dir: &Path,
+) -> AppErrorResult<Vec<DeviceSensorData>> {
+    let serial_numbers = store.serial_numbers()?;
+    let mut devices = Vec::new();
+    let mut first_failure = None;
+
+    for serial_number in serial_numbers {
+        match store.latest(serial_number) {
+            Ok(Some(sensor_data)) => devices.push(DeviceSensorData {
+                serial_number,
+                sensor_data,
+                runner_status: read_status_file(data_dir, serial_number),
+            }),
+            Ok(None) => {}
+            Err(error @ StorageError::Unreadable { .. }) => {
+                log::warn!("{}", error);
+                first_failure.get_or_insert(AppError::from(error));
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    match first_failure {
+        Some(error) if devices.is_empty() => Err(error),
+        _ if devices.is_empty() => Err(AppError::NoSensorData {
+            data_dir: data_dir.to_path_buf(),
+        }),
+        _ => Ok(devices),
+    }
+}
+
+/// The readings of a device since `since`, or the means of the hourly or daily buckets
+pub fn read_sensor_history(
+    store: &dyn SensorDataStore,
+    aggregate_cache: &AggregateCache,
+    serial_number: u32,
+    since: DateTime<Utc>,
+    resolution: Option<Resolution>,
+) -> AppErrorResult<Vec<SensorData>> {
+    match resolution {
+        None => Ok(store.readings(serial_number, Some(since), None)?),
+        Some(resolution) => Ok(aggregate_cache
+            .buckets(store, serial_number, resolution, Some(since))?
+            .iter()
+            .map(Bucket::to_sensor_data)
+            .collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::storage::csv_store::CsvStore;
+    use crate::shared::storage::day_timezone::DayTimezone;
+    use crate::shared::types::metric::Metric;
+    use chrono::Duration;
+    use std::fs;
+
+    fn write_data_file(data_dir: &Path, serial_number: u32, day: u32, co2: f32) {
+        let midnight = SensorData::test_day_start() + Duration::days(i64::from(day) - 1);
+        let sensor_data =
+            SensorData::test_reading(midnight + Duration::hours(12)).with_value(Metric::Co2, co2);
+
+        fs::write(
+            data_dir.join(format!(
+                "waveplus_data_sn_{}_2020-05-{:02}.txt",
+                serial_number, day
+            )),
+            format!("{}\n", sensor_data.to_csv_with_header(serial_number)),
+        )
+        .unwrap();
+    }
 
-    paths.sort();
-    paths.reverse();
+    #[test]
+    fn test_latest_sensor_data_of_every_device() {
+        let data_dir = tempfile::tempdir().unwrap();
+        write_data_file(data_dir.path(), 2930027508, 1, 400.0);
+        write_data_file(data_dir.path(), 2930027508, 2, 500.0);
+        write_data_file(data_dir.path(), 2930000001, 3, 600.0);
+        fs::write(
+            data_dir
+                .path()
+                .join("waveplus_data_sn_2930000002_2020-05-03.txt"),
+            "",
+        )
+        .unwrap();
 
-    let filepath = paths
-        .first()
-        .ok_or_else(|| RunnerError::new("Latest sensor data file not found".to_owned()))?;
+        let store = CsvStore::new(data_dir.path().to_path_buf(), DayTimezone::Utc);
+        let devices = read_latest_sensor_data(&store, data_dir.path()).unwrap();
 
-    let mut file = OpenOptions::new()
-        .create_new(false)
-        .read(true)
-        .open(filepath)?;
+        let latest: Vec<_> = devices
+            .iter()
+            .map(|device| (device.serial_number, device.sensor_data.value(Metric::Co2)))
+            .collect();
+        assert_eq!(
+            latest,
+            vec![(2930000001, Some(600.0)), (2930027508, Some(500.0))]
+        );
+    }
 
-    let mut sensor_data_raw = "".to_string();
-    file.read_to_string(&mut sensor_data_raw)?;
+    #[test]
+    fn test_no_sensor_data() {
+        let data_dir = tempfile::tempdir().unwrap();
 
-    let sensor_data = sensor_data::latest_entry_from_file(&sensor_data_raw).ok_or_else(|| {
-        RunnerError::new("Latest sensor data not found or parsed (file found)".to_owned())
-    })?;
+        let store = CsvStore::new(data_dir.path().to_path_buf(), DayTimezone::Utc);
 
-    Ok(sensor_data)
+        assert!(matches!(
+            read_latest_sensor_data(&store, data_dir.path()),
+            Err(AppError::NoSensorData { .. })
+        ));
+    }
 }
@@ -0,0 +1,126 @@
+use crate::runner::status::{RunnerStatus, StatusRecord};
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+/// How much the latest reading of a device can be trusted, based on its age
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Freshness {
+    Fresh,
+    /// At least one poll was missed
+    Late,
+    /// Too old to say anything about the air quality now
+    Stale,
+}
+
+#[derive(Debug, Clone)]
+pub struct FreshnessPolicy {
+    poll_interval: Duration,
+    stale_after: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct FreshnessPolicyBuilder {
+    /// The interval the runner polls the devices with
+    pub poll_interval: Duration,
+    pub stale_after: Duration,
+}
+
+impl FreshnessPolicy {
+    pub fn new(builder: FreshnessPolicyBuilder) -> Self {
+        let FreshnessPolicyBuilder {
+            poll_interval,
+            stale_after,
+        } = builder;
+
+        Self {
+            poll_interval,
+            stale_after,
+        }
+    }
+
+    pub fn freshness(&self, reading_time: DateTime<Utc>, now: DateTime<Utc>) -> Freshness {
+        // A reading from the future (clock differences between machines) counts as fresh
+        let age = (now - reading_time).to_std().unwrap_or_default();
+
+        if age > self.stale_after {
+            Freshness::Stale
+        } else if age > self.poll_interval * 2 {
+            Freshness::Late
+        } else {
+            Freshness::Fresh
+        }
+    }
+}
+
+impl From<FreshnessPolicyBuilder> for FreshnessPolicy {
+    fn from(builder: FreshnessPolicyBuilder) -> Self {
+        Self::new(builder)
+    }
+}
+
+/// Why the readings of a device are not coming in, if the runner status file tells or the
+/// data is stale
+pub fn runner_warning(freshness: Freshness, status: Option<&StatusRecord>) -> Option<String> {
+    match status {
+        Some(record) if record.status == RunnerStatus::Stopped => {
+            Some(format!("Runner stopped: {}", record.message))
+        }
+        Some(record) if record.status == RunnerStatus::Failing => Some(format!(
+            "Runner can't read the device ({} failures): {}",
+            record.consecutive_failures, record.message
+        )),
+        _ if freshness == Freshness::Stale => Some("Runner not reporting".to_owned()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn policy() -> FreshnessPolicy {
+        FreshnessPolicyBuilder {
+            poll_interval: Duration::from_secs(5 * 60),
+            stale_after: Duration::from_secs(30 * 60),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_freshness_by_age() {
+        let now = Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0).unwrap();
+        let minutes_ago = |minutes| now - chrono::Duration::minutes(minutes);
+
+        assert_eq!(policy().freshness(minutes_ago(-2), now), Freshness::Fresh);
+        assert_eq!(policy().freshness(minutes_ago(10), now), Freshness::Fresh);
+        assert_eq!(policy().freshness(minutes_ago(11), now), Freshness::Late);
+        assert_eq!(policy().freshness(minutes_ago(30), now), Freshness::Late);
+        assert_eq!(policy().freshness(minutes_ago(31), now), Freshness::Stale);
+        assert_eq!(
+            policy().freshness(minutes_ago(3 * 24 * 60), now),
+            Freshness::Stale
+        );
+    }
+
+    #[test]
+    fn test_runner_warning() {
+        let record = StatusRecord {
+            timestamp: Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0).unwrap(),
+            status: RunnerStatus::Failing,
+            consecutive_failures: 3,
+            next_attempt: None,
+            message: "Could not find device".to_owned(),
+        };
+
+        assert_eq!(runner_warning(Freshness::Fresh, None), None);
+        assert_eq!(
+            runner_warning(Freshness::Stale, None).as_deref(),
+            Some("Runner not reporting")
+        );
+        assert_eq!(
+            runner_warning(Freshness::Late, Some(&record)).as_deref(),
+            Some("Runner can't read the device (3 failures): Could not find device")
+        );
+    }
+}
@@ -2,19 +2,45 @@ use std::io;
 use tui::backend::CrosstermBackend;
 
 mod app;
-mod app_error;
+pub mod app_error;
+mod chart;
 mod domain;
+pub mod freshness;
+mod screen;
 mod widgets;
 
 use crate::dashboard_terminal::app::App;
 use crate::dashboard_terminal::app_error::AppErrorResult;
+use crate::dashboard_terminal::freshness::FreshnessPolicy;
 
+use crate::shared::storage::day_timezone::DayTimezone;
+use crate::shared::storage::SensorDataStore;
 use std::path::PathBuf;
 
-pub fn start_gui(data_dir_path: PathBuf) -> AppErrorResult<()> {
+/// Show the readings of `store`. The runner status files are read from `data_dir_path`. The
+/// daily means of the charts are those of the days of `day_timezone`.
+pub fn start_gui(
+    data_dir_path: PathBuf,
+    store: Box<dyn SensorDataStore>,
+    freshness_policy: FreshnessPolicy,
+    day_timezone: DayTimezone,
+) -> AppErrorResult<()> {
+    screen::enter_screen()?;
+    screen::restore_screen_on_panic();
+
     let stdout = io::stdout();
     let backend = CrosstermBackend::new(stdout);
-    App::new(backend, data_dir_path)?.run()?;
+    let result = App::new(
+        backend,
+        data_dir_path,
+        store,
+        freshness_policy,
+        day_timezone,
+    )
+    .and_then(|mut app| Ok(app.run()?));
+
+    // Restore the terminal also when the app failed, so the error is readable
+    screen::leave_screen()?;
 
-    Ok(())
+    result
 }
@@ -0,0 +1,38 @@
+use crossterm::cursor::Show;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use crossterm::{execute, ErrorKind};
+use std::io;
+use std::io::Write;
+use std::panic;
+
+/// Raw mode (so keys like Ctrl+C reach the app) on the alternate screen
+pub fn enter_screen() -> io::Result<()> {
+    enable_raw_mode().map_err(into_io_error)?;
+    execute!(io::stdout(), EnterAlternateScreen).map_err(into_io_error)
+}
+
+/// Give the shell back the terminal it had before the dashboard started
+pub fn leave_screen() -> io::Result<()> {
+    disable_raw_mode().map_err(into_io_error)?;
+    execute!(io::stdout(), LeaveAlternateScreen, Show).map_err(into_io_error)
+}
+
+/// Leave the screen before the panic message is printed, otherwise the message is lost on the
+/// alternate screen and the shell stays in raw mode
+pub fn restore_screen_on_panic() {
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        let _ = leave_screen();
+        default_hook(info);
+    }));
+}
+
+pub fn into_io_error(error: ErrorKind) -> io::Error {
+    match error {
+        ErrorKind::IoError(error) => error,
+        error => io::Error::other(error.to_string()),
+    }
+}
@@ -1,3 +1,10 @@
+use crate::dashboard_terminal::app_error::AppError;
+use crate::dashboard_terminal::chart::{
+    chart_series, max_chart_points, TimeRange, DASHBOARD_METRICS,
+};
+use crate::dashboard_terminal::domain::DeviceSensorData;
+use crate::dashboard_terminal::freshness::{runner_warning, Freshness, FreshnessPolicy};
+use crate::shared::types::metric::Metric;
 use crate::shared::types::sensor_data::SensorData;
 use crate::shared::types::sensor_quality::SensorQuality;
 use chrono::{Local, Utc};
@@ -5,7 +12,8 @@ use std::borrow::Cow;
 use tui::backend::Backend;
 use tui::layout::{Constraint, Direction, Layout, Rect};
 use tui::style::{Color, Modifier, Style};
-use tui::widgets::{Block, Borders, Paragraph, Text};
+use tui::symbols::Marker;
+use tui::widgets::{Axis, Block, Borders, Chart, Clear, Dataset, GraphType, Paragraph, Text};
 use tui::Frame;
 
 pub fn dashboard_loading<B: Backend>(frame: &mut Frame<B>) {
@@ -26,11 +34,11 @@ pub fn dashboard_loading<B: Backend>(frame: &mut Frame<B>) {
     frame.render_widget(paragraph, chunks[0]);
 }
 
-pub fn dashboard_error<B: Backend>(frame: &mut Frame<B>) {
+pub fn dashboard_error<B: Backend>(frame: &mut Frame<B>, error: &AppError) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
-        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .constraints([Constraint::Length(4), Constraint::Min(0)].as_ref())
         .split(frame.size());
 
     let block = Block::default()
@@ -38,35 +46,248 @@ pub fn dashboard_error<B: Backend>(frame: &mut Frame<B>) {
         .title_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
         .borders(Borders::ALL);
 
-    let text = [Text::raw(
-        "Got an error. Could probably not read te sensor data",
-    )];
+    let text = [
+        Text::raw("Got an error. Could probably not read the sensor data\n"),
+        Text::styled(error.to_string(), Style::default().fg(Color::Red)),
+    ];
 
     let paragraph = Paragraph::new(text.iter()).block(block).wrap(true);
     frame.render_widget(paragraph, chunks[0]);
 }
 
-pub fn dashboard_sensor_data<B: Backend>(frame: &mut Frame<B>, sensor_data: &SensorData) {
-    let chunks = Layout::default()
+pub fn dashboard_sensor_data<B: Backend>(
+    frame: &mut Frame<B>,
+    devices: &[DeviceSensorData],
+    selected: usize,
+    freshness_policy: &FreshnessPolicy,
+) {
+    let chunks = dashboard_layout(frame);
+    let device = &devices[selected];
+    let freshness = freshness_policy.freshness(*device.sensor_data.timestamp(), Utc::now());
+
+    device_panels(frame, chunks[0], devices, selected, freshness_policy);
+    sensor_data_block(frame, chunks[1], device, freshness == Freshness::Stale);
+    key_hints(
+        frame,
+        chunks[2],
+        "[up/down] device   [tab] charts   [?] help",
+    );
+}
+
+pub fn dashboard_chart<B: Backend>(
+    frame: &mut Frame<B>,
+    devices: &[DeviceSensorData],
+    selected: usize,
+    freshness_policy: &FreshnessPolicy,
+    history: &[SensorData],
+    metric: Metric,
+    time_range: TimeRange,
+) {
+    let chunks = dashboard_layout(frame);
+
+    device_panels(frame, chunks[0], devices, selected, freshness_policy);
+    key_hints(
+        frame,
+        chunks[2],
+        "[up/down] device   [tab] current values   [t] time range   [m] metric   [?] help",
+    );
+
+    let end = Utc::now();
+    let start = end - time_range.duration();
+    let series = chart_series(
+        history,
+        metric,
+        start,
+        end,
+        max_chart_points(chunks[1].width),
+        time_range.resolution(),
+    );
+
+    let datasets: Vec<Dataset> = series
+        .segments
+        .iter()
+        .map(|segment| {
+            Dataset::default()
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(sensor_quality_into_color(&segment.quality)))
+                .data(&segment.points)
+        })
+        .collect();
+
+    let time_format = match time_range {
+        TimeRange::Day => "%H:%M",
+        TimeRange::Week | TimeRange::Month => "%m-%d %H:%M",
+    };
+    let x_labels: Vec<String> = [start, start + (end - start) / 2, end]
+        .iter()
+        .map(|time| time.with_timezone(&Local).format(time_format).to_string())
+        .collect();
+    let [y_min, y_max] = series.y_bounds;
+    let y_labels: Vec<String> = [y_min, (y_min + y_max) / 2.0, y_max]
+        .iter()
+        .map(|value| format!("{:.1}", value))
+        .collect();
+
+    let title = format!(
+        " {} ({}) of {} - last {} ",
+        metric.short_name(),
+        metric.unit(),
+        devices[selected].serial_number,
+        time_range.label()
+    );
+    let chart = Chart::default()
+        .block(Block::default().borders(Borders::ALL).title(&title))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds(series.x_bounds)
+                .labels(&x_labels),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds(series.y_bounds)
+                .labels(&y_labels),
+        )
+        .datasets(&datasets);
+    frame.render_widget(chart, chunks[1]);
+}
+
+pub fn help_popup<B: Backend>(frame: &mut Frame<B>) {
+    let key_bindings = [
+        ("q, Esc, Ctrl+C", "Quit"),
+        ("r", "Read the data files again"),
+        ("Tab, Right", "Next view"),
+        ("Shift+Tab, Left", "Previous view"),
+        ("Up, Down", "Select device"),
+        ("t", "Chart time range (24h, 7d, 30d)"),
+        ("m", "Chart sensor"),
+        ("?", "Show this help"),
+    ];
+
+    let mut text = vec![Text::raw("\n")];
+    for (keys, description) in key_bindings.iter() {
+        text.push(Text::styled(
+            format!(" {: <16}", keys),
+            Style::default().modifier(Modifier::BOLD),
+        ));
+        text.push(Text::raw(format!("{}\n", description)));
+    }
+    text.push(Text::styled(
+        "\n Press any key to close",
+        Style::default().fg(Color::DarkGray),
+    ));
+
+    let area = centered_rect(52, text.len() as u16 / 2 + 4, frame.size());
+    let block = Block::default()
+        .title(" Help ")
+        .title_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
+        .borders(Borders::ALL);
+    let paragraph = Paragraph::new(text.iter()).block(block).wrap(true);
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
+
+/// A rect of the given size in the middle of `area`, shrunk to fit
+fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Device panels, detail view and a line with the key bindings
+fn dashboard_layout<B: Backend>(frame: &mut Frame<B>) -> Vec<Rect> {
+    Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
-        .constraints([Constraint::Length(4), Constraint::Min(0)].as_ref())
-        .split(frame.size());
+        .constraints(
+            [
+                Constraint::Length(6),
+                Constraint::Min(0),
+                Constraint::Length(1),
+            ]
+            .as_ref(),
+        )
+        .split(frame.size())
+}
+
+/// One panel per device side by side, the selected one is highlighted
+fn device_panels<B>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    devices: &[DeviceSensorData],
+    selected: usize,
+    freshness_policy: &FreshnessPolicy,
+) where
+    B: Backend,
+{
+    let now = Utc::now();
+
+    let constraints: Vec<Constraint> = devices
+        .iter()
+        .map(|_| Constraint::Ratio(1, devices.len() as u32))
+        .collect();
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(constraints)
+        .split(area);
 
+    for (index, (device, chunk)) in devices.iter().zip(chunks).enumerate() {
+        let freshness = freshness_policy.freshness(*device.sensor_data.timestamp(), now);
+        device_panel(frame, chunk, device, freshness, index == selected);
+    }
+}
+
+fn device_panel<B>(
+    frame: &mut Frame<B>,
+    area: Rect,
+    device: &DeviceSensorData,
+    freshness: Freshness,
+    selected: bool,
+) where
+    B: Backend,
+{
+    let sensor_data = &device.sensor_data;
+    let title = format!(" {} ", device.serial_number);
+    let border_style = if selected {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
     let block = Block::default()
-        .title(" Air quality dashboard ")
-        .title_style(Style::default().fg(Color::Magenta).modifier(Modifier::BOLD))
+        .title(&title)
+        .title_style(Style::default().modifier(Modifier::BOLD))
+        .border_style(border_style)
         .borders(Borders::ALL);
 
-    let overall_quality = sensor_data.worst_sensor_quality();
+    // Stale data says nothing about the air now, so don't claim a quality for it
+    let overall_quality = if freshness == Freshness::Stale {
+        Text::styled("UNKNOWN", Style::default().fg(Color::DarkGray))
+    } else {
+        let quality = sensor_data.worst_sensor_quality();
+        Text::styled(
+            sensor_quality_into_text(&quality),
+            Style::default().fg(sensor_quality_into_color(&quality)),
+        )
+    };
+    let age_color = match freshness {
+        Freshness::Fresh => Color::Reset,
+        Freshness::Late => Color::Yellow,
+        Freshness::Stale => Color::Red,
+    };
 
-    let text = [
+    let mut text = vec![
         Text::raw("Overall air quality: "),
         Text::raw("[ "),
-        Text::styled(
-            sensor_quality_into_text(&overall_quality),
-            Style::default().fg(sensor_quality_into_color(&overall_quality)),
-        ),
+        overall_quality,
         Text::raw(" ]"),
         Text::raw("\n"),
         Text::raw(format!(
@@ -76,88 +297,116 @@ pub fn dashboard_sensor_data<B: Backend>(frame: &mut Frame<B>, sensor_data: &Sen
                 .with_timezone(&Local)
                 .format("%Y-%m-%d %H:%M")
         )),
-        Text::raw("   "),
-        {
-            let start = sensor_data.timestamp().with_timezone(&Local);
-            let end = Utc::now().with_timezone(&Local);
-            let diff = end - start;
-            Text::raw(format!("[ {} minutes ago ]", diff.num_minutes()))
-        },
+        Text::raw("\n"),
+        Text::styled(
+            format!("[ {} ]", format_age(Utc::now() - *sensor_data.timestamp())),
+            Style::default().fg(age_color),
+        ),
     ];
+    if let Some(warning) = runner_warning(freshness, device.runner_status.as_ref()) {
+        text.push(Text::raw("\n"));
+        text.push(Text::styled(
+            warning,
+            Style::default().fg(Color::Red).modifier(Modifier::BOLD),
+        ));
+    }
 
     let paragraph = Paragraph::new(text.iter()).block(block).wrap(true);
-    frame.render_widget(paragraph, chunks[0]);
+    frame.render_widget(paragraph, area);
+}
 
-    sensor_data_block(frame, chunks[1], sensor_data);
+fn format_age(age: chrono::Duration) -> String {
+    match age.num_minutes() {
+        minutes if minutes < 120 => format!("{} minutes ago", minutes),
+        minutes if minutes < 48 * 60 => format!("{} hours ago", minutes / 60),
+        minutes => format!("{} days ago", minutes / (24 * 60)),
+    }
 }
 
-fn sensor_data_block<B>(frame: &mut Frame<B>, area: Rect, sensor_data: &SensorData)
+fn key_hints<B>(frame: &mut Frame<B>, area: Rect, keys: &str)
 where
     B: Backend,
 {
     let text = [
-        Text::raw("\n"),
-        sensor_item_heading("RADON"),
-        sensor_item_heading("TVOC"),
-        sensor_item_heading("CO2"),
-        sensor_item_heading("HUMIDITY"),
-        sensor_item_heading("TEMP"),
-        sensor_item_heading("PRESSURE"),
-        Text::raw("\n"),
-        sensor_item_value(
-            &sensor_data.radon_short_term_average(),
-            "Bq/m3",
-            sensor_data.radon_short_term_quality(),
-        ),
-        sensor_item_value(&sensor_data.voc(), "ppb", sensor_data.voc_quality()),
-        sensor_item_value(&sensor_data.co2(), "ppm", sensor_data.co2_quality()),
-        sensor_item_value(
-            &sensor_data.humidity_in_percent(),
-            "%",
-            sensor_data.humidity_quality(),
-        ),
-        sensor_item_value(
-            &sensor_data.temperature_in_celsius(),
-            "C",
-            sensor_data.temperature_quality(),
-        ),
-        sensor_item_value(
-            &sensor_data.atmospheric_pressure(),
-            "mbar",
-            sensor_data.atmospheric_pressure_quality(),
+        Text::styled(
+            " Air quality dashboard ",
+            Style::default().fg(Color::Magenta).modifier(Modifier::BOLD),
         ),
-        Text::raw("\n"),
-        sensor_item_quality(sensor_data.radon_short_term_quality()),
-        sensor_item_quality(sensor_data.voc_quality()),
-        sensor_item_quality(sensor_data.co2_quality()),
-        sensor_item_quality(sensor_data.humidity_quality()),
-        sensor_item_quality(sensor_data.temperature_quality()),
-        sensor_item_quality(sensor_data.atmospheric_pressure_quality()),
+        Text::styled(format!("  {}", keys), Style::default().fg(Color::DarkGray)),
     ];
-    let block = Block::default()
-        .borders(Borders::ALL)
-        .title(" Individual sensor data ");
+
+    let paragraph = Paragraph::new(text.iter());
+    frame.render_widget(paragraph, area);
+}
+
+/// Stale values are dimmed instead of coloured by their quality. Only the sensors the model of
+/// the device has get a column.
+fn sensor_data_block<B>(frame: &mut Frame<B>, area: Rect, device: &DeviceSensorData, stale: bool)
+where
+    B: Backend,
+{
+    let sensor_data = &device.sensor_data;
+    let items: Vec<(Metric, f32, SensorQuality)> = DASHBOARD_METRICS
+        .iter()
+        .filter_map(|&metric| {
+            let value = sensor_data.value(metric)?;
+            Some((metric, value, metric.quality(value)))
+        })
+        .collect();
+
+    let mut text = vec![Text::raw("\n")];
+    text.extend(
+        items
+            .iter()
+            .map(|(metric, ..)| sensor_item_heading(metric.short_name())),
+    );
+    text.push(Text::raw("\n"));
+    text.extend(
+        items.iter().map(|&(metric, value, quality)| {
+            sensor_item_value(&value, metric.unit(), quality, stale)
+        }),
+    );
+    text.push(Text::raw("\n"));
+    text.extend(
+        items
+            .iter()
+            .map(|&(.., quality)| sensor_item_quality(quality, stale)),
+    );
+    let title = if stale {
+        format!(
+            " Individual sensor data of {} (stale) ",
+            device.serial_number
+        )
+    } else {
+        format!(" Individual sensor data of {} ", device.serial_number)
+    };
+    let block = Block::default().borders(Borders::ALL).title(&title);
     let paragraph = Paragraph::new(text.iter()).block(block).wrap(true);
     frame.render_widget(paragraph, area);
 }
 
-fn sensor_item_heading(heading: &str) -> Text {
+fn sensor_item_heading(heading: &str) -> Text<'_> {
     Text::Styled(
         Cow::from(format!("| {: ^10} |", heading)),
         Style::default().modifier(Modifier::BOLD),
     )
 }
 
-fn sensor_item_value<'a>(value: &f32, unit: &str, sensor_quality: SensorQuality) -> Text<'a> {
+fn sensor_item_value<'a>(
+    value: &f32,
+    unit: &str,
+    sensor_quality: SensorQuality,
+    stale: bool,
+) -> Text<'a> {
     Text::Styled(
         Cow::from(format!("| {: ^10} |", format!("{} {}", value, unit))),
         Style::default()
             .modifier(Modifier::BOLD)
-            .fg(sensor_quality_into_color(&sensor_quality)),
+            .fg(sensor_item_color(&sensor_quality, stale)),
     )
 }
 
-fn sensor_item_quality<'a>(sensor_quality: SensorQuality) -> Text<'a> {
+fn sensor_item_quality<'a>(sensor_quality: SensorQuality, stale: bool) -> Text<'a> {
     Text::Styled(
         Cow::from(format!(
             "| {: ^10} |",
@@ -165,11 +414,19 @@ fn sensor_item_quality<'a>(sensor_quality: SensorQuality) -> Text<'a> {
         )),
         Style::default()
             .modifier(Modifier::BOLD)
-            .fg(sensor_quality_into_color(&sensor_quality)),
+            .fg(sensor_item_color(&sensor_quality, stale)),
     )
 }
 
-fn sensor_quality_into_text(sensor_quality: &SensorQuality) -> &str {
+fn sensor_item_color(sensor_quality: &SensorQuality, stale: bool) -> Color {
+    if stale {
+        Color::DarkGray
+    } else {
+        sensor_quality_into_color(sensor_quality)
+    }
+}
+
+fn sensor_quality_into_text(sensor_quality: &SensorQuality) -> &'static str {
     match *sensor_quality {
         SensorQuality::Good => "GOOD",
         SensorQuality::Bad => "BAD",
@@ -0,0 +1,69 @@
+use std::error::Error;
+use std::fmt;
+
+pub type DecodeResult<T> = Result<T, DecodeError>;
+
+/// A characteristic value that doesn't match the layout of the device model
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    /// Not one value per characteristic of the model
+    ValueCount {
+        expected: usize,
+        actual: usize,
+    },
+    Length {
+        model: &'static str,
+        expected: usize,
+        raw_data: Vec<u8>,
+    },
+    TooShort {
+        model: &'static str,
+        min_length: usize,
+        raw_data: Vec<u8>,
+    },
+    UnknownVersion {
+        model: &'static str,
+        version: u8,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::ValueCount { expected, actual } => write!(
+                f,
+                "expected {} characteristic values, got {}",
+                expected, actual
+            ),
+            DecodeError::Length {
+                model,
+                expected,
+                raw_data,
+            } => write!(
+                f,
+                "expected {} bytes from the {}, got {}: {:?}",
+                expected,
+                model,
+                raw_data.len(),
+                raw_data
+            ),
+            DecodeError::TooShort {
+                model,
+                min_length,
+                raw_data,
+            } => write!(
+                f,
+                "expected at least {} bytes from the {}, got {}: {:?}",
+                min_length,
+                model,
+                raw_data.len(),
+                raw_data
+            ),
+            DecodeError::UnknownVersion { model, version } => {
+                write!(f, "unknown {} sensor version: {}", model, version)
+            }
+        }
+    }
+}
+
+impl Error for DecodeError {}
@@ -1 +1,151 @@
-// pub const AIRTINGS_WAVE_PLUS_UUID: &str = "b42e2a68-ade7-11e4-89d3-123b93f75cba";
+use crate::device::error::{DecodeError, DecodeResult};
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::SensorData;
+use chrono::{DateTime, Utc};
+use std::convert::TryInto;
+use std::fmt;
+
+pub mod error;
+mod view_plus;
+mod wave;
+mod wave_mini;
+mod wave_plus;
+
+pub use view_plus::ViewPlus;
+pub use wave::Wave;
+pub use wave_mini::WaveMini;
+pub use wave_plus::WavePlus;
+
+/// Radon values above this are reported by the devices when no measurement is available yet
+const RADON_MAX_VALID_VALUE: u16 = 16383;
+
+/// An Airthings model. The model of a device is told by the first four digits of its serial
+/// number.
+pub trait Device: fmt::Debug + Sync {
+    fn name(&self) -> &'static str;
+
+    /// First four digits of the serial numbers of the model, e.g. `2930` for `2930027508`
+    fn serial_number_prefix(&self) -> u32;
+
+    /// The metrics the model measures, the others are `None` in its readings
+    fn metrics(&self) -> &'static [Metric];
+
+    /// GATT characteristics to read for a reading
+    fn characteristic_uuids(&self) -> &'static [&'static str];
+
+    /// Decode the values of the characteristics, in the order of `characteristic_uuids`
+    fn decode(&self, timestamp: DateTime<Utc>, values: &[Vec<u8>]) -> DecodeResult<SensorData>;
+
+    /// File name prefix of the daily data files: `<prefix><serial>_<date>.txt`
+    fn file_prefix(&self) -> &'static str;
+}
+
+pub const DEVICES: [&dyn Device; 4] = [&Wave, &WaveMini, &WavePlus, &ViewPlus];
+
+pub fn device_of_serial_number(serial_number: u32) -> Option<&'static dyn Device> {
+    let prefix = serial_number / 1_000_000;
+
+    DEVICES
+        .iter()
+        .copied()
+        .find(|device| device.serial_number_prefix() == prefix)
+}
+
+/// The model to read and store the device as. A serial number with an unknown prefix is handled
+/// as a Wave Plus everywhere: it was the only model known before the others were added, and
+/// older versions read and stored every device that way.
+pub fn device_or_wave_plus(serial_number: u32) -> &'static dyn Device {
+    device_of_serial_number(serial_number).unwrap_or(&WavePlus)
+}
+
+pub fn file_prefix_of_serial_number(serial_number: u32) -> &'static str {
+    device_or_wave_plus(serial_number).file_prefix()
+}
+
+pub fn metrics_of_serial_number(serial_number: u32) -> &'static [Metric] {
+    device_or_wave_plus(serial_number).metrics()
+}
+
+fn single_value<'a>(
+    values: &'a [Vec<u8>],
+    model: &'static str,
+    expected: usize,
+) -> DecodeResult<&'a [u8]> {
+    let raw_data = only_value(values)?;
+
+    check_length(model, raw_data, expected)?;
+    Ok(raw_data)
+}
+
+fn only_value(values: &[Vec<u8>]) -> DecodeResult<&[u8]> {
+    match values {
+        [raw_data] => Ok(raw_data),
+        _ => Err(DecodeError::ValueCount {
+            expected: 1,
+            actual: values.len(),
+        }),
+    }
+}
+
+fn check_length(model: &'static str, raw_data: &[u8], expected: usize) -> DecodeResult<()> {
+    if raw_data.len() != expected {
+        return Err(DecodeError::Length {
+            model,
+            expected,
+            raw_data: raw_data.to_vec(),
+        });
+    }
+
+    Ok(())
+}
+
+/// For layouts with unused values at the end, which may be cut short or extended
+fn check_min_length(model: &'static str, raw_data: &[u8], min_length: usize) -> DecodeResult<()> {
+    if raw_data.len() < min_length {
+        return Err(DecodeError::TooShort {
+            model,
+            min_length,
+            raw_data: raw_data.to_vec(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Little endian u16 at the byte offset, the length has to be checked before
+fn read_u16(raw_data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(raw_data[offset..offset + 2].try_into().unwrap())
+}
+
+/// `None` when the device has no radon measurement yet
+fn radon_value(raw_value: u16) -> Option<f32> {
+    if raw_value > RADON_MAX_VALID_VALUE {
+        return None;
+    }
+
+    Some(f32::from(raw_value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_device_of_serial_number() {
+        let name_of = |serial_number| device_of_serial_number(serial_number).map(|d| d.name());
+
+        assert_eq!(name_of(2900012345), Some("Wave"));
+        assert_eq!(name_of(2920012345), Some("Wave Mini"));
+        assert_eq!(name_of(2930027508), Some("Wave Plus"));
+        assert_eq!(name_of(2960012345), Some("View Plus"));
+        assert_eq!(name_of(2950012345), None);
+
+        assert_eq!(
+            file_prefix_of_serial_number(2920012345),
+            "wavemini_data_sn_"
+        );
+        assert_eq!(file_prefix_of_serial_number(1), "waveplus_data_sn_");
+        assert_eq!(metrics_of_serial_number(2920012345).len(), 3);
+        assert_eq!(device_or_wave_plus(2950012345).name(), "Wave Plus");
+    }
+}
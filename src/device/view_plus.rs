@@ -0,0 +1,40 @@
+use crate::device::error::DecodeResult;
+use crate::device::wave_plus::{decode_current_values, CURRENT_VALUES_UUID, PAYLOAD_LENGTH};
+use crate::device::{single_value, Device};
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::SensorData;
+use chrono::{DateTime, Utc};
+
+/// Read through the current values characteristic it shares with the Wave Plus, so it has the
+/// same metrics. The particulate matter values (PM1, PM2.5) of the View Plus aren't part of that
+/// characteristic and are not read, the README lists this gap.
+#[derive(Debug, Clone, Copy)]
+pub struct ViewPlus;
+
+impl Device for ViewPlus {
+    fn name(&self) -> &'static str {
+        "View Plus"
+    }
+
+    fn serial_number_prefix(&self) -> u32 {
+        2960
+    }
+
+    fn metrics(&self) -> &'static [Metric] {
+        Metric::ALL
+    }
+
+    fn characteristic_uuids(&self) -> &'static [&'static str] {
+        &[CURRENT_VALUES_UUID]
+    }
+
+    fn decode(&self, timestamp: DateTime<Utc>, values: &[Vec<u8>]) -> DecodeResult<SensorData> {
+        let raw_data = single_value(values, self.name(), PAYLOAD_LENGTH)?;
+
+        decode_current_values(self.name(), timestamp, raw_data)
+    }
+
+    fn file_prefix(&self) -> &'static str {
+        "viewplus_data_sn_"
+    }
+}
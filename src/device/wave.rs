@@ -0,0 +1,114 @@
+use crate::device::error::{DecodeError, DecodeResult};
+use crate::device::{check_length, radon_value, read_u16, Device};
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::{SensorData, SensorDataBuilder};
+use chrono::{DateTime, Utc};
+
+/// Standard GATT temperature characteristic, i16 in degrees Celsius x100
+const TEMPERATURE_UUID: &str = "00002a6e-0000-1000-8000-00805f9b34fb";
+/// Standard GATT humidity characteristic, u16 in percent x100
+const HUMIDITY_UUID: &str = "00002a6f-0000-1000-8000-00805f9b34fb";
+const RADON_SHORT_TERM_AVERAGE_UUID: &str = "b42e01aa-ade7-11e4-89d3-123b93f75cba";
+const RADON_LONG_TERM_AVERAGE_UUID: &str = "b42e0a4c-ade7-11e4-89d3-123b93f75cba";
+
+/// The first generation Wave, which has a characteristic per sensor instead of a packed struct
+#[derive(Debug, Clone, Copy)]
+pub struct Wave;
+
+impl Device for Wave {
+    fn name(&self) -> &'static str {
+        "Wave"
+    }
+
+    fn serial_number_prefix(&self) -> u32 {
+        2900
+    }
+
+    fn metrics(&self) -> &'static [Metric] {
+        &[
+            Metric::Temperature,
+            Metric::Humidity,
+            Metric::RadonShortTermAverage,
+            Metric::RadonLongTermAverage,
+        ]
+    }
+
+    fn characteristic_uuids(&self) -> &'static [&'static str] {
+        &[
+            TEMPERATURE_UUID,
+            HUMIDITY_UUID,
+            RADON_SHORT_TERM_AVERAGE_UUID,
+            RADON_LONG_TERM_AVERAGE_UUID,
+        ]
+    }
+
+    fn decode(&self, timestamp: DateTime<Utc>, values: &[Vec<u8>]) -> DecodeResult<SensorData> {
+        let (temperature, humidity, radon_short_term, radon_long_term) = match values {
+            [temperature, humidity, radon_short_term, radon_long_term] => {
+                (temperature, humidity, radon_short_term, radon_long_term)
+            }
+            _ => {
+                return Err(DecodeError::ValueCount {
+                    expected: self.characteristic_uuids().len(),
+                    actual: values.len(),
+                })
+            }
+        };
+        for raw_data in values {
+            check_length(self.name(), raw_data, 2)?;
+        }
+
+        let mut values = vec![
+            (
+                Metric::Temperature,
+                f32::from(read_u16(temperature, 0) as i16) / 100.0,
+            ),
+            (Metric::Humidity, f32::from(read_u16(humidity, 0)) / 100.0),
+        ];
+        values.extend(
+            radon_value(read_u16(radon_short_term, 0))
+                .map(|radon| (Metric::RadonShortTermAverage, radon)),
+        );
+        values.extend(
+            radon_value(read_u16(radon_long_term, 0))
+                .map(|radon| (Metric::RadonLongTermAverage, radon)),
+        );
+
+        Ok(SensorDataBuilder { timestamp, values }.into())
+    }
+
+    fn file_prefix(&self) -> &'static str {
+        "wave_data_sn_"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_wave_characteristics() {
+        let values = [
+            (-150i16).to_le_bytes().to_vec(),
+            4210u16.to_le_bytes().to_vec(),
+            35u16.to_le_bytes().to_vec(),
+            48u16.to_le_bytes().to_vec(),
+        ];
+
+        let sensor_data = Wave.decode(Utc::now(), &values).unwrap();
+
+        assert_eq!(sensor_data.value(Metric::Temperature), Some(-1.5));
+        assert_eq!(sensor_data.value(Metric::Humidity), Some(42.1));
+        assert_eq!(sensor_data.value(Metric::RadonShortTermAverage), Some(35.0));
+        assert_eq!(sensor_data.value(Metric::RadonLongTermAverage), Some(48.0));
+        assert_eq!(sensor_data.value(Metric::Voc), None);
+
+        assert!(matches!(
+            Wave.decode(Utc::now(), &values[..3]),
+            Err(DecodeError::ValueCount {
+                expected: 4,
+                actual: 3
+            })
+        ));
+    }
+}
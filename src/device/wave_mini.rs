@@ -0,0 +1,97 @@
+use crate::device::error::DecodeResult;
+use crate::device::{check_min_length, only_value, read_u16, Device};
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::{SensorData, SensorDataBuilder};
+use chrono::{DateTime, Utc};
+
+/// GATT characteristic holding the packed current values of the Wave Mini
+const CURRENT_VALUES_UUID: &str = "b42e3b98-ade7-11e4-89d3-123b93f75cba";
+
+/// Packed little endian struct: 6 x u16 followed by 3 x u32, 24 bytes. Only the u16 values are
+/// read, so the u32 values at the end are not required.
+const MIN_PAYLOAD_LENGTH: usize = 12;
+
+/// Offset of the Kelvin scale, the Wave Mini sends the temperature in Kelvin
+const ZERO_CELSIUS_IN_KELVIN: f32 = 273.15;
+
+#[derive(Debug, Clone, Copy)]
+pub struct WaveMini;
+
+impl Device for WaveMini {
+    fn name(&self) -> &'static str {
+        "Wave Mini"
+    }
+
+    fn serial_number_prefix(&self) -> u32 {
+        2920
+    }
+
+    fn metrics(&self) -> &'static [Metric] {
+        &[Metric::Temperature, Metric::Humidity, Metric::Voc]
+    }
+
+    fn characteristic_uuids(&self) -> &'static [&'static str] {
+        &[CURRENT_VALUES_UUID]
+    }
+
+    /// Layout (`<HHHHHHLLL`): unused, temperature (Kelvin x100), unused, humidity (x100), VOC
+    /// and the rest unused
+    fn decode(&self, timestamp: DateTime<Utc>, values: &[Vec<u8>]) -> DecodeResult<SensorData> {
+        let raw_data = only_value(values)?;
+        check_min_length(self.name(), raw_data, MIN_PAYLOAD_LENGTH)?;
+        let read_u16 = |index: usize| read_u16(raw_data, index * 2);
+
+        Ok(SensorDataBuilder {
+            timestamp,
+            values: vec![
+                (
+                    Metric::Temperature,
+                    f32::from(read_u16(1)) / 100.0 - ZERO_CELSIUS_IN_KELVIN,
+                ),
+                (Metric::Humidity, f32::from(read_u16(3)) / 100.0),
+                (Metric::Voc, f32::from(read_u16(4))),
+            ],
+        }
+        .into())
+    }
+
+    fn file_prefix(&self) -> &'static str {
+        "wavemini_data_sn_"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::error::DecodeError;
+
+    /// 295.73 K, 35.5 % and 120 ppb in the `<HHHHHHLLL` layout, with values in the unused fields
+    const PAYLOAD: [u8; 24] = [
+        0x01, 0x00, 0x85, 0x73, 0xff, 0x00, 0xde, 0x0d, 0x78, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x78, 0x56, 0x34, 0x12, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_decode_wave_mini_current_values() {
+        let sensor_data = WaveMini.decode(Utc::now(), &[PAYLOAD.to_vec()]).unwrap();
+
+        assert!((sensor_data.value(Metric::Temperature).unwrap() - 22.58).abs() < 0.01);
+        assert_eq!(sensor_data.value(Metric::Humidity), Some(35.5));
+        assert_eq!(sensor_data.value(Metric::Voc), Some(120.0));
+        assert_eq!(sensor_data.value(Metric::Co2), None);
+        assert_eq!(sensor_data.value(Metric::RadonShortTermAverage), None);
+    }
+
+    #[test]
+    fn test_decode_without_the_unused_values_at_the_end() {
+        let sensor_data = WaveMini
+            .decode(Utc::now(), &[PAYLOAD[..20].to_vec()])
+            .unwrap();
+        assert_eq!(sensor_data.value(Metric::Voc), Some(120.0));
+
+        assert!(matches!(
+            WaveMini.decode(Utc::now(), &[PAYLOAD[..10].to_vec()]),
+            Err(DecodeError::TooShort { .. })
+        ));
+    }
+}
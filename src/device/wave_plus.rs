@@ -0,0 +1,138 @@
+use crate::device::error::{DecodeError, DecodeResult};
+use crate::device::{radon_value, read_u16, single_value, Device};
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::{SensorData, SensorDataBuilder};
+use chrono::{DateTime, Utc};
+
+pub const FILE_PREFIX: &str = "waveplus_data_sn_";
+
+/// GATT characteristic holding the packed current values of the Wave Plus
+pub const CURRENT_VALUES_UUID: &str = "b42e2a68-ade7-11e4-89d3-123b93f75cba";
+
+/// The only layout version of the current values struct the Wave Plus is known to send
+const SENSOR_VERSION: u8 = 1;
+/// Packed little endian struct: 4 x u8 followed by 8 x u16
+pub const PAYLOAD_LENGTH: usize = 20;
+
+#[derive(Debug, Clone, Copy)]
+pub struct WavePlus;
+
+impl Device for WavePlus {
+    fn name(&self) -> &'static str {
+        "Wave Plus"
+    }
+
+    fn serial_number_prefix(&self) -> u32 {
+        2930
+    }
+
+    fn metrics(&self) -> &'static [Metric] {
+        Metric::ALL
+    }
+
+    fn characteristic_uuids(&self) -> &'static [&'static str] {
+        &[CURRENT_VALUES_UUID]
+    }
+
+    fn decode(&self, timestamp: DateTime<Utc>, values: &[Vec<u8>]) -> DecodeResult<SensorData> {
+        let raw_data = single_value(values, self.name(), PAYLOAD_LENGTH)?;
+
+        decode_current_values(self.name(), timestamp, raw_data)
+    }
+
+    fn file_prefix(&self) -> &'static str {
+        FILE_PREFIX
+    }
+}
+
+/// Decode the "current values" characteristic of the Wave Plus, also sent by the View Plus.
+///
+/// Layout (`<BBBBHHHHHHHH`): version, humidity (x2), 2 x unused, radon short-term average,
+/// radon long-term average, temperature (x100), pressure (x50), CO2, VOC and 2 x unused.
+pub fn decode_current_values(
+    model: &'static str,
+    timestamp: DateTime<Utc>,
+    raw_data: &[u8],
+) -> DecodeResult<SensorData> {
+    let version = raw_data[0];
+    if version != SENSOR_VERSION {
+        return Err(DecodeError::UnknownVersion { model, version });
+    }
+
+    let read_u16 = |index: usize| read_u16(raw_data, 4 + index * 2);
+
+    let mut values = vec![
+        (Metric::Humidity, f32::from(raw_data[1]) / 2.0),
+        (Metric::Temperature, f32::from(read_u16(2)) / 100.0),
+        (Metric::AtmosphericPressure, f32::from(read_u16(3)) / 50.0),
+        (Metric::Co2, f32::from(read_u16(4))),
+        (Metric::Voc, f32::from(read_u16(5))),
+    ];
+    // Right after a reset the device has no radon values yet, the rest of the reading is fine
+    values.extend(radon_value(read_u16(0)).map(|radon| (Metric::RadonShortTermAverage, radon)));
+    values.extend(radon_value(read_u16(1)).map(|radon| (Metric::RadonLongTermAverage, radon)));
+
+    Ok(SensorDataBuilder { timestamp, values }.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Recorded from a Wave Plus with serial number 2930027508
+    const RECORDED_PAYLOAD: [u8; 20] = [
+        1, 45, 0, 0, 1, 0, 6, 0, 210, 8, 160, 199, 220, 1, 152, 0, 0, 0, 0, 0,
+    ];
+
+    fn decode(raw_data: &[u8]) -> DecodeResult<SensorData> {
+        WavePlus.decode(Utc::now(), &[raw_data.to_vec()])
+    }
+
+    #[test]
+    fn test_decode_wave_plus_current_values() {
+        let sensor_data = decode(&RECORDED_PAYLOAD).unwrap();
+
+        assert_eq!(sensor_data.value(Metric::Humidity), Some(22.5));
+        assert_eq!(sensor_data.value(Metric::RadonShortTermAverage), Some(1.0));
+        assert_eq!(sensor_data.value(Metric::RadonLongTermAverage), Some(6.0));
+        assert_eq!(sensor_data.value(Metric::Temperature), Some(22.58));
+        assert_eq!(
+            sensor_data.value(Metric::AtmosphericPressure),
+            Some(1022.08)
+        );
+        assert_eq!(sensor_data.value(Metric::Co2), Some(476.0));
+        assert_eq!(sensor_data.value(Metric::Voc), Some(152.0));
+    }
+
+    #[test]
+    fn test_decode_wave_plus_without_radon_values() {
+        // Radon short-term average of 0xffff right after the device has been reset
+        let mut radon_not_available = RECORDED_PAYLOAD;
+        radon_not_available[4] = 0xff;
+        radon_not_available[5] = 0xff;
+
+        let sensor_data = decode(&radon_not_available).unwrap();
+
+        assert_eq!(sensor_data.value(Metric::RadonShortTermAverage), None);
+        assert_eq!(sensor_data.value(Metric::RadonLongTermAverage), Some(6.0));
+        assert_eq!(sensor_data.value(Metric::Co2), Some(476.0));
+    }
+
+    #[test]
+    fn test_decode_wave_plus_rejects_invalid_payloads() {
+        let truncated = &RECORDED_PAYLOAD[..12];
+        assert!(matches!(
+            decode(truncated),
+            Err(DecodeError::Length { expected: 20, .. })
+        ));
+
+        let mut unknown_version = RECORDED_PAYLOAD;
+        unknown_version[0] = 2;
+        assert!(decode(&unknown_version).is_err());
+
+        assert!(matches!(
+            WavePlus.decode(Utc::now(), &[]),
+            Err(DecodeError::ValueCount { .. })
+        ));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::shared::storage::error::StorageError;
+use chrono::NaiveDate;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+pub type ExportErrorResult<T> = Result<T, ExportError>;
+
+#[derive(Debug)]
+pub enum ExportError {
+    Storage(StorageError),
+    /// Writing to the output file, or to stdout when there is no path, failed
+    Output {
+        path: Option<PathBuf>,
+        source: std::io::Error,
+    },
+    Parquet(parquet::errors::ParquetError),
+    /// `--from` is after `--to`
+    InvalidRange {
+        from: NaiveDate,
+        to: NaiveDate,
+    },
+}
+
+impl<T> From<ExportError> for ExportErrorResult<T> {
+    fn from(error: ExportError) -> Self {
+        Err(error)
+    }
+}
+
+impl From<StorageError> for ExportError {
+    fn from(error: StorageError) -> Self {
+        ExportError::Storage(error)
+    }
+}
+
+impl From<parquet::errors::ParquetError> for ExportError {
+    fn from(error: parquet::errors::ParquetError) -> Self {
+        ExportError::Parquet(error)
+    }
+}
+
+impl ExportError {
+    /// Process exit code for the `export` command
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ExportError::Storage(_) => 60,
+            ExportError::Output { .. } => 61,
+            ExportError::Parquet(_) => 62,
+            ExportError::InvalidRange { .. } => 63,
+        }
+    }
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Storage(_) => write!(f, "Could not read the sensor data"),
+            ExportError::Output {
+                path: Some(path), ..
+            } => {
+                write!(f, "Could not write {}", path.to_string_lossy())
+            }
+            ExportError::Output { path: None, .. } => write!(f, "Could not write to stdout"),
+            ExportError::Parquet(_) => write!(f, "Could not write the Parquet file"),
+            ExportError::InvalidRange { from, to } => {
+                write!(f, "The first day {} is after the last day {}", from, to)
+            }
+        }
+    }
+}
+
+impl Error for ExportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ExportError::Storage(source) => Some(source),
+            ExportError::Output { source, .. } => Some(source),
+            ExportError::Parquet(source) => Some(source),
+            ExportError::InvalidRange { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use crate::export::error::{ExportError, ExportErrorResult};
+use crate::shared::storage::day_timezone::DayTimezone;
+use crate::shared::storage::SensorDataStore;
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::SensorData;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use parquet::basic::Compression;
+use parquet::data_type::{FloatType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Column names of every format, the same as in the SQLite table
+fn columns() -> Vec<&'static str> {
+    let mut columns = vec!["serial_number", "timestamp"];
+    columns.extend(Metric::ALL.iter().map(|metric| metric.key()));
+
+    columns
+}
+
+fn parquet_schema() -> String {
+    let metric_columns: String = Metric::ALL
+        .iter()
+        .map(|metric| format!("OPTIONAL FLOAT {}; ", metric.key()))
+        .collect();
+
+    format!(
+        "message sensor_data {{
+            REQUIRED INT64 serial_number;
+            REQUIRED INT64 timestamp (TIMESTAMP(MILLIS, true));
+            {}
+        }}",
+        metric_columns
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+    Parquet,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "csv" => Ok(ExportFormat::Csv),
+            "jsonl" => Ok(ExportFormat::JsonLines),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(format!(
+                "Unknown export format '{}', expected csv, jsonl or parquet",
+                value
+            )),
+        }
+    }
+}
+
+/// Which readings to export. The days are days in the timezone of the daily data files and
+/// both are included.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportQuery {
+    pub serial_number: Option<u32>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub day_timezone: DayTimezone,
+}
+
+impl ExportQuery {
+    fn since(&self) -> Option<DateTime<Utc>> {
+        self.from.map(|from| self.day_timezone.start_of_day(from))
+    }
+
+    fn until(&self) -> Option<DateTime<Utc>> {
+        self.to
+            .and_then(|to| to.succ_opt())
+            .map(|day_after| self.day_timezone.start_of_day(day_after))
+    }
+}
+
+/// The metrics follow the serial number and the timestamp in the order of `Metric::ALL`
+struct JsonRecord<'a> {
+    serial_number: u32,
+    timestamp: &'a str,
+    sensor_data: &'a SensorData,
+}
+
+impl Serialize for JsonRecord<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2 + Metric::COUNT))?;
+        map.serialize_entry("serial_number", &self.serial_number)?;
+        map.serialize_entry("timestamp", self.timestamp)?;
+        for metric in Metric::ALL.iter() {
+            map.serialize_entry(metric.key(), &self.sensor_data.value(*metric))?;
+        }
+
+        map.end()
+    }
+}
+
+/// Write the readings of the query to the file, or to stdout without a path. The readings are
+/// ordered by device and then by time. Returns the number of exported readings.
+pub fn export_sensor_data(
+    store: &dyn SensorDataStore,
+    query: ExportQuery,
+    format: ExportFormat,
+    output_path: Option<&Path>,
+) -> ExportErrorResult<usize> {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return ExportError::InvalidRange { from, to }.into();
+        }
+    }
+
+    let output_error = |source| ExportError::Output {
+        path: output_path.map(Path::to_path_buf),
+        source,
+    };
+
+    let output: Box<dyn Write + Send> = match output_path {
+        Some(path) => Box::new(File::create(path).map_err(output_error)?),
+        None => Box::new(std::io::stdout()),
+    };
+    let mut output = BufWriter::new(output);
+
+    let serial_numbers = match query.serial_number {
+        Some(serial_number) => vec![serial_number],
+        None => store.serial_numbers()?,
+    };
+
+    let mut count = 0;
+    match format {
+        ExportFormat::Csv | ExportFormat::JsonLines => {
+            if format == ExportFormat::Csv {
+                writeln!(output, "{}", columns().join(",")).map_err(output_error)?;
+            }
+
+            for serial_number in serial_numbers {
+                let readings = store.readings(serial_number, query.since(), query.until())?;
+                for sensor_data in &readings {
+                    let line = match format {
+                        ExportFormat::Csv => csv_line(serial_number, sensor_data),
+                        _ => json_line(serial_number, sensor_data),
+                    };
+                    writeln!(output, "{}", line).map_err(output_error)?;
+                }
+                count += readings.len();
+            }
+            output.flush().map_err(output_error)?;
+        }
+        ExportFormat::Parquet => {
+            let properties = WriterProperties::builder()
+                .set_compression(Compression::SNAPPY)
+                .build();
+            let schema = Arc::new(parse_message_type(&parquet_schema())?);
+            let mut writer = SerializedFileWriter::new(output, schema, Arc::new(properties))?;
+
+            // A row group per device
+            for serial_number in serial_numbers {
+                let readings = store.readings(serial_number, query.since(), query.until())?;
+                if !readings.is_empty() {
+                    write_parquet_row_group(&mut writer, serial_number, &readings)?;
+                }
+                count += readings.len();
+            }
+
+            let mut output = writer.into_inner()?;
+            output.flush().map_err(output_error)?;
+        }
+    }
+
+    Ok(count)
+}
+
+fn rfc3339(sensor_data: &SensorData) -> String {
+    sensor_data
+        .timestamp()
+        .to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn csv_line(serial_number: u32, sensor_data: &SensorData) -> String {
+    let mut columns = vec![serial_number.to_string(), rfc3339(sensor_data)];
+    columns.extend(
+        Metric::ALL
+            .iter()
+            .map(|metric| optional_value(sensor_data.value(*metric))),
+    );
+
+    columns.join(",")
+}
+
+/// Metrics the device doesn't measure are empty
+fn optional_value(value: Option<f32>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn json_line(serial_number: u32, sensor_data: &SensorData) -> String {
+    let timestamp = rfc3339(sensor_data);
+    let record = JsonRecord {
+        serial_number,
+        timestamp: &timestamp,
+        sensor_data,
+    };
+
+    serde_json::to_string(&record).expect("A JSON record can always be serialized")
+}
+
+fn write_parquet_row_group<W: Write + Send>(
+    writer: &mut SerializedFileWriter<W>,
+    serial_number: u32,
+    readings: &[SensorData],
+) -> parquet::errors::Result<()> {
+    let mut row_group = writer.next_row_group()?;
+    let mut column_index = 0;
+    while let Some(mut column) = row_group.next_column()? {
+        match column_index {
+            0 => {
+                let values = vec![serial_number as i64; readings.len()];
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&values, None, None)?;
+            }
+            1 => {
+                let values: Vec<i64> = readings
+                    .iter()
+                    .map(|sensor_data| sensor_data.timestamp().timestamp_millis())
+                    .collect();
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&values, None, None)?;
+            }
+            _ => {
+                // Only the present values are written, a definition level of 0 is a null
+                let metric = Metric::ALL[column_index - 2];
+                let optional_values: Vec<Option<f32>> = readings
+                    .iter()
+                    .map(|sensor_data| sensor_data.value(metric))
+                    .collect();
+                let definition_levels: Vec<i16> = optional_values
+                    .iter()
+                    .map(|value| value.is_some() as i16)
+                    .collect();
+                let values: Vec<f32> = optional_values.into_iter().flatten().collect();
+                column
+                    .typed::<FloatType>()
+                    .write_batch(&values, Some(&definition_levels), None)?;
+            }
+        }
+        column.close()?;
+        column_index += 1;
+    }
+    row_group.close()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::storage::sqlite_store::SqliteStore;
+    use chrono::{Duration, TimeZone};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+    use std::fs;
+
+    fn store_with_readings(dir: &Path) -> SqliteStore {
+        let store = SqliteStore::open(&dir.join("test.sqlite3")).unwrap();
+        for (serial_number, day, co2) in [
+            (2930027508, 1, 400.0),
+            (2930027508, 2, 500.0),
+            (2930027508, 3, 600.0),
+            (2930000001, 2, 700.0),
+        ] {
+            let timestamp =
+                SensorData::test_day_start() + Duration::days(day - 1) + Duration::hours(12);
+            let sensor_data = SensorData::test_reading(timestamp).with_value(Metric::Co2, co2);
+            store.append(serial_number, &sensor_data).unwrap();
+        }
+
+        store
+    }
+
+    fn may_2020(day: u32) -> Option<NaiveDate> {
+        NaiveDate::from_ymd_opt(2020, 5, day)
+    }
+
+    #[test]
+    fn test_csv_with_a_single_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_readings(dir.path());
+        let output_path = dir.path().join("export.csv");
+
+        let query = ExportQuery {
+            serial_number: None,
+            from: may_2020(2),
+            to: may_2020(3),
+            day_timezone: DayTimezone::Utc,
+        };
+        let count =
+            export_sensor_data(&store, query, ExportFormat::Csv, Some(&output_path)).unwrap();
+
+        assert_eq!(count, 3);
+        assert_eq!(
+            fs::read_to_string(&output_path).unwrap(),
+            "serial_number,timestamp,temperature_in_celsius,humidity_in_percent,atmospheric_pressure,co2,voc,radon_short_term_average,radon_long_term_average\n\
+             2930000001,2020-05-02T12:00:00Z,21,40,1000,700,100,20,20\n\
+             2930027508,2020-05-02T12:00:00Z,21,40,1000,500,100,20,20\n\
+             2930027508,2020-05-03T12:00:00Z,21,40,1000,600,100,20,20\n"
+        );
+    }
+
+    #[test]
+    fn test_json_lines_of_one_device() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_readings(dir.path());
+        let output_path = dir.path().join("export.jsonl");
+
+        let query = ExportQuery {
+            serial_number: Some(2930027508),
+            from: None,
+            to: may_2020(1),
+            day_timezone: DayTimezone::Utc,
+        };
+        export_sensor_data(&store, query, ExportFormat::JsonLines, Some(&output_path)).unwrap();
+
+        let content = fs::read_to_string(&output_path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["serial_number"], 2930027508u32);
+        assert_eq!(lines[0]["timestamp"], "2020-05-01T12:00:00Z");
+        assert_eq!(lines[0]["co2"], 400.0);
+    }
+
+    #[test]
+    fn test_days_of_the_day_timezone() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_readings(dir.path());
+        // 00:30 on the 2nd in Oslo
+        let timestamp = SensorData::test_day_start() + Duration::minutes(22 * 60 + 30);
+        store
+            .append(2930027508, &SensorData::test_reading(timestamp))
+            .unwrap();
+        let output_path = dir.path().join("export.csv");
+
+        let query = ExportQuery {
+            serial_number: Some(2930027508),
+            from: may_2020(2),
+            to: may_2020(2),
+            day_timezone: "Europe/Oslo".parse().unwrap(),
+        };
+        let count =
+            export_sensor_data(&store, query, ExportFormat::Csv, Some(&output_path)).unwrap();
+
+        assert_eq!(count, 2);
+        let content = fs::read_to_string(&output_path).unwrap();
+        assert!(content.contains("2020-05-01T22:30:00Z"));
+        assert!(content.contains("2020-05-02T12:00:00Z"));
+    }
+
+    #[test]
+    fn test_refuse_a_range_that_ends_before_it_starts() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_readings(dir.path());
+        let output_path = dir.path().join("export.csv");
+
+        let query = ExportQuery {
+            from: may_2020(3),
+            to: may_2020(2),
+            ..ExportQuery::default()
+        };
+        let result = export_sensor_data(&store, query, ExportFormat::Csv, Some(&output_path));
+
+        assert!(matches!(result, Err(ExportError::InvalidRange { .. })));
+        assert!(!output_path.exists());
+    }
+
+    #[test]
+    fn test_parquet() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_with_readings(dir.path());
+        let output_path = dir.path().join("export.parquet");
+
+        export_sensor_data(
+            &store,
+            ExportQuery::default(),
+            ExportFormat::Parquet,
+            Some(&output_path),
+        )
+        .unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&output_path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 4);
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+
+        let rows: Vec<(i64, i64, f32)> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| {
+                let row = row.unwrap();
+                (
+                    row.get_long(0).unwrap(),
+                    row.get_timestamp_millis(1).unwrap(),
+                    row.get_float(5).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows[1],
+            (
+                2930027508,
+                Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0)
+                    .unwrap()
+                    .timestamp_millis(),
+                400.0
+            )
+        );
+    }
+}
@@ -1,9 +1,9 @@
-use crate::file_sync::types::error::SynchronizeRunnerErrorResult;
+use crate::file_sync::types::error::{SynchronizeRunnerError, SynchronizeRunnerErrorResult};
 use crate::file_sync::types::metadata::FileMetadata;
 use ssh2::{Channel, Session};
 use std::fs::{read_dir, OpenOptions};
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::from_utf8;
 
 pub fn fetch_metadata_remote(
@@ -17,11 +17,10 @@ pub fn fetch_metadata_remote(
 
     let file_metadata = filenames
         .iter()
-        .map(|filename| {
+        .flat_map(|filename| {
             let path = format!("{}/{}", remote_dir_path, filename);
-            fetch_remote_file_metadata(&session, &PathBuf::from(path), filename.to_owned())
+            fetch_remote_file_metadata(session, &PathBuf::from(path), filename.to_owned())
         })
-        .flatten()
         .collect();
 
     Ok(file_metadata)
@@ -31,54 +30,81 @@ pub fn fetch_remote_filenames(
     mut channel: Channel,
     remote_dir_path: &str,
 ) -> SynchronizeRunnerErrorResult<Vec<String>> {
+    let listing_error = |reason: &str, source: Option<Box<dyn std::error::Error + Send + Sync>>| {
+        SynchronizeRunnerError::RemoteListing {
+            remote_dir_path: remote_dir_path.to_owned(),
+            reason: reason.to_owned(),
+            source,
+        }
+    };
+
     let cmd = format!("cd {} && ls", remote_dir_path);
-    channel.exec(&cmd)?;
+    channel
+        .exec(&cmd)
+        .map_err(|err| listing_error("could not run ls", Some(Box::new(err))))?;
     let files_str = {
         let mut files_str = String::new();
-        channel.read_to_string(&mut files_str)?;
+        channel
+            .read_to_string(&mut files_str)
+            .map_err(|err| listing_error("could not read the ls output", Some(Box::new(err))))?;
         files_str
     };
     channel.wait_close()?;
+
+    let exit_status = channel.exit_status()?;
+    if exit_status != 0 {
+        return Err(listing_error(
+            &format!("ls exited with status {}. Does the dir exist?", exit_status),
+            None,
+        ));
+    }
+
     Ok(files_str.lines().map(|s| s.to_owned()).collect())
 }
 
 pub fn download_remote_file(
     session: &Session,
-    path: &PathBuf,
+    path: &Path,
 ) -> SynchronizeRunnerErrorResult<Vec<u8>> {
-    let (mut remote_file, _stat) = session.scp_recv(path)?;
+    let download_error =
+        |source: Box<dyn std::error::Error + Send + Sync>| SynchronizeRunnerError::Download {
+            remote_path: path.to_string_lossy().into_owned(),
+            source,
+        };
+
+    let (mut remote_file, _stat) = session
+        .scp_recv(path)
+        .map_err(|err| download_error(Box::new(err)))?;
     let mut content_buffer = Vec::new();
-    remote_file.read_to_end(&mut content_buffer)?;
+    remote_file
+        .read_to_end(&mut content_buffer)
+        .map_err(|err| download_error(Box::new(err)))?;
     Ok(content_buffer)
 }
 
 pub fn fetch_remote_file_metadata(
     session: &Session,
-    path: &PathBuf,
+    path: &Path,
     file_name: String,
 ) -> SynchronizeRunnerErrorResult<FileMetadata> {
     let (mut remote_file, _stat) = session.scp_recv(path)?;
     let mut content_buffer = Vec::new();
-    let content = {
-        remote_file.read_to_end(&mut content_buffer)?;
-        let content = from_utf8(&content_buffer)?;
-        content
-    };
+    remote_file.read_to_end(&mut content_buffer)?;
+    let content = from_utf8(&content_buffer)?;
     FileMetadata::from_content(content, file_name)
 }
 
 pub fn fetch_metadata_local(
-    local_dir_path: &PathBuf,
+    local_dir_path: &Path,
 ) -> SynchronizeRunnerErrorResult<Vec<FileMetadata>> {
     let files = read_dir(local_dir_path)?;
 
     let metadata = files
-        .map(|file| {
+        .flat_map(|file| {
             let dir_entry = file?;
 
             FileMetadata::from_dir_entry(dir_entry)
         })
-        .flatten()
         .collect();
 
     Ok(metadata)
@@ -87,7 +113,7 @@ pub fn fetch_metadata_local(
 pub fn sync_remote_files_to_local(
     session: &Session,
     remote_dir_path: &str,
-    local_dir_path: &PathBuf,
+    local_dir_path: &Path,
     file_names: &[String],
 ) -> SynchronizeRunnerErrorResult<()> {
     for file_name in file_names {
@@ -98,6 +124,7 @@ pub fn sync_remote_files_to_local(
         OpenOptions::new()
             .create(true)
             .write(true)
+            .truncate(true)
             .open(local_path)?
             .write_all(&file_content)?;
     }
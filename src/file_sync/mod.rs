@@ -1,3 +1,3 @@
+pub mod file_service;
 pub mod synchronize;
 pub mod types;
-pub mod file_service;
\ No newline at end of file
@@ -3,6 +3,7 @@ use crate::file_sync::file_service::{
 };
 use crate::file_sync::types::error::{SynchronizeRunnerError, SynchronizeRunnerErrorResult};
 use crate::file_sync::types::metadata::reduce_remote_metadata_list_to_modified_or_not_exist_local;
+use crate::shared::aggregation::cache::AggregateCache;
 use chrono::Local;
 use ssh2::Session;
 use std::net::TcpStream;
@@ -77,7 +78,7 @@ impl SynchronizeRunner {
 
     fn sync_remote_to_local_once(&self, session: &Session) -> SynchronizeRunnerErrorResult<()> {
         let local_metadata = fetch_metadata_local(&self.local_dir_path)?;
-        let remote_metadata = fetch_metadata_remote(&session, &self.remote_dir_path)?;
+        let remote_metadata = fetch_metadata_remote(session, &self.remote_dir_path)?;
 
         let new_or_changed_list: Vec<String> =
             reduce_remote_metadata_list_to_modified_or_not_exist_local(
@@ -89,22 +90,26 @@ impl SynchronizeRunner {
             .collect();
 
         sync_remote_files_to_local(
-            &session,
+            session,
             &self.remote_dir_path,
             &self.local_dir_path,
             &new_or_changed_list,
         )?;
+        // The copied files may have readings older than the last update of the cached buckets
+        if !new_or_changed_list.is_empty() {
+            AggregateCache::clear(&self.local_dir_path)?;
+        }
 
         Ok(())
     }
 
     pub fn authenticate(&self, remote_password: String) -> SynchronizeRunnerErrorResult<Session> {
         // Connect to the local SSH server
-        let tcp = TcpStream::connect(self.remote_host_and_port()).map_err(|_err| {
-            SynchronizeRunnerError::new(format!(
-                "Could not connect to SSH server {}",
-                self.remote_host_and_port()
-            ))
+        let tcp = TcpStream::connect(self.remote_host_and_port()).map_err(|err| {
+            SynchronizeRunnerError::Connect {
+                address: self.remote_host_and_port(),
+                source: err,
+            }
         })?;
 
         let mut session = Session::new()?;
@@ -114,19 +119,121 @@ impl SynchronizeRunner {
         let maybe_session = session.userauth_password(&self.remote_username, &remote_password);
 
         drop(remote_password);
-        if maybe_session.is_err() {
-            return Err(SynchronizeRunnerError::new(format!(
-                "Username ({}) and password combination failed",
-                self.remote_username
-            )));
+        if let Err(err) = maybe_session {
+            return Err(SynchronizeRunnerError::SshAuth {
+                username: self.remote_username.clone(),
+                source: Some(err),
+            });
         }
 
-        if session.authenticated() == false {
-            return Err(SynchronizeRunnerError::new(
-                "Unable to authenticate with remote SSH server".to_owned(),
-            ));
+        if !session.authenticated() {
+            return Err(SynchronizeRunnerError::SshAuth {
+                username: self.remote_username.clone(),
+                source: None,
+            });
         }
 
         Ok(session)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::aggregation::cache::AGGREGATE_DIR_NAME;
+    use std::env;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::path::Path;
+
+    /// `<username>:<password>@<host>:<port>` of the SSH server the sync is tested against
+    const SSH_SERVER_ENV: &str = "AIR_QUALITY_TEST_SSH";
+
+    const DATA_FILE_NAME: &str = "waveplus_data_sn_2930027508_2020-05-01.txt";
+    const DATA_FILE_CONTENT: &str = "#schema_version=2,model=Wave Plus,serial_number=2930027508\n\
+        Timestamp,Temperature (C),Humidity (%),Atmospheric pressure (mbar),CO2 (ppm),TVOC (ppb),\
+        Radon short-term average (Bq/m3),Radon long-term average (Bq/m3)\n\
+        2020-05-01T12:00:00Z,22.58,22.5,1022.08,476,152,1,6\n";
+
+    fn runner_from_env(
+        local_dir_path: &Path,
+        remote_dir_path: String,
+    ) -> (SynchronizeRunner, String) {
+        let server = env::var(SSH_SERVER_ENV).unwrap_or_else(|_| {
+            panic!(
+                "Set {} to <username>:<password>@<host>:<port>",
+                SSH_SERVER_ENV
+            )
+        });
+        let (credentials, address) = server.rsplit_once('@').expect("No @ in the SSH server");
+        let (username, password) = credentials.split_once(':').expect("No password");
+        let (host, port) = address.rsplit_once(':').expect("No port");
+
+        let runner = SynchronizeRunnerBuilder {
+            local_dir_path: local_dir_path.to_path_buf(),
+            remote_dir_path,
+            remote_host: host.to_owned(),
+            remote_port: port.parse().expect("Invalid port"),
+            remote_username: username.to_owned(),
+        }
+        .into();
+
+        (runner, password.to_owned())
+    }
+
+    fn run_remote(session: &Session, command: &str) -> String {
+        let mut channel = session.channel_session().unwrap();
+        channel.exec(command).unwrap();
+        let mut output = String::new();
+        channel.read_to_string(&mut output).unwrap();
+        channel.wait_close().unwrap();
+        assert_eq!(channel.exit_status().unwrap(), 0, "{} failed", command);
+
+        output.trim().to_owned()
+    }
+
+    /// Needs an SSH server with password authentication, e.g. a local sshd:
+    /// `AIR_QUALITY_TEST_SSH=pi:raspberry@localhost:22 cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn test_sync_from_ssh_server() {
+        let local_dir = tempfile::tempdir().unwrap();
+        let (runner, password) = runner_from_env(local_dir.path(), String::new());
+        let session = runner.authenticate(password).unwrap();
+
+        let remote_dir_path = run_remote(&session, "mktemp -d");
+        let remote_file_path = format!("{}/{}", remote_dir_path, DATA_FILE_NAME);
+        let mut remote_file = session
+            .scp_send(
+                Path::new(&remote_file_path),
+                0o644,
+                DATA_FILE_CONTENT.len() as u64,
+                None,
+            )
+            .unwrap();
+        remote_file.write_all(DATA_FILE_CONTENT.as_bytes()).unwrap();
+        remote_file.send_eof().unwrap();
+        remote_file.wait_eof().unwrap();
+        remote_file.close().unwrap();
+        remote_file.wait_close().unwrap();
+        drop(remote_file);
+
+        let (runner, _) = runner_from_env(local_dir.path(), remote_dir_path.clone());
+        let cache_dir = local_dir.path().join(AGGREGATE_DIR_NAME);
+        fs::create_dir(&cache_dir).unwrap();
+
+        let first_sync = runner.sync_remote_to_local_once(&session);
+        let local_content = fs::read_to_string(local_dir.path().join(DATA_FILE_NAME));
+        let cache_cleared = !cache_dir.exists();
+        // Nothing changed since, the cache is kept
+        fs::create_dir_all(&cache_dir).unwrap();
+        let second_sync = runner.sync_remote_to_local_once(&session);
+        run_remote(&session, &format!("rm -r {}", remote_dir_path));
+
+        first_sync.unwrap();
+        assert_eq!(local_content.unwrap(), DATA_FILE_CONTENT);
+        assert!(cache_cleared);
+        second_sync.unwrap();
+        assert!(cache_dir.exists());
+    }
+}
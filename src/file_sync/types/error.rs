@@ -1,3 +1,4 @@
+use crate::shared::storage::error::StorageError;
 use std::error::Error;
 use std::ffi::OsString;
 use std::fmt;
@@ -6,60 +7,149 @@ use std::str::Utf8Error;
 
 pub type SynchronizeRunnerErrorResult<T> = Result<T, SynchronizeRunnerError>;
 
+type BoxedError = Box<dyn Error + Send + Sync>;
+
 #[derive(Debug)]
-pub struct SynchronizeRunnerError {
-    message: Option<String>,
+pub enum SynchronizeRunnerError {
+    /// No TCP connection to the SSH server
+    Connect {
+        address: String,
+        source: std::io::Error,
+    },
+    SshAuth {
+        username: String,
+        source: Option<ssh2::Error>,
+    },
+    RemoteListing {
+        remote_dir_path: String,
+        reason: String,
+        source: Option<BoxedError>,
+    },
+    Download {
+        remote_path: String,
+        source: BoxedError,
+    },
+    /// SSH session errors outside of authentication, listing and downloading
+    Ssh(ssh2::Error),
+    /// Reading or writing the local files
+    Io(std::io::Error),
+    /// Clearing the aggregate cache of the local data dir after new readings were copied
+    Storage(StorageError),
+    InvalidFileName(OsString),
+    InvalidContent(Utf8Error),
 }
 
 impl SynchronizeRunnerError {
-    pub fn new(message: String) -> Self {
-        Self {
-            message: Some(message),
+    /// Process exit code for the `file-sync` command
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SynchronizeRunnerError::Connect { .. } => 30,
+            SynchronizeRunnerError::SshAuth { .. } => 31,
+            SynchronizeRunnerError::RemoteListing { .. } => 32,
+            SynchronizeRunnerError::Download { .. } => 33,
+            SynchronizeRunnerError::Ssh(_) => 34,
+            SynchronizeRunnerError::Io(_) | SynchronizeRunnerError::Storage(_) => 35,
+            SynchronizeRunnerError::InvalidFileName(_)
+            | SynchronizeRunnerError::InvalidContent(_) => 36,
         }
     }
 }
 
-impl Default for SynchronizeRunnerError {
-    fn default() -> Self {
-        Self { message: None }
-    }
-}
-
 impl fmt::Display for SynchronizeRunnerError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        if let Some(message) = &self.message {
-            write!(f, "[FileSyncError] {}", message)
-        } else {
-            write!(
+        match self {
+            SynchronizeRunnerError::Connect { address, .. } => write!(
+                f,
+                "[FileSyncError] Could not connect to SSH server {}. Check the host and port",
+                address
+            ),
+            SynchronizeRunnerError::SshAuth { username, .. } => write!(
+                f,
+                "[FileSyncError] Username ({}) and password combination failed",
+                username
+            ),
+            SynchronizeRunnerError::RemoteListing {
+                remote_dir_path,
+                reason,
+                ..
+            } => write!(
+                f,
+                "[FileSyncError] Could not list the remote dir {}: {}",
+                remote_dir_path, reason
+            ),
+            SynchronizeRunnerError::Download { remote_path, .. } => write!(
                 f,
-                "[FileSyncError] Synchronize runner error without a message"
-            )
+                "[FileSyncError] Could not download remote file {}",
+                remote_path
+            ),
+            SynchronizeRunnerError::Ssh(_) => write!(f, "[FileSyncError] SSH session error"),
+            SynchronizeRunnerError::Io(_) => {
+                write!(f, "[FileSyncError] Could not read or write the local files")
+            }
+            SynchronizeRunnerError::Storage(_) => {
+                write!(
+                    f,
+                    "[FileSyncError] Could not clear the local aggregate cache"
+                )
+            }
+            SynchronizeRunnerError::InvalidFileName(file_name) => write!(
+                f,
+                "[FileSyncError] Could not convert file name {:?} to a generic String",
+                file_name
+            ),
+            SynchronizeRunnerError::InvalidContent(_) => {
+                write!(f, "[FileSyncError] Remote file is not valid UTF-8")
+            }
         }
     }
 }
 
-impl Error for SynchronizeRunnerError {}
+impl Error for SynchronizeRunnerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SynchronizeRunnerError::Connect { source, .. } => Some(source),
+            SynchronizeRunnerError::SshAuth { source, .. } => source
+                .as_ref()
+                .map(|source| source as &(dyn Error + 'static)),
+            SynchronizeRunnerError::RemoteListing { source, .. } => source
+                .as_ref()
+                .map(|source| source.as_ref() as &(dyn Error + 'static)),
+            SynchronizeRunnerError::Download { source, .. } => Some(source.as_ref()),
+            SynchronizeRunnerError::Ssh(source) => Some(source),
+            SynchronizeRunnerError::Io(source) => Some(source),
+            SynchronizeRunnerError::Storage(source) => Some(source),
+            SynchronizeRunnerError::InvalidFileName(_) => None,
+            SynchronizeRunnerError::InvalidContent(source) => Some(source),
+        }
+    }
+}
 
 impl From<std::io::Error> for SynchronizeRunnerError {
     fn from(err: std::io::Error) -> Self {
-        Self::new(err.to_string())
+        SynchronizeRunnerError::Io(err)
+    }
+}
+
+impl From<StorageError> for SynchronizeRunnerError {
+    fn from(err: StorageError) -> Self {
+        SynchronizeRunnerError::Storage(err)
     }
 }
 
 impl From<ssh2::Error> for SynchronizeRunnerError {
     fn from(err: ssh2::Error) -> Self {
-        Self::new(err.to_string())
+        SynchronizeRunnerError::Ssh(err)
     }
 }
 
 impl From<std::ffi::OsString> for SynchronizeRunnerError {
-    fn from(_: OsString) -> Self {
-        Self::new("Could not convert OsString to generic String".to_owned())
+    fn from(file_name: OsString) -> Self {
+        SynchronizeRunnerError::InvalidFileName(file_name)
     }
 }
 
 impl From<std::str::Utf8Error> for SynchronizeRunnerError {
     fn from(err: Utf8Error) -> Self {
-        Self::new(err.to_string())
+        SynchronizeRunnerError::InvalidContent(err)
     }
 }
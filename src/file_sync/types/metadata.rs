@@ -1,5 +1,5 @@
 use crate::file_sync::types::error::SynchronizeRunnerErrorResult;
-use sha1::digest::generic_array::GenericArray;
+use sha1::digest::Output;
 use sha1::{Digest, Sha1};
 use std::fs::{DirEntry, File};
 use std::io::{BufReader, Read};
@@ -90,11 +90,9 @@ impl FileMetadata {
     }
 }
 
-type HasherType = GenericArray<u8, <Sha1 as Digest>::OutputSize>;
+type HasherType = Output<Sha1>;
 
-fn sha1_digest<R: Read>(
-    mut reader: R,
-) -> SynchronizeRunnerErrorResult<GenericArray<u8, <Sha1 as Digest>::OutputSize>> {
+fn sha1_digest<R: Read>(mut reader: R) -> SynchronizeRunnerErrorResult<HasherType> {
     let mut hasher = Sha1::new();
     let mut buffer = [0; 1024];
 
@@ -134,7 +132,7 @@ pub fn reduce_remote_metadata_list_to_modified_or_not_exist_local(
                 .find(|local_metadata| local_metadata.file_name == remote_metadata.file_name)
         };
         if let Some(local_metadata) = local_metadata {
-            if remote_metadata.clone().equal_checksum(local_metadata) == false {
+            if !remote_metadata.equal_checksum(local_metadata) {
                 // Only when the checksums are different
                 changed_or_new.push(remote_metadata.to_owned());
             }
@@ -145,3 +143,41 @@ pub fn reduce_remote_metadata_list_to_modified_or_not_exist_local(
 
     changed_or_new
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_sync::file_service::fetch_metadata_local;
+    use std::fs;
+
+    #[test]
+    fn test_only_new_and_changed_remote_files_are_synced() {
+        let local_dir = tempfile::tempdir().unwrap();
+        fs::write(local_dir.path().join("same.txt"), "1,2,3\n").unwrap();
+        fs::write(local_dir.path().join("changed.txt"), "1,2,3\n4,5,6\n").unwrap();
+        fs::create_dir(local_dir.path().join("migration_backup")).unwrap();
+
+        let local_metadata = fetch_metadata_local(local_dir.path()).unwrap();
+        let remote_metadata = vec![
+            FileMetadata::from_content("1,2,3\n", "same.txt".to_owned()).unwrap(),
+            FileMetadata::from_content("1,2,3\n", "changed.txt".to_owned()).unwrap(),
+            FileMetadata::from_content("7,8,9\n", "new.txt".to_owned()).unwrap(),
+        ];
+
+        let mut local_file_names: Vec<_> = local_metadata
+            .iter()
+            .map(|metadata| metadata.file_name())
+            .collect();
+        local_file_names.sort_unstable();
+        assert_eq!(local_file_names, vec!["changed.txt", "same.txt"]);
+
+        let to_sync: Vec<_> = reduce_remote_metadata_list_to_modified_or_not_exist_local(
+            &remote_metadata,
+            &local_metadata,
+        )
+        .iter()
+        .map(|metadata| metadata.file_name().to_owned())
+        .collect();
+        assert_eq!(to_sync, vec!["changed.txt", "new.txt"]);
+    }
+}
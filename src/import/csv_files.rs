@@ -0,0 +1,277 @@
+use crate::import::error::{ImportError, ImportErrorResult};
+use crate::runner::status::active_runner_serial_number;
+use crate::shared::data_reader::{data_file_paths, DataLine, SensorDataReader};
+use crate::shared::storage::{SensorDataStore, StorageBackend};
+use crate::shared::types::sensor_data::SensorData;
+use chrono::Utc;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Row counts of a single imported data file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileImport {
+    pub path: PathBuf,
+    /// From the header row, or the file name when the header is missing
+    pub serial_number: Option<u32>,
+    pub imported: usize,
+    /// Already stored, or a repeated timestamp in the file
+    pub skipped: usize,
+    /// Lines that could not be read or parsed, and readings without a serial number
+    pub malformed: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportSummary {
+    pub files: Vec<FileImport>,
+}
+
+impl ImportSummary {
+    pub fn imported(&self) -> usize {
+        self.files.iter().map(|file| file.imported).sum()
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.files.iter().map(|file| file.skipped).sum()
+    }
+
+    pub fn malformed(&self) -> usize {
+        self.files.iter().map(|file| file.malformed).sum()
+    }
+}
+
+impl fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for file in &self.files {
+            let file_name = file
+                .path
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy())
+                .unwrap_or_else(|| file.path.to_string_lossy());
+            writeln!(
+                f,
+                "{}: {} imported, {} skipped, {} malformed",
+                file_name, file.imported, file.skipped, file.malformed
+            )?;
+        }
+
+        writeln!(
+            f,
+            "{} files: {} imported, {} skipped, {} malformed",
+            self.files.len(),
+            self.imported(),
+            self.skipped(),
+            self.malformed()
+        )
+    }
+}
+
+/// Insert every reading of the data files in `source_dir` into the store. Readings that are
+/// already stored are skipped, so the import can be repeated, e.g. after filling a gap with
+/// files copied from another machine. The CSV backend rewrites the daily files, so that is
+/// refused while a runner stores readings in `data_dir`, a reading appended during the rewrite
+/// would be lost.
+pub fn import_data_dir(
+    store: &dyn SensorDataStore,
+    source_dir: &Path,
+    data_dir: &Path,
+) -> ImportErrorResult<ImportSummary> {
+    if store.backend() == StorageBackend::Csv {
+        let active_runner =
+            active_runner_serial_number(data_dir, Utc::now()).map_err(|source| {
+                ImportError::SourceDir {
+                    path: data_dir.to_path_buf(),
+                    source,
+                }
+            })?;
+        if let Some(serial_number) = active_runner {
+            return ImportError::RunnerActive { serial_number }.into();
+        }
+    }
+
+    let paths = data_file_paths(source_dir, None).map_err(|source| ImportError::SourceDir {
+        path: source_dir.to_path_buf(),
+        source,
+    })?;
+
+    let mut summary = ImportSummary::default();
+    for path in paths {
+        summary.files.push(import_data_file(store, path)?);
+    }
+
+    Ok(summary)
+}
+
+fn import_data_file(store: &dyn SensorDataStore, path: PathBuf) -> ImportErrorResult<FileImport> {
+    let mut readings: BTreeMap<u32, Vec<SensorData>> = BTreeMap::new();
+    let mut serial_number = None;
+    let mut malformed = 0;
+
+    for line in SensorDataReader::from_paths(vec![path.clone()]) {
+        match line {
+            Ok(DataLine::Header {
+                serial_number: header_serial_number,
+            }) => serial_number = header_serial_number.or(serial_number),
+            Ok(DataLine::Reading(record)) => match record.serial_number {
+                Some(record_serial_number) => {
+                    serial_number = Some(record_serial_number);
+                    readings
+                        .entry(record_serial_number)
+                        .or_default()
+                        .push(record.sensor_data);
+                }
+                None => {
+                    log::warn!(
+                        "Not imported. {}:{}: no serial number in the header or the file name",
+                        record.path.to_string_lossy(),
+                        record.line_number
+                    );
+                    malformed += 1;
+                }
+            },
+            Err(error) => {
+                log::warn!("Not imported. {}", error);
+                malformed += 1;
+            }
+        }
+    }
+
+    let mut imported = 0;
+    let mut skipped = 0;
+    for (serial_number, readings) in readings {
+        let inserted = store.insert_missing(serial_number, &readings)?;
+        imported += inserted;
+        skipped += readings.len() - inserted;
+    }
+
+    Ok(FileImport {
+        path,
+        serial_number,
+        imported,
+        skipped,
+        malformed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::status::{write_status_file, RunnerStatus, StatusRecord};
+    use crate::shared::storage::csv_store::CsvStore;
+    use crate::shared::storage::day_timezone::DayTimezone;
+    use crate::shared::storage::sqlite_store::SqliteStore;
+    use crate::shared::types::data_file_header::DataFileHeader;
+    use crate::shared::types::metric::Metric;
+    use chrono::Duration;
+    use std::fs;
+
+    fn csv_line(hour: i64, co2: f32) -> String {
+        SensorData::test_reading(SensorData::test_day_start() + Duration::hours(hour))
+            .with_value(Metric::Co2, co2)
+            .to_csv()
+    }
+
+    fn write_source_files(source_dir: &Path) {
+        // The header says which device the file belongs to, not the file name
+        fs::write(
+            source_dir.join("waveplus_data_sn_1_2020-05-01.txt"),
+            format!(
+                "{}\n{}\n{}\nnot a reading\n{}\n{}",
+                DataFileHeader::new(2930027508),
+                csv_line(10, 400.0),
+                csv_line(11, 500.0),
+                csv_line(11, 500.0),
+                csv_line(12, 600.0),
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_import_into_sqlite_and_again() {
+        let source_dir = tempfile::tempdir().unwrap();
+        write_source_files(source_dir.path());
+        let store = SqliteStore::open(&source_dir.path().join("test.sqlite3")).unwrap();
+
+        let summary = import_data_dir(&store, source_dir.path(), source_dir.path()).unwrap();
+
+        assert_eq!(
+            summary.files,
+            vec![FileImport {
+                path: source_dir.path().join("waveplus_data_sn_1_2020-05-01.txt"),
+                serial_number: Some(2930027508),
+                imported: 2,
+                skipped: 1,
+                malformed: 2,
+            }]
+        );
+        assert_eq!(store.serial_numbers().unwrap(), vec![2930027508]);
+
+        let summary = import_data_dir(&store, source_dir.path(), source_dir.path()).unwrap();
+        assert_eq!(
+            (summary.imported(), summary.skipped(), summary.malformed()),
+            (0, 3, 2)
+        );
+    }
+
+    #[test]
+    fn test_import_fills_gap_in_csv_files() {
+        let source_dir = tempfile::tempdir().unwrap();
+        write_source_files(source_dir.path());
+        let data_dir = tempfile::tempdir().unwrap();
+        let data_file_path = data_dir
+            .path()
+            .join("waveplus_data_sn_2930027508_2020-05-01.txt");
+        fs::write(
+            &data_file_path,
+            format!(
+                "{}\n{}\n{}\n",
+                DataFileHeader::new(2930027508),
+                csv_line(9, 300.0),
+                csv_line(12, 600.0)
+            ),
+        )
+        .unwrap();
+        let store = CsvStore::new(data_dir.path().to_path_buf(), DayTimezone::Utc);
+
+        let summary = import_data_dir(&store, source_dir.path(), data_dir.path()).unwrap();
+
+        assert_eq!((summary.imported(), summary.skipped()), (2, 1));
+        let co2: Vec<f32> = store
+            .readings(2930027508, None, None)
+            .unwrap()
+            .iter()
+            .filter_map(|reading| reading.value(Metric::Co2))
+            .collect();
+        assert_eq!(co2, vec![300.0, 400.0, 500.0, 600.0]);
+        assert!(fs::read_to_string(&data_file_path)
+            .unwrap()
+            .starts_with("#schema_version=2,"));
+    }
+
+    #[test]
+    fn test_refuse_to_rewrite_csv_files_while_the_runner_is_active() {
+        let source_dir = tempfile::tempdir().unwrap();
+        write_source_files(source_dir.path());
+        let data_dir = tempfile::tempdir().unwrap();
+        let record = StatusRecord {
+            timestamp: Utc::now(),
+            status: RunnerStatus::Ok,
+            consecutive_failures: 0,
+            next_attempt: Some(Utc::now() + Duration::minutes(5)),
+            message: String::new(),
+        };
+        write_status_file(data_dir.path(), 2930027508, &record).unwrap();
+        let store = CsvStore::new(data_dir.path().to_path_buf(), DayTimezone::Utc);
+
+        let error = import_data_dir(&store, source_dir.path(), data_dir.path()).unwrap_err();
+
+        assert_eq!(error.exit_code(), 52);
+        assert!(store.serial_numbers().unwrap().is_empty());
+
+        // The database can take the readings while the runner writes
+        let sqlite_store = SqliteStore::open(&data_dir.path().join("test.sqlite3")).unwrap();
+        let summary = import_data_dir(&sqlite_store, source_dir.path(), data_dir.path()).unwrap();
+        assert_eq!(summary.imported(), 2);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::shared::storage::error::StorageError;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+pub type ImportErrorResult<T> = Result<T, ImportError>;
+
+#[derive(Debug)]
+pub enum ImportError {
+    /// The dir with the data files to import could not be listed
+    SourceDir {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Storage(StorageError),
+    /// The runner of the device may be appending to the daily files that would be rewritten
+    RunnerActive {
+        serial_number: u32,
+    },
+}
+
+impl<T> From<ImportError> for ImportErrorResult<T> {
+    fn from(error: ImportError) -> Self {
+        Err(error)
+    }
+}
+
+impl From<StorageError> for ImportError {
+    fn from(error: StorageError) -> Self {
+        ImportError::Storage(error)
+    }
+}
+
+impl ImportError {
+    /// Process exit code for the `import` command
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ImportError::SourceDir { .. } => 50,
+            ImportError::Storage(_) => 51,
+            ImportError::RunnerActive { .. } => 52,
+        }
+    }
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::SourceDir { path, .. } => {
+                write!(f, "Could not read the dir {}", path.to_string_lossy())
+            }
+            ImportError::Storage(_) => write!(f, "Could not store the imported sensor data"),
+            ImportError::RunnerActive { serial_number } => write!(
+                f,
+                "The runner of device {} is storing readings in the data dir, stop it before importing into the CSV files",
+                serial_number
+            ),
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImportError::SourceDir { source, .. } => Some(source),
+            ImportError::Storage(source) => Some(source),
+            ImportError::RunnerActive { .. } => None,
+        }
+    }
+}
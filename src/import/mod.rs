@@ -0,0 +1,2 @@
+pub mod csv_files;
+pub mod error;
@@ -1,18 +1,39 @@
+mod alert;
+mod config;
 mod dashboard_terminal;
 mod device;
-
-#[cfg(target_os = "windows")]
+mod export;
 mod file_sync;
+mod import;
+mod migrate;
+mod report;
 mod runner;
 mod shared;
 
-#[cfg(target_os = "windows")]
+use crate::config::Config;
+use crate::export::error::ExportError;
+use crate::export::history::{export_sensor_data, ExportFormat, ExportQuery};
 use crate::file_sync::synchronize::{SynchronizeRunner, SynchronizeRunnerBuilder};
+use crate::import::csv_files::import_data_dir;
+use crate::import::error::ImportError;
+use crate::migrate::data_files::{migrate_data_dir, MigrationOutcome};
+use crate::report::error::ReportError;
+use crate::report::radon::radon_reports;
+use crate::report::stats::{stats_reports, stats_reports_to_json, StatsFormat, StatsPeriod};
+use crate::runner::error::RunnerError;
+use crate::shared::aggregation::cache::AggregateCache;
+use crate::shared::storage::{open_store, StorageBackend};
+use crate::shared::types::quality_profile::QualityProfile;
+use chrono::{NaiveDate, Utc};
+use dashboard_terminal::app_error::AppError;
+use dashboard_terminal::freshness::FreshnessPolicyBuilder;
 use dashboard_terminal::start_gui;
 use flexi_logger::{opt_format, Age, Cleanup, Criterion, Duplicate, Naming};
+use runner::runner_loop::RunnerBuilder;
 use runner::start_data_generator;
 use std::error::Error;
 use std::fs;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use structopt::StructOpt;
@@ -25,6 +46,9 @@ use structopt::StructOpt;
 struct Opt {
     #[structopt(long)]
     debug: bool,
+    /// Path to the TOML config file. Defaults to ~/.air-quality/config.toml
+    #[structopt(short = "c", long = "config", parse(from_os_str))]
+    config_path: Option<PathBuf>,
     #[structopt(subcommand)]
     command: Command,
 }
@@ -33,7 +57,16 @@ struct Opt {
 enum Command {
     Runner(RunnerOpt),
     Gui(GuiOpt),
-    #[cfg(target_os = "windows")]
+    /// Reports computed from the stored sensor data
+    Report(ReportOpt),
+    /// Add the readings of a dir of data files to the storage backend, skipping the readings
+    /// that are already stored
+    Import(ImportOpt),
+    /// Write the stored readings of a date range as CSV, JSON Lines or Parquet
+    Export(ExportOpt),
+    /// Rewrite the daily data files of older versions into the current layout. The original
+    /// files are copied to the migration_backup dir in the data dir. Stop the runner first
+    Migrate(MigrateOpt),
     FileSync(FileSyncOpt),
 }
 
@@ -41,17 +74,123 @@ enum Command {
 struct RunnerOpt {
     #[structopt(short = "d", long = "data-dir", parse(from_os_str))]
     data_dir_path: Option<PathBuf>,
-    #[structopt(short = "s", long = "serial-number")]
-    serial_number: u32,
+    /// Serial number(s) of the devices to poll. Can be repeated or comma separated. Falls back to
+    /// `serial_numbers` in the [runner] section of the config file
+    #[structopt(short = "s", long = "serial-number", use_delimiter = true)]
+    serial_numbers: Vec<u32>,
+    /// Seconds between reads. Reads are aligned to the wall clock, e.g. :00, :05, :10 for 300
+    /// [default: 300]
+    #[structopt(long = "interval")]
+    interval_seconds: Option<u64>,
+    /// Seconds to wait for a device before a read attempt is given up [default: 60]
+    #[structopt(long = "read-timeout")]
+    read_timeout_seconds: Option<u64>,
+    /// Number of retries (with a Bluetooth restart) after a failed read [default: 3]
+    #[structopt(long = "max-retries")]
+    max_retries: Option<u8>,
+    /// Stop the runner after this many failed polls in a row for a device. Failed devices are
+    /// retried with an exponential backoff, and by default the runner never gives up
+    #[structopt(long = "max-consecutive-failures")]
+    max_consecutive_failures: Option<u32>,
+    /// Serve the latest readings and the read failure counters for Prometheus on
+    /// http://<address>/metrics, e.g. 0.0.0.0:9101
+    #[structopt(long = "metrics-address")]
+    metrics_address: Option<SocketAddr>,
 }
 
 #[derive(Debug, StructOpt)]
 struct GuiOpt {
     #[structopt(short = "d", long = "data-dir", parse(from_os_str))]
     data_dir_path: Option<PathBuf>,
+    /// Minutes after which a reading is shown as stale and the runner as not reporting. Falls
+    /// back to `stale_after_minutes` in the [dashboard] section of the config file [default: 30,
+    /// or three poll intervals if that is longer]
+    #[structopt(long = "stale-after")]
+    stale_after_minutes: Option<u64>,
+}
+
+/// Without a report name: min, max, mean, median and 95th percentile of every sensor, the time
+/// in each quality band and the longest BAD/TERRIBLE stretch
+#[derive(Debug, StructOpt)]
+struct ReportOpt {
+    #[structopt(subcommand)]
+    report: Option<ReportCommand>,
+    #[structopt(short = "d", long = "data-dir", parse(from_os_str))]
+    data_dir_path: Option<PathBuf>,
+    /// Only report this device [default: every device in the data dir]
+    #[structopt(short = "s", long = "serial-number")]
+    serial_number: Option<u32>,
+    /// day, week, month or year, up to now
+    #[structopt(long = "period", default_value = "week")]
+    period: StatsPeriod,
+    /// table or json
+    #[structopt(short = "f", long = "format", default_value = "table")]
+    format: StatsFormat,
+}
+
+#[derive(Debug, StructOpt)]
+enum ReportCommand {
+    /// Monthly and annual radon means, compared with the long-term average of the device and
+    /// the 100 and 200 Bq/m3 levels
+    Radon(RadonReportOpt),
+}
+
+#[derive(Debug, StructOpt)]
+struct RadonReportOpt {
+    #[structopt(short = "d", long = "data-dir", parse(from_os_str))]
+    data_dir_path: Option<PathBuf>,
+    /// Only report this device [default: every device in the data dir]
+    #[structopt(short = "s", long = "serial-number")]
+    serial_number: Option<u32>,
+    /// Report this calendar year [default: the last twelve months with data]
+    #[structopt(long = "year")]
+    year: Option<i32>,
+}
+
+#[derive(Debug, StructOpt)]
+struct ImportOpt {
+    /// Dir with the daily data files to import, e.g. `waveplus_data_sn_<serial>_<date>.txt`
+    #[structopt(long = "source-dir", parse(from_os_str))]
+    source_dir_path: PathBuf,
+    #[structopt(short = "d", long = "data-dir", parse(from_os_str))]
+    data_dir_path: Option<PathBuf>,
+    /// csv or sqlite. Falls back to `backend` in the [storage] section of the config file
+    /// [default: csv]
+    #[structopt(long = "backend")]
+    backend: Option<StorageBackend>,
+    /// Falls back to `sqlite_path` in the [storage] section of the config file [default:
+    /// air-quality.sqlite3 in the data dir]
+    #[structopt(long = "sqlite-path", parse(from_os_str))]
+    sqlite_path: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+struct ExportOpt {
+    #[structopt(short = "d", long = "data-dir", parse(from_os_str))]
+    data_dir_path: Option<PathBuf>,
+    /// Only export this device [default: every device]
+    #[structopt(short = "s", long = "serial")]
+    serial_number: Option<u32>,
+    /// First day to export, in the timezone of the daily data files, e.g. 2024-01-01 [default: the first reading]
+    #[structopt(long = "from")]
+    from: Option<NaiveDate>,
+    /// Last day to export, this day included [default: the last reading]
+    #[structopt(long = "to")]
+    to: Option<NaiveDate>,
+    /// csv, jsonl or parquet
+    #[structopt(short = "f", long = "format", default_value = "csv")]
+    format: ExportFormat,
+    /// Write to this file instead of stdout
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    output_path: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+struct MigrateOpt {
+    #[structopt(short = "d", long = "data-dir", parse(from_os_str))]
+    data_dir_path: Option<PathBuf>,
 }
 
-#[cfg(target_os = "windows")]
 #[derive(Debug, StructOpt)]
 struct FileSyncOpt {
     #[structopt(short = "l", long = "local-data-dir", parse(from_os_str))]
@@ -72,10 +211,25 @@ fn main() -> Result<(), Box<dyn Error>> {
     crate_app_dirs()?;
     set_up_logger(opt.debug, get_log_dir().expect("Log dir"));
 
+    let config = load_config(opt.config_path)?;
+    let quality_profile = config
+        .quality
+        .to_profile()
+        .map_err(|message| format!("Invalid quality settings in the config file: {}", message))?;
+    // The default ranges would stay in use without a warning otherwise
+    if !QualityProfile::set_active(quality_profile) {
+        return Err(
+            "The quality ranges were already in use before the config file was read".into(),
+        );
+    }
+
     match opt.command {
-        Command::Runner(command_opt) => runner_subcommand(command_opt)?,
-        Command::Gui(command_opt) => gui_subcommand(command_opt),
-        #[cfg(target_os = "windows")]
+        Command::Runner(command_opt) => runner_subcommand(command_opt, &config)?,
+        Command::Gui(command_opt) => gui_subcommand(command_opt, &config),
+        Command::Report(command_opt) => report_subcommand(command_opt, &config),
+        Command::Import(command_opt) => import_subcommand(command_opt, &config),
+        Command::Export(command_opt) => export_subcommand(command_opt, &config),
+        Command::Migrate(command_opt) => migrate_subcommand(command_opt),
         Command::FileSync(command_opt) => file_sync_subcommand(command_opt),
     };
 
@@ -88,19 +242,18 @@ fn crate_app_dirs() -> Result<(), Box<dyn Error>> {
     dirs::home_dir()
         .map(|path| {
             let base_path = path.join(BASE_DEFAULT_DIR_NAME);
-            fs::create_dir_all(&base_path).expect(
-                format!(
+            fs::create_dir_all(&base_path).unwrap_or_else(|_| {
+                panic!(
                     "Could not create default directory: {}",
                     base_path.to_string_lossy()
                 )
-                .as_str(),
-            );
+            });
 
             ["data", "logs"].iter().for_each(|dir_name| {
                 let path = base_path.join(dir_name);
-                fs::create_dir_all(&path).expect(
-                    format!("Could not create directory: {}", path.to_string_lossy()).as_str(),
-                );
+                fs::create_dir_all(&path).unwrap_or_else(|_| {
+                    panic!("Could not create directory: {}", path.to_string_lossy())
+                });
             })
         })
         .expect("Could not get home dir");
@@ -112,6 +265,15 @@ fn get_app_dir() -> Option<PathBuf> {
     dirs::home_dir().map(|path| path.join(BASE_DEFAULT_DIR_NAME))
 }
 
+fn load_config(config_path: Option<PathBuf>) -> Result<Config, Box<dyn Error>> {
+    let explicitly_given = config_path.is_some();
+    let config_path = config_path
+        .or_else(|| get_app_dir().map(|path| config::default_config_path(&path)))
+        .unwrap_or_else(|| Path::new(config::DEFAULT_CONFIG_FILE_NAME).to_path_buf());
+
+    Ok(Config::load(&config_path, explicitly_given)?)
+}
+
 fn get_log_dir() -> Option<PathBuf> {
     get_app_dir().map(|path| path.join("logs"))
 }
@@ -143,24 +305,265 @@ fn get_data_path(path: Option<PathBuf>) -> PathBuf {
         .unwrap_or_else(|| Path::new(".").to_path_buf())
 }
 
-fn runner_subcommand(opt: RunnerOpt) -> Result<(), Box<dyn Error>> {
+const DEFAULT_POLL_INTERVAL_SECONDS: u64 = 60 * 5;
+
+fn runner_subcommand(opt: RunnerOpt, config: &Config) -> Result<(), Box<dyn Error>> {
     let data_dir_path = get_data_path(opt.data_dir_path);
-    start_data_generator(data_dir_path, opt.serial_number)?;
+
+    let mut serial_numbers = if opt.serial_numbers.is_empty() {
+        config.runner.serial_numbers.clone()
+    } else {
+        opt.serial_numbers
+    };
+    serial_numbers.sort_unstable();
+    serial_numbers.dedup();
+
+    if serial_numbers.is_empty() {
+        return Err("No serial numbers given. Use --serial-number or the config file".into());
+    }
+
+    let poll_interval = opt
+        .interval_seconds
+        .or(config.runner.interval_seconds)
+        .unwrap_or(DEFAULT_POLL_INTERVAL_SECONDS);
+    let read_timeout = opt
+        .read_timeout_seconds
+        .or(config.runner.read_timeout_seconds)
+        .unwrap_or(60);
+    let max_retries = opt.max_retries.or(config.runner.max_retries).unwrap_or(3);
+    let max_consecutive_failures = opt
+        .max_consecutive_failures
+        .or(config.runner.max_consecutive_failures)
+        .filter(|&max_consecutive_failures| max_consecutive_failures > 0);
+
+    if poll_interval == 0 {
+        return Err("The poll interval must be at least one second".into());
+    }
+
+    let alerter = config
+        .alerts
+        .to_alerter(QualityProfile::active())
+        .map_err(|message| format!("Invalid alert settings in the config file: {}", message))?;
+
+    let mqtt_publisher = config
+        .mqtt
+        .as_ref()
+        .map(|mqtt| mqtt.to_publisher_builder(&serial_numbers).into());
+
+    let store = match config.storage.open_store(&data_dir_path) {
+        Ok(store) => store,
+        Err(error) => {
+            let error = RunnerError::Storage(error);
+            exit_with_error(&error, error.exit_code())
+        }
+    };
+
+    let runner_result = start_data_generator(RunnerBuilder {
+        output_dir_path: data_dir_path,
+        store,
+        serial_numbers,
+        poll_interval: Duration::from_secs(poll_interval),
+        read_timeout: Duration::from_secs(read_timeout),
+        max_retries,
+        max_consecutive_failures,
+        alerter,
+        metrics_address: opt.metrics_address.or(config.runner.metrics_address),
+        mqtt_publisher,
+    });
+
+    if let Err(error) = runner_result {
+        exit_with_error(&error, error.exit_code());
+    }
 
     Ok(())
 }
 
-fn gui_subcommand(opt: GuiOpt) {
+fn gui_subcommand(opt: GuiOpt, config: &Config) {
+    let data_dir_path = get_data_path(opt.data_dir_path);
+
+    // The dashboard doesn't poll itself, but it needs to know how often new readings should come in
+    let poll_interval = config
+        .runner
+        .interval_seconds
+        .unwrap_or(DEFAULT_POLL_INTERVAL_SECONDS);
+    let stale_after_seconds = opt
+        .stale_after_minutes
+        .or(config.dashboard.stale_after_minutes)
+        .map(|minutes| minutes * 60)
+        .unwrap_or_else(|| (30 * 60).max(poll_interval * 3));
+
+    let store = match config.storage.open_store(&data_dir_path) {
+        Ok(store) => store,
+        Err(error) => {
+            let error = AppError::from(error);
+            exit_with_error(&error, error.exit_code())
+        }
+    };
+
+    let gui_result = start_gui(
+        data_dir_path,
+        store,
+        FreshnessPolicyBuilder {
+            poll_interval: Duration::from_secs(poll_interval),
+            stale_after: Duration::from_secs(stale_after_seconds),
+        }
+        .into(),
+        config.storage.timezone,
+    );
+
+    if let Err(error) = gui_result {
+        exit_with_error(&error, error.exit_code());
+    }
+}
+
+fn report_subcommand(opt: ReportOpt, config: &Config) {
+    match opt.report {
+        Some(ReportCommand::Radon(opt)) => {
+            let data_dir_path = get_data_path(opt.data_dir_path);
+            let (serial_number, year) = (opt.serial_number, opt.year);
+
+            let reports = config
+                .storage
+                .open_store(&data_dir_path)
+                .map_err(ReportError::from)
+                .and_then(|store| {
+                    radon_reports(
+                        store.as_ref(),
+                        &AggregateCache::new(&data_dir_path, config.storage.timezone),
+                        &data_dir_path,
+                        config.storage.timezone,
+                        serial_number,
+                        year,
+                    )
+                });
+            match reports {
+                Ok(reports) => {
+                    let reports: Vec<String> =
+                        reports.iter().map(|report| report.to_string()).collect();
+                    print!("{}", reports.join("\n"));
+                }
+                Err(error) => exit_with_error(&error, error.exit_code()),
+            }
+        }
+        None => {
+            let data_dir_path = get_data_path(opt.data_dir_path);
+            let (serial_number, period) = (opt.serial_number, opt.period);
+            // Same as the dashboard: a reading is late after two poll intervals
+            let max_gap = chrono::Duration::seconds(
+                2 * config
+                    .runner
+                    .interval_seconds
+                    .unwrap_or(DEFAULT_POLL_INTERVAL_SECONDS) as i64,
+            );
+
+            let reports = config
+                .storage
+                .open_store(&data_dir_path)
+                .map_err(ReportError::from)
+                .and_then(|store| {
+                    stats_reports(
+                        store.as_ref(),
+                        &data_dir_path,
+                        serial_number,
+                        period,
+                        Utc::now(),
+                        max_gap,
+                    )
+                });
+            match reports {
+                Ok(reports) => match opt.format {
+                    StatsFormat::Table => {
+                        let reports: Vec<String> =
+                            reports.iter().map(|report| report.to_string()).collect();
+                        print!("{}", reports.join("\n"));
+                    }
+                    StatsFormat::Json => println!("{}", stats_reports_to_json(&reports)),
+                },
+                Err(error) => exit_with_error(&error, error.exit_code()),
+            }
+        }
+    }
+}
+
+fn import_subcommand(opt: ImportOpt, config: &Config) {
     let data_dir_path = get_data_path(opt.data_dir_path);
+    let source_dir_path = opt.source_dir_path;
+    let backend = opt.backend.unwrap_or(config.storage.backend);
+    let sqlite_path = opt
+        .sqlite_path
+        .or_else(|| config.storage.sqlite_path.clone());
 
-    let gui_result = start_gui(data_dir_path);
+    let summary = open_store(
+        backend,
+        &data_dir_path,
+        sqlite_path,
+        config.storage.timezone,
+    )
+    .map_err(ImportError::from)
+    .and_then(|store| import_data_dir(store.as_ref(), &source_dir_path, &data_dir_path))
+    .and_then(|summary| {
+        // The cached buckets only pick up readings newer than the last update
+        if summary.imported() > 0 {
+            AggregateCache::clear(&data_dir_path)?;
+        }
+        Ok(summary)
+    });
 
-    if gui_result.is_err() {
-        eprintln!("The terminal GUI failed to start/run");
+    match summary {
+        Ok(summary) => print!("{}", summary),
+        Err(error) => exit_with_error(&error, error.exit_code()),
+    }
+}
+
+fn export_subcommand(opt: ExportOpt, config: &Config) {
+    let data_dir_path = get_data_path(opt.data_dir_path);
+    let query = ExportQuery {
+        serial_number: opt.serial_number,
+        from: opt.from,
+        to: opt.to,
+        day_timezone: config.storage.timezone,
+    };
+    let (format, output_path) = (opt.format, opt.output_path);
+
+    let count = config
+        .storage
+        .open_store(&data_dir_path)
+        .map_err(ExportError::from)
+        .and_then(|store| {
+            export_sensor_data(store.as_ref(), query, format, output_path.as_deref())
+        });
+
+    match count {
+        Ok(count) => {
+            if let Some(output_path) = output_path {
+                eprintln!(
+                    "Exported {} readings to {}",
+                    count,
+                    output_path.to_string_lossy()
+                );
+            }
+        }
+        Err(error) => exit_with_error(&error, error.exit_code()),
+    }
+}
+
+fn migrate_subcommand(opt: MigrateOpt) {
+    let data_dir_path = get_data_path(opt.data_dir_path);
+
+    let summary = migrate_data_dir(&data_dir_path).and_then(|summary| {
+        // The cached buckets were computed from the files before the migration
+        if summary.count(MigrationOutcome::Migrated) > 0 {
+            AggregateCache::clear(&data_dir_path)?;
+        }
+        Ok(summary)
+    });
+
+    match summary {
+        Ok(summary) => print!("{}", summary),
+        Err(error) => exit_with_error(&error, error.exit_code()),
     }
 }
 
-#[cfg(target_os = "windows")]
 fn file_sync_subcommand(opt: FileSyncOpt) {
     let local_dir_path = get_data_path(opt.local_data_dir_path);
     let remote_dir_path = opt
@@ -168,7 +571,7 @@ fn file_sync_subcommand(opt: FileSyncOpt) {
         .unwrap_or_else(|| format!("{}/data", BASE_DEFAULT_DIR_NAME));
 
     let remote_host = opt.remote_host.unwrap_or_else(|| "raspberrypi".to_owned());
-    let remote_port = opt.remote_port.unwrap_or_else(|| 22);
+    let remote_port = opt.remote_port.unwrap_or(22);
     let remote_username = opt.remote_username.unwrap_or_else(|| "pi".to_owned());
 
     println!(
@@ -191,10 +594,7 @@ fn file_sync_subcommand(opt: FileSyncOpt) {
         Ok(remote_password) => std::process::exit(
             match runner.sync_remote_to_local_loop(remote_password, Duration::from_secs(60 * 10)) {
                 Ok(_) => 0,
-                Err(err) => {
-                    eprintln!("Error running the file sync: {}", err);
-                    1
-                }
+                Err(err) => exit_with_error(&err, err.exit_code()),
             },
         ),
         Err(_) => {
@@ -203,3 +603,16 @@ fn file_sync_subcommand(opt: FileSyncOpt) {
         }
     }
 }
+
+/// Print the error with its chain of causes and exit with the exit code of the error kind
+fn exit_with_error(error: &dyn Error, exit_code: i32) -> ! {
+    eprintln!("Error: {}", error);
+
+    let mut source = error.source();
+    while let Some(cause) = source {
+        eprintln!("  Caused by: {}", cause);
+        source = cause.source();
+    }
+
+    std::process::exit(exit_code)
+}
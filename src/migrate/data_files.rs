@@ -0,0 +1,280 @@
+use crate::migrate::error::{MigrateError, MigrateErrorResult};
+use crate::runner::status::active_runner_serial_number;
+use crate::shared::data_reader::{data_file_paths, serial_number_from_file_name};
+use crate::shared::types::data_file_header::DataFileHeader;
+use chrono::Utc;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Dir in the data dir with the original of every migrated file
+pub const BACKUP_DIR_NAME: &str = "migration_backup";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationOutcome {
+    Migrated,
+    /// Already in the current layout
+    UpToDate,
+    /// Neither the header nor the file name has a serial number, the file is left as it is
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMigration {
+    pub path: PathBuf,
+    pub outcome: MigrationOutcome,
+    pub readings: usize,
+    /// Lines that could not be parsed, copied as they are
+    pub kept: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MigrationSummary {
+    pub files: Vec<FileMigration>,
+}
+
+impl MigrationSummary {
+    pub fn count(&self, outcome: MigrationOutcome) -> usize {
+        self.files
+            .iter()
+            .filter(|file| file.outcome == outcome)
+            .count()
+    }
+}
+
+impl fmt::Display for MigrationSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for file in &self.files {
+            let file_name = file
+                .path
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy())
+                .unwrap_or_else(|| file.path.to_string_lossy());
+            match file.outcome {
+                MigrationOutcome::Migrated => writeln!(
+                    f,
+                    "{}: {} readings migrated, {} lines kept as they were",
+                    file_name, file.readings, file.kept
+                )?,
+                MigrationOutcome::UpToDate => {}
+                MigrationOutcome::Skipped => {
+                    writeln!(f, "{}: skipped, no serial number", file_name)?
+                }
+            }
+        }
+
+        writeln!(
+            f,
+            "{} files: {} migrated, {} up to date, {} skipped",
+            self.files.len(),
+            self.count(MigrationOutcome::Migrated),
+            self.count(MigrationOutcome::UpToDate),
+            self.count(MigrationOutcome::Skipped)
+        )
+    }
+}
+
+/// Rewrite the data files of older versions in the data dir into the current layout. The
+/// original of every rewritten file is copied to `BACKUP_DIR_NAME` first. Files that are
+/// already up to date are left alone, so the migration can be repeated. Refused while a runner
+/// stores readings in the dir, a reading appended during the rewrite would be lost.
+pub fn migrate_data_dir(data_dir: &Path) -> MigrateErrorResult<MigrationSummary> {
+    let dir_error = |source| MigrateError::DataDir {
+        path: data_dir.to_path_buf(),
+        source,
+    };
+    if let Some(serial_number) =
+        active_runner_serial_number(data_dir, Utc::now()).map_err(dir_error)?
+    {
+        return MigrateError::RunnerActive { serial_number }.into();
+    }
+
+    let paths = data_file_paths(data_dir, None).map_err(dir_error)?;
+    let backup_dir = data_dir.join(BACKUP_DIR_NAME);
+
+    let mut summary = MigrationSummary::default();
+    for path in paths {
+        let migration =
+            migrate_data_file(&path, &backup_dir).map_err(|source| MigrateError::DataFile {
+                path: path.clone(),
+                source,
+            })?;
+        summary.files.push(migration);
+    }
+
+    Ok(summary)
+}
+
+fn migrate_data_file(path: &Path, backup_dir: &Path) -> std::io::Result<FileMigration> {
+    let content = fs::read_to_string(path)?;
+    let mut migration = FileMigration {
+        path: path.to_path_buf(),
+        outcome: MigrationOutcome::UpToDate,
+        readings: 0,
+        kept: 0,
+    };
+
+    let mut header = DataFileHeader::legacy(serial_number_from_file_name(path));
+    let mut lines = content.lines().peekable();
+    while lines.next_if(|line| header.read_line(line)).is_some() {}
+
+    if header.is_current() || content.is_empty() {
+        return Ok(migration);
+    }
+    let serial_number = match header.serial_number {
+        Some(serial_number) => serial_number,
+        None => {
+            migration.outcome = MigrationOutcome::Skipped;
+            return Ok(migration);
+        }
+    };
+
+    let mut migrated = vec![DataFileHeader::new(serial_number).to_string()];
+    for line in lines {
+        match header.parse_reading(line) {
+            Ok(sensor_data) => {
+                migrated.push(sensor_data.to_csv());
+                migration.readings += 1;
+            }
+            Err(_) => {
+                migrated.push(line.to_owned());
+                migration.kept += 1;
+            }
+        }
+    }
+    let mut migrated = migrated.join("\n");
+    // An incomplete last line stays incomplete
+    if content.ends_with('\n') {
+        migrated.push('\n');
+    }
+
+    // The backup of an earlier, interrupted run is the original
+    fs::create_dir_all(backup_dir)?;
+    let backup_path = backup_dir.join(path.file_name().unwrap_or_default());
+    if !backup_path.exists() {
+        fs::copy(path, &backup_path)?;
+    }
+
+    // Replace the file in one go, so a reader never sees half of it
+    let temp_path = path.with_extension("txt.tmp");
+    fs::write(&temp_path, migrated)?;
+    fs::rename(&temp_path, path)?;
+
+    migration.outcome = MigrationOutcome::Migrated;
+    Ok(migration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::status::{write_status_file, RunnerStatus, StatusRecord};
+    use crate::shared::data_reader::SensorDataReader;
+    use crate::shared::types::metric::Metric;
+
+    const LEGACY_HEADER: &str = "Timestamp,Temperature (C),Humidity (%),\
+        Atmospheric pressure (mbar),CO2 (ppm),TVOC (ppb),Radon short-term average (Bq/m3),\
+        Radon long-term average (Bq/m3),2930027508";
+
+    fn write_legacy_file(data_dir: &Path) -> (PathBuf, String) {
+        let path = data_dir.join("waveplus_data_sn_2930027508_2020-05-01.txt");
+        let content = format!(
+            "{}\n\
+             2020-05-01 12:00:00 UTC,22.58,22.5,1022.08,476,152,1,6,\n\
+             not a reading\n\
+             2020-05-01 12:05:00 UTC,22.6,22.5,1022.1,480,150,1,6,\n\
+             2020-05-01 12:10:00 UTC,22.6",
+            LEGACY_HEADER
+        );
+        fs::write(&path, &content).unwrap();
+
+        (path, content)
+    }
+
+    #[test]
+    fn test_migrate_legacy_file_with_backup() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let (path, original) = write_legacy_file(data_dir.path());
+
+        let summary = migrate_data_dir(data_dir.path()).unwrap();
+
+        assert_eq!(
+            summary.files,
+            vec![FileMigration {
+                path: path.clone(),
+                outcome: MigrationOutcome::Migrated,
+                readings: 2,
+                kept: 2,
+            }]
+        );
+        let backup_path = data_dir
+            .path()
+            .join(BACKUP_DIR_NAME)
+            .join("waveplus_data_sn_2930027508_2020-05-01.txt");
+        assert_eq!(fs::read_to_string(&backup_path).unwrap(), original);
+
+        let migrated = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = migrated.lines().collect();
+        assert_eq!(
+            lines[0],
+            "#schema_version=2,model=Wave Plus,serial_number=2930027508"
+        );
+        assert_eq!(
+            lines[2],
+            "2020-05-01T12:00:00Z,22.58,22.5,1022.08,476,152,1,6"
+        );
+        assert_eq!(lines[3], "not a reading");
+        assert!(migrated.ends_with("\n2020-05-01 12:10:00 UTC,22.6"));
+
+        let readings: Vec<_> = SensorDataReader::from_paths(vec![path])
+            .readings()
+            .collect();
+        assert_eq!(readings.len(), 2);
+        assert_eq!(readings[1].serial_number, Some(2930027508));
+        assert_eq!(readings[1].sensor_data.value(Metric::Co2), Some(480.0));
+    }
+
+    #[test]
+    fn test_migrate_again_keeps_the_files() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let (path, original) = write_legacy_file(data_dir.path());
+        migrate_data_dir(data_dir.path()).unwrap();
+        let migrated = fs::read_to_string(&path).unwrap();
+
+        let summary = migrate_data_dir(data_dir.path()).unwrap();
+
+        assert_eq!(summary.count(MigrationOutcome::UpToDate), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), migrated);
+        let backup_path = data_dir
+            .path()
+            .join(BACKUP_DIR_NAME)
+            .join("waveplus_data_sn_2930027508_2020-05-01.txt");
+        assert_eq!(fs::read_to_string(&backup_path).unwrap(), original);
+    }
+
+    #[test]
+    fn test_refuse_to_migrate_while_the_runner_is_active() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let (path, original) = write_legacy_file(data_dir.path());
+        let mut record = StatusRecord {
+            timestamp: Utc::now(),
+            status: RunnerStatus::Ok,
+            consecutive_failures: 0,
+            next_attempt: Some(Utc::now() + chrono::Duration::minutes(5)),
+            message: String::new(),
+        };
+        write_status_file(data_dir.path(), 2930027508, &record).unwrap();
+
+        let error = migrate_data_dir(data_dir.path()).unwrap_err();
+
+        assert_eq!(error.exit_code(), 72);
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+
+        record.status = RunnerStatus::Stopped;
+        record.next_attempt = None;
+        write_status_file(data_dir.path(), 2930027508, &record).unwrap();
+
+        let summary = migrate_data_dir(data_dir.path()).unwrap();
+
+        assert_eq!(summary.count(MigrationOutcome::Migrated), 1);
+    }
+}
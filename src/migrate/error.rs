@@ -0,0 +1,78 @@
+use crate::shared::storage::error::StorageError;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+pub type MigrateErrorResult<T> = Result<T, MigrateError>;
+
+#[derive(Debug)]
+pub enum MigrateError {
+    /// The data dir could not be listed
+    DataDir {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// A data file could not be read, backed up or rewritten
+    DataFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The runner is writing to the data dir
+    RunnerActive { serial_number: u32 },
+    /// The aggregate cache could not be cleared
+    Storage(StorageError),
+}
+
+impl<T> From<MigrateError> for MigrateErrorResult<T> {
+    fn from(error: MigrateError) -> Self {
+        Err(error)
+    }
+}
+
+impl From<StorageError> for MigrateError {
+    fn from(error: StorageError) -> Self {
+        MigrateError::Storage(error)
+    }
+}
+
+impl MigrateError {
+    /// Process exit code for the `migrate` command
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MigrateError::DataDir { .. } => 70,
+            MigrateError::DataFile { .. } => 71,
+            MigrateError::RunnerActive { .. } => 72,
+            MigrateError::Storage(_) => 73,
+        }
+    }
+}
+
+impl fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrateError::DataDir { path, .. } => {
+                write!(f, "Could not read the dir {}", path.to_string_lossy())
+            }
+            MigrateError::DataFile { path, .. } => {
+                write!(f, "Could not migrate the file {}", path.to_string_lossy())
+            }
+            MigrateError::RunnerActive { serial_number } => write!(
+                f,
+                "The runner is still storing readings of device {}, stop it before migrating",
+                serial_number
+            ),
+            MigrateError::Storage(_) => write!(f, "Could not clear the aggregate cache"),
+        }
+    }
+}
+
+impl Error for MigrateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MigrateError::DataDir { source, .. } => Some(source),
+            MigrateError::DataFile { source, .. } => Some(source),
+            MigrateError::RunnerActive { .. } => None,
+            MigrateError::Storage(source) => Some(source),
+        }
+    }
+}
@@ -0,0 +1,2 @@
+pub mod data_files;
+pub mod error;
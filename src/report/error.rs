@@ -0,0 +1,68 @@
+use crate::shared::storage::error::StorageError;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+pub type ReportErrorResult<T> = Result<T, ReportError>;
+
+#[derive(Debug)]
+pub enum ReportError {
+    NoSensorData {
+        data_dir: PathBuf,
+        serial_number: Option<u32>,
+    },
+    Storage(StorageError),
+}
+
+impl<T> From<ReportError> for ReportErrorResult<T> {
+    fn from(error: ReportError) -> Self {
+        Err(error)
+    }
+}
+
+impl From<StorageError> for ReportError {
+    fn from(error: StorageError) -> Self {
+        ReportError::Storage(error)
+    }
+}
+
+impl ReportError {
+    /// Process exit code for the `report` command
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ReportError::NoSensorData { .. } => 40,
+            ReportError::Storage(_) => 41,
+        }
+    }
+}
+
+impl fmt::Display for ReportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportError::NoSensorData {
+                data_dir,
+                serial_number,
+            } => {
+                write!(f, "No sensor data")?;
+                if let Some(serial_number) = serial_number {
+                    write!(f, " of device {}", serial_number)?;
+                }
+                write!(
+                    f,
+                    " in {} for the report period. Is the runner writing to this dir?",
+                    data_dir.to_string_lossy()
+                )
+            }
+            ReportError::Storage(_) => write!(f, "Could not read the sensor data"),
+        }
+    }
+}
+
+impl Error for ReportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReportError::Storage(source) => Some(source),
+            ReportError::NoSensorData { .. } => None,
+        }
+    }
+}
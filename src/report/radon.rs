@@ -0,0 +1,481 @@
+use crate::device::metrics_of_serial_number;
+use crate::report::error::{ReportError, ReportErrorResult};
+use crate::shared::aggregation::cache::AggregateCache;
+use crate::shared::aggregation::{mean, Bucket, Resolution};
+use crate::shared::storage::day_timezone::DayTimezone;
+use crate::shared::storage::error::StorageError;
+use crate::shared::storage::SensorDataStore;
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::SensorData;
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, TimeZone, Utc};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// WHO reference level for homes
+pub const RADON_REFERENCE_LEVEL: f64 = 100.0;
+/// Level above which mitigation is strongly advised
+pub const RADON_ACTION_LEVEL: f64 = 200.0;
+/// Below this share of covered hours the means are marked as not representative. Radon varies
+/// a lot between seasons, so a few good months don't make an annual average.
+const MIN_REPRESENTATIVE_COVERAGE: f64 = 0.5;
+
+/// Whole days from `start` up to, but not including, `end`, in the timezone of the days of the
+/// data files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportPeriod {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl ReportPeriod {
+    pub fn year(year: i32) -> Option<Self> {
+        Some(Self {
+            start: NaiveDate::from_ymd_opt(year, 1, 1)?,
+            end: NaiveDate::from_ymd_opt(year + 1, 1, 1)?,
+        })
+    }
+
+    /// The twelve months that end with `last_day`
+    pub fn twelve_months_until(last_day: NaiveDate) -> Self {
+        let end = last_day.succ_opt().unwrap_or(last_day);
+        let start = end.checked_sub_months(Months::new(12)).unwrap_or(end);
+
+        Self { start, end }
+    }
+
+    fn start_time(&self, day_timezone: DayTimezone) -> DateTime<Utc> {
+        day_timezone.start_of_day(self.start)
+    }
+
+    fn end_time(&self, day_timezone: DayTimezone) -> DateTime<Utc> {
+        day_timezone.start_of_day(self.end)
+    }
+}
+
+/// Mean of the hourly means of one calendar month, or of the part of it in the report period
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadonMonth {
+    /// First day of the month
+    pub month: NaiveDate,
+    /// In Bq/m3, `None` without readings
+    pub mean: Option<f64>,
+    /// Share of the hours with at least one reading
+    pub coverage: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct RadonReport {
+    pub serial_number: u32,
+    pub period: ReportPeriod,
+    pub months: Vec<RadonMonth>,
+    /// Mean of all hourly means in the period, in Bq/m3
+    pub annual_mean: Option<f64>,
+    pub coverage: f64,
+    /// The latest reading with radon in the period, for the long-term average the device
+    /// computes itself
+    pub latest: Option<SensorData>,
+}
+
+impl RadonReport {
+    /// The readings are averaged per hour first, so a changed poll interval or a gap doesn't
+    /// give some part of the period more weight than the rest
+    pub fn from_readings<I>(
+        serial_number: u32,
+        period: ReportPeriod,
+        day_timezone: DayTimezone,
+        readings: I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = SensorData>,
+    {
+        let start = period.start_time(day_timezone);
+        let end = period.end_time(day_timezone);
+
+        let mut hours: BTreeMap<DateTime<Utc>, (f64, u32)> = BTreeMap::new();
+        let mut latest: Option<SensorData> = None;
+        for sensor_data in readings {
+            let timestamp = *sensor_data.timestamp();
+            let radon = match sensor_data.value(Metric::RadonShortTermAverage) {
+                Some(radon) if timestamp >= start && timestamp < end => radon,
+                _ => continue,
+            };
+
+            let hour = Utc
+                .timestamp_opt(
+                    timestamp.timestamp() - timestamp.timestamp().rem_euclid(3600),
+                    0,
+                )
+                .unwrap();
+            let (sum, count) = hours.entry(hour).or_insert((0.0, 0));
+            *sum += radon as f64;
+            *count += 1;
+
+            if latest
+                .as_ref()
+                .is_none_or(|latest| latest.timestamp() <= sensor_data.timestamp())
+            {
+                latest = Some(sensor_data);
+            }
+        }
+
+        let hourly_means: Vec<(DateTime<Utc>, f64)> = hours
+            .into_iter()
+            .map(|(hour, (sum, count))| (hour, sum / count as f64))
+            .collect();
+
+        let months = months_in(period)
+            .map(|month| {
+                let month_start = day_timezone.start_of_day(month).max(start);
+                let month_end = day_timezone.start_of_day(month + Months::new(1)).min(end);
+                let means: Vec<f64> = hourly_means
+                    .iter()
+                    .filter(|(hour, _)| *hour >= month_start && *hour < month_end)
+                    .map(|(_, mean)| *mean)
+                    .collect();
+
+                RadonMonth {
+                    month,
+                    mean: mean(&means),
+                    coverage: coverage(means.len(), month_end - month_start),
+                }
+            })
+            .collect();
+
+        let means: Vec<f64> = hourly_means.iter().map(|(_, mean)| *mean).collect();
+
+        Self {
+            serial_number,
+            period,
+            months,
+            annual_mean: mean(&means),
+            coverage: coverage(means.len(), end - start),
+            latest,
+        }
+    }
+
+    /// `None` without readings
+    pub fn exceeds(&self, level: f64) -> Option<bool> {
+        self.annual_mean.map(|annual_mean| annual_mean > level)
+    }
+}
+
+impl fmt::Display for RadonReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Radon report for device {}, {} to {}",
+            self.serial_number,
+            self.period.start,
+            self.period.end.pred_opt().unwrap_or(self.period.end)
+        )?;
+        writeln!(f)?;
+        writeln!(f, "{:<9}{:>14}{:>10}", "Month", "Mean (Bq/m3)", "Coverage")?;
+        for month in &self.months {
+            writeln!(
+                f,
+                "{:<9}{:>14}{:>9.0}%",
+                month.month.format("%Y-%m").to_string(),
+                format_mean(month.mean),
+                month.coverage * 100.0
+            )?;
+        }
+        writeln!(f)?;
+
+        writeln!(
+            f,
+            "Annual mean:              {} Bq/m3 from {:.0}% of the hours",
+            format_mean(self.annual_mean),
+            self.coverage * 100.0
+        )?;
+        let long_term_average = self.latest.as_ref().and_then(|latest| {
+            Some((
+                latest.value(Metric::RadonLongTermAverage)?,
+                latest.quality(Metric::RadonLongTermAverage)?,
+            ))
+        });
+        if let Some((long_term_average, long_term_quality)) = long_term_average {
+            write!(
+                f,
+                "Device long-term average: {:.0} Bq/m3 ({})",
+                long_term_average,
+                format!("{:?}", long_term_quality).to_uppercase()
+            )?;
+            if let Some(annual_mean) = self.annual_mean {
+                let difference = annual_mean - long_term_average as f64;
+                write!(
+                    f,
+                    ", the annual mean is {:.0} Bq/m3 {}",
+                    difference.abs(),
+                    if difference < 0.0 { "lower" } else { "higher" }
+                )?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f)?;
+
+        for (level, name) in [
+            (RADON_REFERENCE_LEVEL, "reference level"),
+            (RADON_ACTION_LEVEL, "action level"),
+        ] {
+            let verdict = match self.exceeds(level) {
+                Some(true) => "OVER",
+                Some(false) => "under",
+                None => "unknown",
+            };
+            writeln!(f, "{:.0} Bq/m3 {}: {}", level, name, verdict)?;
+        }
+
+        if self.coverage < MIN_REPRESENTATIVE_COVERAGE {
+            writeln!(
+                f,
+                "Less than {:.0}% of the hours have readings, the annual mean may not be representative",
+                MIN_REPRESENTATIVE_COVERAGE * 100.0
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A report for the device, or for every device in the store. Covers the calendar year when
+/// given, otherwise the twelve months up to the latest reading of each device. The days and
+/// months are those of `day_timezone`.
+pub fn radon_reports(
+    store: &dyn SensorDataStore,
+    aggregate_cache: &AggregateCache,
+    data_dir: &Path,
+    day_timezone: DayTimezone,
+    serial_number: Option<u32>,
+    year: Option<i32>,
+) -> ReportErrorResult<Vec<RadonReport>> {
+    let serial_numbers = match serial_number {
+        Some(serial_number) => vec![serial_number],
+        None => store.serial_numbers()?,
+    };
+
+    let mut reports = Vec::new();
+    for serial_number in serial_numbers {
+        if !metrics_of_serial_number(serial_number).contains(&Metric::RadonShortTermAverage) {
+            log::info!("Skipping device {}, it has no radon sensor", serial_number);
+            continue;
+        }
+
+        let period = match year {
+            Some(year) => ReportPeriod::year(year),
+            None => match store.latest(serial_number) {
+                Ok(latest) => latest.map(|latest| {
+                    ReportPeriod::twelve_months_until(day_timezone.date_of(*latest.timestamp()))
+                }),
+                Err(error @ StorageError::Unreadable { .. }) => {
+                    log::warn!("{}", error);
+                    None
+                }
+                Err(error) => return Err(error.into()),
+            },
+        };
+        let period = match period {
+            Some(period) => period,
+            None => continue,
+        };
+
+        // The report averages per hour first, so the cached hourly means give the same result
+        let readings = aggregate_cache
+            .buckets(
+                store,
+                serial_number,
+                Resolution::Hourly,
+                Some(period.start_time(day_timezone)),
+            )?
+            .iter()
+            .map(Bucket::to_sensor_data)
+            .collect::<Vec<_>>();
+
+        let report = RadonReport::from_readings(serial_number, period, day_timezone, readings);
+        if report.latest.is_some() {
+            reports.push(report);
+        }
+    }
+
+    if reports.is_empty() {
+        return Err(ReportError::NoSensorData {
+            data_dir: data_dir.to_path_buf(),
+            serial_number,
+        });
+    }
+
+    Ok(reports)
+}
+
+/// First day of every month that overlaps the period
+fn months_in(period: ReportPeriod) -> impl Iterator<Item = NaiveDate> {
+    let first = period.start.with_day(1).unwrap();
+
+    (0..)
+        .map(move |offset| first + Months::new(offset))
+        .take_while(move |month| *month < period.end)
+}
+
+fn coverage(covered_hours: usize, duration: Duration) -> f64 {
+    let hours = duration.num_hours();
+    if hours <= 0 {
+        0.0
+    } else {
+        covered_hours as f64 / hours as f64
+    }
+}
+
+fn format_mean(mean: Option<f64>) -> String {
+    mean.map(|mean| format!("{:.0}", mean))
+        .unwrap_or_else(|| "-".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn radon_reading(timestamp: DateTime<Utc>, short_term: f32, long_term: f32) -> SensorData {
+        SensorData::test_reading(timestamp)
+            .with_value(Metric::RadonShortTermAverage, short_term)
+            .with_value(Metric::RadonLongTermAverage, long_term)
+    }
+
+    /// A reading every 30 minutes for the whole month
+    fn month_of_readings(year: i32, month: u32, radon: f32) -> Vec<SensorData> {
+        let first_day = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+        let start = DayTimezone::Utc.start_of_day(first_day);
+        let end = DayTimezone::Utc.start_of_day(first_day + Months::new(1));
+
+        (0..)
+            .map(|half_hour| start + Duration::minutes(30 * half_hour))
+            .take_while(|timestamp| *timestamp < end)
+            .map(|timestamp| radon_reading(timestamp, radon, 90.0))
+            .collect()
+    }
+
+    #[test]
+    fn test_monthly_and_annual_means() {
+        let readings = month_of_readings(2020, 1, 80.0)
+            .into_iter()
+            .chain(month_of_readings(2020, 2, 160.0));
+
+        let report = RadonReport::from_readings(
+            1,
+            ReportPeriod::year(2020).unwrap(),
+            DayTimezone::Utc,
+            readings,
+        );
+
+        assert_eq!(report.months.len(), 12);
+        assert_eq!(report.months[0].mean, Some(80.0));
+        assert_eq!(report.months[0].coverage, 1.0);
+        assert_eq!(report.months[1].mean, Some(160.0));
+        assert_eq!(report.months[2].mean, None);
+        assert_eq!(report.months[2].coverage, 0.0);
+
+        // January has 744 hours and February 696, so January weighs more
+        let expected = (744.0 * 80.0 + 696.0 * 160.0) / (744.0 + 696.0);
+        assert!((report.annual_mean.unwrap() - expected).abs() < 1e-9);
+        assert!((report.coverage - 1440.0 / 8784.0).abs() < 1e-9);
+        assert_eq!(
+            report
+                .latest
+                .as_ref()
+                .and_then(|latest| latest.value(Metric::RadonLongTermAverage)),
+            Some(90.0)
+        );
+        assert_eq!(report.exceeds(RADON_REFERENCE_LEVEL), Some(true));
+        assert_eq!(report.exceeds(RADON_ACTION_LEVEL), Some(false));
+    }
+
+    #[test]
+    fn test_hours_are_weighted_equally() {
+        let hour = Utc.with_ymd_and_hms(2020, 3, 1, 12, 0, 0).unwrap();
+        // Four readings in the first hour, one in the second
+        let readings = vec![
+            radon_reading(hour, 100.0, 0.0),
+            radon_reading(hour + Duration::minutes(15), 100.0, 0.0),
+            radon_reading(hour + Duration::minutes(30), 100.0, 0.0),
+            radon_reading(hour + Duration::minutes(45), 100.0, 0.0),
+            radon_reading(hour + Duration::minutes(60), 200.0, 0.0),
+        ];
+
+        let report = RadonReport::from_readings(
+            1,
+            ReportPeriod::year(2020).unwrap(),
+            DayTimezone::Utc,
+            readings,
+        );
+
+        assert_eq!(report.annual_mean, Some(150.0));
+    }
+
+    #[test]
+    fn test_twelve_months_until() {
+        let period =
+            ReportPeriod::twelve_months_until(NaiveDate::from_ymd_opt(2021, 3, 14).unwrap());
+
+        assert_eq!(period.start, NaiveDate::from_ymd_opt(2020, 3, 15).unwrap());
+        assert_eq!(period.end, NaiveDate::from_ymd_opt(2021, 3, 15).unwrap());
+        assert_eq!(months_in(period).count(), 13);
+    }
+
+    #[test]
+    fn test_readings_outside_the_period_are_ignored() {
+        let readings = vec![
+            radon_reading(
+                Utc.with_ymd_and_hms(2019, 12, 31, 23, 0, 0).unwrap(),
+                500.0,
+                0.0,
+            ),
+            radon_reading(
+                Utc.with_ymd_and_hms(2020, 6, 1, 0, 0, 0).unwrap(),
+                50.0,
+                0.0,
+            ),
+        ];
+
+        let report = RadonReport::from_readings(
+            1,
+            ReportPeriod::year(2020).unwrap(),
+            DayTimezone::Utc,
+            readings,
+        );
+
+        assert_eq!(report.annual_mean, Some(50.0));
+        assert_eq!(report.exceeds(RADON_REFERENCE_LEVEL), Some(false));
+    }
+
+    #[test]
+    fn test_months_of_the_day_timezone() {
+        let readings = || {
+            vec![
+                radon_reading(
+                    Utc.with_ymd_and_hms(2020, 1, 31, 12, 0, 0).unwrap(),
+                    100.0,
+                    0.0,
+                ),
+                // 00:30 on February 1st in Oslo
+                radon_reading(
+                    Utc.with_ymd_and_hms(2020, 1, 31, 23, 30, 0).unwrap(),
+                    300.0,
+                    0.0,
+                ),
+            ]
+        };
+        let period = ReportPeriod::year(2020).unwrap();
+
+        let utc = RadonReport::from_readings(1, period, DayTimezone::Utc, readings());
+        let oslo =
+            RadonReport::from_readings(1, period, "Europe/Oslo".parse().unwrap(), readings());
+
+        assert_eq!(
+            (utc.months[0].mean, utc.months[1].mean),
+            (Some(200.0), None)
+        );
+        assert_eq!(
+            (oslo.months[0].mean, oslo.months[1].mean),
+            (Some(100.0), Some(300.0))
+        );
+    }
+}
@@ -0,0 +1,474 @@
+use crate::report::error::{ReportError, ReportErrorResult};
+use crate::shared::aggregation::mean;
+use crate::shared::storage::SensorDataStore;
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::SensorData;
+use crate::shared::types::sensor_quality::SensorQuality;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The time range of a statistics report, counted back from now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsPeriod {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl StatsPeriod {
+    pub fn duration(self) -> Duration {
+        match self {
+            StatsPeriod::Day => Duration::days(1),
+            StatsPeriod::Week => Duration::days(7),
+            StatsPeriod::Month => Duration::days(30),
+            StatsPeriod::Year => Duration::days(365),
+        }
+    }
+}
+
+impl FromStr for StatsPeriod {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "day" => Ok(StatsPeriod::Day),
+            "week" => Ok(StatsPeriod::Week),
+            "month" => Ok(StatsPeriod::Month),
+            "year" => Ok(StatsPeriod::Year),
+            _ => Err(format!(
+                "Unknown period '{}', expected day, week, month or year",
+                value
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsFormat {
+    Table,
+    Json,
+}
+
+impl FromStr for StatsFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "table" => Ok(StatsFormat::Table),
+            "json" => Ok(StatsFormat::Json),
+            _ => Err(format!(
+                "Unknown report format '{}', expected table or json",
+                value
+            )),
+        }
+    }
+}
+
+/// Share of the covered time in every quality band, in percent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct QualityShares {
+    pub good: f64,
+    pub bad: f64,
+    pub terrible: f64,
+    pub depends_on_context: f64,
+}
+
+/// The longest uninterrupted time a sensor was BAD or TERRIBLE
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PoorStretch {
+    #[serde(serialize_with = "serialize_rfc3339")]
+    pub start: DateTime<Utc>,
+    #[serde(serialize_with = "serialize_rfc3339")]
+    pub end: DateTime<Utc>,
+    pub minutes: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricStats {
+    /// `Metric::key`
+    pub metric: &'static str,
+    #[serde(skip)]
+    pub label: &'static str,
+    pub unit: &'static str,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub quality_percent: QualityShares,
+    pub longest_poor_stretch: Option<PoorStretch>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceStats {
+    pub serial_number: u32,
+    #[serde(serialize_with = "serialize_rfc3339")]
+    pub since: DateTime<Utc>,
+    #[serde(serialize_with = "serialize_rfc3339")]
+    pub until: DateTime<Utc>,
+    pub readings: usize,
+    pub metrics: Vec<MetricStats>,
+}
+
+impl DeviceStats {
+    /// Every reading counts for the time up to the next reading, but for at most `max_gap`, so
+    /// the time the runner wasn't running doesn't count for any band. A longer gap also ends a
+    /// stretch of BAD or TERRIBLE values.
+    pub fn from_readings(
+        serial_number: u32,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+        mut readings: Vec<SensorData>,
+        max_gap: Duration,
+    ) -> Option<Self> {
+        readings.retain(|sensor_data| {
+            *sensor_data.timestamp() >= since && *sensor_data.timestamp() < until
+        });
+        if readings.is_empty() {
+            return None;
+        }
+        readings.sort_by_key(|sensor_data| *sensor_data.timestamp());
+
+        let time_to_next: Vec<Duration> = readings
+            .iter()
+            .enumerate()
+            .map(|(index, sensor_data)| {
+                let next = readings
+                    .get(index + 1)
+                    .map_or(until, |next| *next.timestamp());
+                next - *sensor_data.timestamp()
+            })
+            .collect();
+
+        // The sensors the model of the device doesn't have are left out
+        let metrics = Metric::ALL
+            .iter()
+            .filter_map(|metric| metric_stats(*metric, &readings, &time_to_next, max_gap))
+            .collect();
+
+        Some(Self {
+            serial_number,
+            since,
+            until,
+            readings: readings.len(),
+            metrics,
+        })
+    }
+}
+
+/// `None` when none of the readings has a value of the metric
+fn metric_stats(
+    metric: Metric,
+    readings: &[SensorData],
+    time_to_next: &[Duration],
+    max_gap: Duration,
+) -> Option<MetricStats> {
+    let mut values: Vec<f64> = readings
+        .iter()
+        .filter_map(|sensor_data| sensor_data.value(metric))
+        .map(|value| value as f64)
+        .collect();
+    let mean = mean(&values)?;
+    values.sort_by(|a, b| a.total_cmp(b));
+
+    let mut band_seconds = [0i64; 4];
+    let mut longest_poor_stretch: Option<PoorStretch> = None;
+    let mut current_stretch: Option<PoorStretch> = None;
+
+    for (sensor_data, time_to_next) in readings.iter().zip(time_to_next) {
+        let duration = (*time_to_next).min(max_gap);
+        let quality = match sensor_data.quality(metric) {
+            Some(quality) => quality,
+            None => {
+                current_stretch = None;
+                continue;
+            }
+        };
+        band_seconds[band_index(quality)] += duration.num_seconds();
+
+        let is_poor = matches!(quality, SensorQuality::Bad | SensorQuality::Terrible);
+        current_stretch = match current_stretch {
+            Some(stretch) if is_poor => Some(PoorStretch {
+                end: *sensor_data.timestamp() + duration,
+                ..stretch
+            }),
+            None if is_poor => Some(PoorStretch {
+                start: *sensor_data.timestamp(),
+                end: *sensor_data.timestamp() + duration,
+                minutes: 0,
+            }),
+            _ => None,
+        };
+
+        if let Some(stretch) = &mut current_stretch {
+            stretch.minutes = (stretch.end - stretch.start).num_minutes();
+            if longest_poor_stretch.is_none_or(|longest| stretch.minutes > longest.minutes) {
+                longest_poor_stretch = Some(*stretch);
+            }
+            // A gap in the readings ends the stretch
+            if *time_to_next > max_gap {
+                current_stretch = None;
+            }
+        }
+    }
+
+    let total_seconds: i64 = band_seconds.iter().sum();
+    let share = |seconds: i64| {
+        if total_seconds == 0 {
+            0.0
+        } else {
+            seconds as f64 / total_seconds as f64 * 100.0
+        }
+    };
+
+    Some(MetricStats {
+        metric: metric.key(),
+        label: metric.name(),
+        unit: metric.unit(),
+        min: values[0],
+        max: values[values.len() - 1],
+        mean,
+        median: percentile(&values, 50.0),
+        p95: percentile(&values, 95.0),
+        quality_percent: QualityShares {
+            good: share(band_seconds[0]),
+            bad: share(band_seconds[1]),
+            terrible: share(band_seconds[2]),
+            depends_on_context: share(band_seconds[3]),
+        },
+        longest_poor_stretch,
+    })
+}
+
+fn band_index(quality: SensorQuality) -> usize {
+    match quality {
+        SensorQuality::Good => 0,
+        SensorQuality::Bad => 1,
+        SensorQuality::Terrible => 2,
+        SensorQuality::DependsOnContext => 3,
+    }
+}
+
+/// Linear interpolation between the closest ranks of the sorted values
+fn percentile(sorted_values: &[f64], percent: f64) -> f64 {
+    let rank = percent / 100.0 * (sorted_values.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+
+    sorted_values[lower] + (sorted_values[upper] - sorted_values[lower]) * (rank - lower as f64)
+}
+
+impl fmt::Display for DeviceStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Statistics for device {}, {} to {}, {} readings",
+            self.serial_number,
+            self.since.format("%Y-%m-%d %H:%M UTC"),
+            self.until.format("%Y-%m-%d %H:%M UTC"),
+            self.readings
+        )?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<26}{:<7}{:>8}{:>8}{:>8}{:>8}{:>8}{:>7}{:>7}{:>9}{:>9}  Longest BAD/TERRIBLE",
+            "Sensor",
+            "Unit",
+            "Min",
+            "Max",
+            "Mean",
+            "Median",
+            "P95",
+            "Good",
+            "Bad",
+            "Terrible",
+            "Context"
+        )?;
+
+        for metric in &self.metrics {
+            let shares = &metric.quality_percent;
+            let stretch = match &metric.longest_poor_stretch {
+                Some(stretch) => format!(
+                    "{} from {}",
+                    format_minutes(stretch.minutes),
+                    stretch.start.format("%Y-%m-%d %H:%M")
+                ),
+                None => "-".to_owned(),
+            };
+            writeln!(
+                f,
+                "{:<26}{:<7}{:>8.1}{:>8.1}{:>8.1}{:>8.1}{:>8.1}{:>6.0}%{:>6.0}%{:>8.0}%{:>8.0}%  {}",
+                metric.label,
+                metric.unit,
+                metric.min,
+                metric.max,
+                metric.mean,
+                metric.median,
+                metric.p95,
+                shares.good,
+                shares.bad,
+                shares.terrible,
+                shares.depends_on_context,
+                stretch
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn format_minutes(minutes: i64) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}m", minutes),
+        (hours, 0) => format!("{}h", hours),
+        (hours, minutes) => format!("{}h {}m", hours, minutes),
+    }
+}
+
+/// Statistics of the device, or of every device in the store, for the period up to `now`
+pub fn stats_reports(
+    store: &dyn SensorDataStore,
+    data_dir: &Path,
+    serial_number: Option<u32>,
+    period: StatsPeriod,
+    now: DateTime<Utc>,
+    max_gap: Duration,
+) -> ReportErrorResult<Vec<DeviceStats>> {
+    let since = now - period.duration();
+    let serial_numbers = match serial_number {
+        Some(serial_number) => vec![serial_number],
+        None => store.serial_numbers()?,
+    };
+
+    let mut reports = Vec::new();
+    for serial_number in serial_numbers {
+        let readings = store.readings(serial_number, Some(since), Some(now))?;
+        if let Some(report) =
+            DeviceStats::from_readings(serial_number, since, now, readings, max_gap)
+        {
+            reports.push(report);
+        }
+    }
+
+    if reports.is_empty() {
+        return Err(ReportError::NoSensorData {
+            data_dir: data_dir.to_path_buf(),
+            serial_number,
+        });
+    }
+
+    Ok(reports)
+}
+
+/// The reports as a JSON array
+pub fn stats_reports_to_json(reports: &[DeviceStats]) -> String {
+    serde_json::to_string_pretty(reports).expect("The statistics can always be serialized")
+}
+
+fn serialize_rfc3339<S: Serializer>(
+    timestamp: &DateTime<Utc>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&timestamp.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(minute: i64, co2: f32) -> SensorData {
+        SensorData::test_reading(SensorData::test_day_start() + Duration::minutes(minute))
+            .with_value(Metric::Co2, co2)
+    }
+
+    fn co2_stats(stats: &DeviceStats) -> &MetricStats {
+        stats
+            .metrics
+            .iter()
+            .find(|metric| metric.metric == "co2")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_value_statistics() {
+        let start = SensorData::test_day_start();
+        let readings = (0..20)
+            .map(|index| reading(index * 5, 400.0 + index as f32 * 10.0))
+            .collect();
+
+        let stats = DeviceStats::from_readings(
+            2930027508,
+            start,
+            start + Duration::minutes(100),
+            readings,
+            Duration::minutes(10),
+        )
+        .unwrap();
+
+        let co2 = co2_stats(&stats);
+        assert_eq!(stats.readings, 20);
+        assert_eq!((co2.min, co2.max), (400.0, 590.0));
+        assert_eq!(co2.mean, 495.0);
+        assert_eq!(co2.median, 495.0);
+        assert!((co2.p95 - 580.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_time_in_quality_bands_and_longest_poor_stretch() {
+        let start = SensorData::test_day_start();
+        // With the Airthings ranges CO2 is BAD from 800 and TERRIBLE from 1000 ppm
+        let readings = vec![
+            reading(0, 500.0),
+            reading(10, 900.0),
+            reading(20, 1200.0),
+            reading(30, 500.0),
+            // A gap of two hours, of which only 10 minutes count
+            reading(40, 900.0),
+            reading(160, 900.0),
+            reading(170, 900.0),
+            reading(180, 900.0),
+        ];
+
+        let stats = DeviceStats::from_readings(
+            2930027508,
+            start,
+            start + Duration::minutes(190),
+            readings,
+            Duration::minutes(10),
+        )
+        .unwrap();
+
+        let co2 = co2_stats(&stats);
+        assert_eq!(
+            co2.quality_percent,
+            QualityShares {
+                good: 25.0,
+                bad: 62.5,
+                terrible: 12.5,
+                depends_on_context: 0.0,
+            }
+        );
+
+        let stretch = co2.longest_poor_stretch.unwrap();
+        assert_eq!(stretch.start, start + Duration::minutes(160));
+        assert_eq!(stretch.minutes, 30);
+    }
+
+    #[test]
+    fn test_no_readings_in_the_period() {
+        let start = SensorData::test_day_start();
+        let stats = DeviceStats::from_readings(
+            2930027508,
+            start + Duration::days(1),
+            start + Duration::days(2),
+            vec![reading(0, 500.0)],
+            Duration::minutes(10),
+        );
+
+        assert!(stats.is_none());
+    }
+}
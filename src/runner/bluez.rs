@@ -0,0 +1,287 @@
+use crate::runner::error::{RunnerError, RunnerErrorResult};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use zbus::blocking::Connection;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+const BLUEZ_SERVICE: &str = "org.bluez";
+const ADAPTER_INTERFACE: &str = "org.bluez.Adapter1";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const GATT_CHARACTERISTIC_INTERFACE: &str = "org.bluez.GattCharacteristic1";
+const OBJECT_MANAGER_INTERFACE: &str = "org.freedesktop.DBus.ObjectManager";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+/// Bluetooth SIG company identifier assigned to Airthings
+const AIRTHINGS_COMPANY_ID: u16 = 0x0334;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+type ManagedObjects = HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>>;
+
+/// Minimal blocking BlueZ client. Talks to the `bluetoothd` daemon over the system D-Bus, so
+/// the runner only needs permission to use the bus (the `bluetooth` group on Raspberry Pi OS)
+/// instead of root.
+pub struct BluezClient {
+    connection: Connection,
+}
+
+impl BluezClient {
+    pub fn new() -> RunnerErrorResult<Self> {
+        let connection = Connection::system().map_err(|err| RunnerError::BluetoothUnavailable {
+            reason: "could not connect to the system D-Bus".to_owned(),
+            source: Some(err),
+        })?;
+
+        Ok(Self { connection })
+    }
+
+    /// Scan until a device advertising the serial number in its Airthings manufacturer data
+    /// shows up, connect to it and read the values of the characteristics with the given UUIDs,
+    /// in the same order.
+    pub fn read_characteristics(
+        &self,
+        serial_number: u32,
+        characteristic_uuids: &[&str],
+        timeout: Duration,
+    ) -> RunnerErrorResult<Vec<Vec<u8>>> {
+        let deadline = Instant::now() + timeout;
+
+        let device_path = self.discover_device(serial_number, deadline)?;
+        self.call(&device_path, DEVICE_INTERFACE, "Connect", &())?;
+
+        let values = self
+            .wait_for_services_resolved(&device_path, deadline, timeout)
+            .and_then(|_| {
+                characteristic_uuids
+                    .iter()
+                    .map(|uuid| self.read_value(&device_path, uuid))
+                    .collect()
+            });
+
+        let _best_effort = self.call(&device_path, DEVICE_INTERFACE, "Disconnect", &());
+
+        values
+    }
+
+    fn read_value(
+        &self,
+        device_path: &OwnedObjectPath,
+        characteristic_uuid: &str,
+    ) -> RunnerErrorResult<Vec<u8>> {
+        let characteristic_path = self.find_characteristic(device_path, characteristic_uuid)?;
+        let options: HashMap<&str, Value> = HashMap::new();
+
+        self.call(
+            &characteristic_path,
+            GATT_CHARACTERISTIC_INTERFACE,
+            "ReadValue",
+            &(options,),
+        )?
+        .body()
+        .deserialize::<Vec<u8>>()
+        .map_err(|err| RunnerError::parse_failure(format!("unexpected ReadValue reply: {}", err)))
+    }
+
+    fn discover_device(
+        &self,
+        serial_number: u32,
+        deadline: Instant,
+    ) -> RunnerErrorResult<OwnedObjectPath> {
+        // BlueZ keeps devices from earlier scans around, so a known device is found without
+        // starting a new discovery.
+        if let Some(device_path) = self.find_device(serial_number)? {
+            return Ok(device_path);
+        }
+
+        let adapter_path = self.find_adapter()?;
+        self.call(&adapter_path, ADAPTER_INTERFACE, "StartDiscovery", &())?;
+
+        let device_path = loop {
+            if let Some(device_path) = self.find_device(serial_number)? {
+                break Ok(device_path);
+            }
+            if Instant::now() >= deadline {
+                break Err(RunnerError::DeviceNotFound { serial_number });
+            }
+            sleep(POLL_INTERVAL);
+        };
+
+        let _best_effort = self.call(&adapter_path, ADAPTER_INTERFACE, "StopDiscovery", &());
+
+        device_path
+    }
+
+    fn find_adapter(&self) -> RunnerErrorResult<OwnedObjectPath> {
+        self.managed_objects()?
+            .into_iter()
+            .filter(|(_, interfaces)| interfaces.contains_key(ADAPTER_INTERFACE))
+            .map(|(path, _)| path)
+            .min_by(|a, b| a.as_str().cmp(b.as_str()))
+            .ok_or_else(|| RunnerError::BluetoothUnavailable {
+                reason: "no Bluetooth adapter found".to_owned(),
+                source: None,
+            })
+    }
+
+    fn find_device(&self, serial_number: u32) -> RunnerErrorResult<Option<OwnedObjectPath>> {
+        let device_path = self
+            .managed_objects()?
+            .into_iter()
+            .find(|(_, interfaces)| {
+                interfaces
+                    .get(DEVICE_INTERFACE)
+                    .and_then(|properties| properties.get("ManufacturerData"))
+                    .and_then(airthings_manufacturer_data)
+                    .and_then(|data| parse_serial_number(&data))
+                    == Some(serial_number)
+            })
+            .map(|(path, _)| path);
+
+        Ok(device_path)
+    }
+
+    fn find_characteristic(
+        &self,
+        device_path: &OwnedObjectPath,
+        characteristic_uuid: &str,
+    ) -> RunnerErrorResult<OwnedObjectPath> {
+        let device_prefix = format!("{}/", device_path.as_str());
+
+        self.managed_objects()?
+            .into_iter()
+            .filter(|(path, _)| path.as_str().starts_with(&device_prefix))
+            .find(|(_, interfaces)| {
+                interfaces
+                    .get(GATT_CHARACTERISTIC_INTERFACE)
+                    .and_then(|properties| properties.get("UUID"))
+                    .and_then(|uuid| <&str>::try_from(&**uuid).ok())
+                    .map(|uuid| uuid.eq_ignore_ascii_case(characteristic_uuid))
+                    .unwrap_or(false)
+            })
+            .map(|(path, _)| path)
+            .ok_or_else(|| RunnerError::Bluetooth {
+                operation: format!("finding characteristic {}", characteristic_uuid),
+                source: None,
+            })
+    }
+
+    fn wait_for_services_resolved(
+        &self,
+        device_path: &OwnedObjectPath,
+        deadline: Instant,
+        timeout: Duration,
+    ) -> RunnerErrorResult<()> {
+        loop {
+            let services_resolved = self
+                .call(
+                    device_path,
+                    PROPERTIES_INTERFACE,
+                    "Get",
+                    &(DEVICE_INTERFACE, "ServicesResolved"),
+                )?
+                .body()
+                .deserialize::<OwnedValue>()
+                .ok()
+                .and_then(|value| bool::try_from(value).ok())
+                .unwrap_or(false);
+
+            if services_resolved {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(RunnerError::ReaderTimeout {
+                    operation: "waiting for the device services to resolve".to_owned(),
+                    timeout,
+                });
+            }
+            sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn managed_objects(&self) -> RunnerErrorResult<ManagedObjects> {
+        self.connection
+            .call_method(
+                Some(BLUEZ_SERVICE),
+                "/",
+                Some(OBJECT_MANAGER_INTERFACE),
+                "GetManagedObjects",
+                &(),
+            )
+            .map_err(|err| RunnerError::BluetoothUnavailable {
+                reason: "could not list the BlueZ objects".to_owned(),
+                source: Some(err),
+            })?
+            .body()
+            .deserialize::<ManagedObjects>()
+            .map_err(|err| {
+                RunnerError::parse_failure(format!("unexpected BlueZ object list: {}", err))
+            })
+    }
+
+    fn call<B>(
+        &self,
+        path: &OwnedObjectPath,
+        interface: &str,
+        method: &str,
+        body: &B,
+    ) -> RunnerErrorResult<zbus::Message>
+    where
+        B: serde::ser::Serialize + zbus::zvariant::DynamicType,
+    {
+        self.connection
+            .call_method(Some(BLUEZ_SERVICE), path, Some(interface), method, body)
+            .map_err(|err| RunnerError::Bluetooth {
+                operation: format!("{}.{} on {}", interface, method, path.as_str()),
+                source: Some(err),
+            })
+    }
+}
+
+/// Extract the Airthings payload from the `ManufacturerData` (`a{qv}`) property of a device.
+fn airthings_manufacturer_data(manufacturer_data: &OwnedValue) -> Option<Vec<u8>> {
+    let entries = match &**manufacturer_data {
+        Value::Dict(dict) => dict,
+        _ => return None,
+    };
+
+    entries
+        .iter()
+        .find(|(company_id, _)| u16::try_from(*company_id).ok() == Some(AIRTHINGS_COMPANY_ID))
+        .and_then(|(_, data)| {
+            let data = match data {
+                Value::Value(inner) => inner.as_ref(),
+                data => data,
+            };
+            match data {
+                Value::Array(array) => array
+                    .iter()
+                    .map(|byte| u8::try_from(byte).ok())
+                    .collect::<Option<Vec<u8>>>(),
+                _ => None,
+            }
+        })
+}
+
+/// The serial number is the first four bytes (little endian) of the Airthings manufacturer data.
+pub fn parse_serial_number(manufacturer_data: &[u8]) -> Option<u32> {
+    match manufacturer_data {
+        [b0, b1, b2, b3, ..] => Some(u32::from_le_bytes([*b0, *b1, *b2, *b3])),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_serial_number() {
+        // Recorded manufacturer data from a Wave Plus with serial number 2930027508
+        let manufacturer_data = [0xf4, 0xab, 0xa4, 0xae, 0x09, 0x00];
+
+        assert_eq!(parse_serial_number(&manufacturer_data), Some(2930027508));
+        assert_eq!(parse_serial_number(&manufacturer_data[..3]), None);
+    }
+}
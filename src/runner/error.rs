@@ -1,66 +1,202 @@
+use crate::device::error::DecodeError;
+use crate::shared::storage::error::StorageError;
 use std::error::Error;
 use std::fmt;
-use std::num::ParseFloatError;
+use std::net::SocketAddr;
+use std::time::Duration;
 
 pub type RunnerErrorResult<T> = Result<T, RunnerError>;
 
-#[derive(Debug, Clone)]
-pub struct RunnerError {
-    pub message: Option<String>,
+#[derive(Debug)]
+pub enum RunnerError {
+    /// No D-Bus connection to BlueZ or no Bluetooth adapter
+    BluetoothUnavailable {
+        reason: String,
+        source: Option<zbus::Error>,
+    },
+    /// A BlueZ call failed after the connection to the adapter was made
+    Bluetooth {
+        operation: String,
+        source: Option<zbus::Error>,
+    },
+    DeviceNotFound {
+        serial_number: u32,
+    },
+    ReaderTimeout {
+        operation: String,
+        timeout: Duration,
+    },
+    /// A BlueZ reply could not be parsed
+    ParseFailure {
+        message: String,
+    },
+    /// The values of the device don't match the layout of its model
+    Decode(DecodeError),
+    Io(std::io::Error),
+    /// A reading could not be added to the data files or the database
+    Storage(StorageError),
+    /// The `/metrics` endpoint could not be started
+    MetricsServer {
+        address: SocketAddr,
+        message: String,
+    },
+    /// All read attempts for a device failed. The source is the error of the last attempt.
+    ReadFailed {
+        serial_number: u32,
+        attempts: u8,
+        source: Box<RunnerError>,
+    },
 }
 
-impl Default for RunnerError {
-    fn default() -> Self {
-        Self { message: None }
+impl<T> From<RunnerError> for RunnerErrorResult<T> {
+    fn from(error: RunnerError) -> Self {
+        Err(error)
     }
 }
 
 impl RunnerError {
-    pub fn new(message: String) -> Self {
-        Self {
-            message: Some(message),
+    pub fn parse_failure(message: String) -> Self {
+        RunnerError::ParseFailure { message }
+    }
+
+    /// The error that caused the failure, skipping the retry wrapper
+    pub fn root_kind(&self) -> &RunnerError {
+        match self {
+            RunnerError::ReadFailed { source, .. } => source.root_kind(),
+            error => error,
         }
     }
-}
 
-impl<T> Into<RunnerErrorResult<T>> for RunnerError {
-    fn into(self) -> RunnerErrorResult<T> {
-        Err(self)
+    /// Process exit code for the `runner` command
+    pub fn exit_code(&self) -> i32 {
+        match self.root_kind() {
+            RunnerError::BluetoothUnavailable { .. } => 10,
+            RunnerError::Bluetooth { .. } => 11,
+            RunnerError::DeviceNotFound { .. } => 12,
+            RunnerError::ReaderTimeout { .. } => 13,
+            RunnerError::ParseFailure { .. } | RunnerError::Decode(_) => 14,
+            RunnerError::Io(_) | RunnerError::Storage(_) => 15,
+            RunnerError::MetricsServer { .. } => 16,
+            RunnerError::ReadFailed { .. } => 1,
+        }
     }
 }
 
 impl From<std::io::Error> for RunnerError {
-    fn from(_: std::io::Error) -> Self {
-        RunnerError::default()
+    fn from(error: std::io::Error) -> Self {
+        RunnerError::Io(error)
     }
 }
 
-impl From<&mut std::io::Error> for RunnerError {
-    fn from(_: &mut std::io::Error) -> Self {
-        RunnerError::default()
+impl From<DecodeError> for RunnerError {
+    fn from(error: DecodeError) -> Self {
+        RunnerError::Decode(error)
     }
 }
 
-impl From<std::str::Utf8Error> for RunnerError {
-    fn from(_: std::str::Utf8Error) -> Self {
-        RunnerError::default()
+impl fmt::Display for RunnerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunnerError::BluetoothUnavailable { reason, .. } => write!(
+                f,
+                "Bluetooth is not available: {}. Is bluetoothd running and is the user allowed to use it (e.g. in the `bluetooth` group)?",
+                reason
+            ),
+            RunnerError::Bluetooth { operation, .. } => {
+                write!(f, "Bluetooth operation failed: {}", operation)
+            }
+            RunnerError::DeviceNotFound { serial_number } => write!(
+                f,
+                "Could not find device with serial number {}. Check the serial number under the back plate and that the device is in range",
+                serial_number
+            ),
+            RunnerError::ReaderTimeout { operation, timeout } => write!(
+                f,
+                "Timed out after {}s while {}",
+                timeout.as_secs(),
+                operation
+            ),
+            RunnerError::ParseFailure { message } => {
+                write!(f, "Could not parse sensor data: {}", message)
+            }
+            RunnerError::Decode(source) => write!(f, "Could not decode sensor data: {}", source),
+            RunnerError::Io(_) => write!(f, "Could not read or write the data files"),
+            RunnerError::Storage(source) => write!(f, "Could not store the sensor data: {}", source),
+            RunnerError::MetricsServer { address, message } => write!(
+                f,
+                "Could not serve the metrics on {}: {}",
+                address, message
+            ),
+            RunnerError::ReadFailed {
+                serial_number,
+                attempts,
+                ..
+            } => write!(
+                f,
+                "Failed to read device {} after {} attempts",
+                serial_number, attempts
+            ),
+        }
     }
 }
 
-impl From<ParseFloatError> for RunnerError {
-    fn from(_: ParseFloatError) -> Self {
-        RunnerError::new("parse float error".to_string())
+impl Error for RunnerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RunnerError::BluetoothUnavailable { source, .. }
+            | RunnerError::Bluetooth { source, .. } => source
+                .as_ref()
+                .map(|source| source as &(dyn Error + 'static)),
+            RunnerError::Io(source) => Some(source),
+            RunnerError::Storage(source) => Some(source),
+            RunnerError::Decode(source) => Some(source),
+            RunnerError::ReadFailed { source, .. } => Some(source.as_ref()),
+            RunnerError::DeviceNotFound { .. }
+            | RunnerError::ReaderTimeout { .. }
+            | RunnerError::ParseFailure { .. }
+            | RunnerError::MetricsServer { .. } => None,
+        }
     }
 }
 
-impl fmt::Display for RunnerError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(message) = &self.message {
-            write!(f, "[RunnerError]! {}", message)
-        } else {
-            write!(f, "Runner error without a message")
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_failed_keeps_the_cause() {
+        let error = RunnerError::ReadFailed {
+            serial_number: 2930027508,
+            attempts: 4,
+            source: Box::new(RunnerError::DeviceNotFound {
+                serial_number: 2930027508,
+            }),
+        };
+
+        assert!(matches!(
+            error.root_kind(),
+            RunnerError::DeviceNotFound { .. }
+        ));
+        assert_eq!(error.exit_code(), 12);
+        assert!(error
+            .source()
+            .unwrap()
+            .to_string()
+            .contains("serial number 2930027508"));
     }
-}
 
-impl Error for RunnerError {}
+    #[test]
+    fn test_decode_error_is_the_source() {
+        let error = RunnerError::from(DecodeError::ValueCount {
+            expected: 1,
+            actual: 2,
+        });
+
+        assert_eq!(error.exit_code(), 14);
+        assert!(error
+            .source()
+            .unwrap()
+            .downcast_ref::<DecodeError>()
+            .is_some());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::SensorData;
+use crate::shared::types::sensor_quality::SensorQuality;
+use log::{info, warn};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use tiny_http::{Header, Method, Response, Server};
+
+const METRIC_PREFIX: &str = "air_quality";
+
+/// Counters and the latest reading of every device, shared between the runner loop and the
+/// `/metrics` endpoint
+pub struct RunnerMetrics {
+    devices: Mutex<BTreeMap<u32, DeviceMetrics>>,
+}
+
+#[derive(Default)]
+struct DeviceMetrics {
+    latest: Option<SensorData>,
+    read_failures: u64,
+    bluetooth_restarts: u64,
+}
+
+impl RunnerMetrics {
+    /// The counters of the devices start at zero, so they are exported before the first failure
+    pub fn new(serial_numbers: &[u32]) -> Self {
+        let devices = serial_numbers
+            .iter()
+            .map(|&serial_number| (serial_number, DeviceMetrics::default()))
+            .collect();
+
+        Self {
+            devices: Mutex::new(devices),
+        }
+    }
+
+    pub fn record_reading(&self, serial_number: u32, sensor_data: &SensorData) {
+        self.lock().entry(serial_number).or_default().latest = Some(sensor_data.clone());
+    }
+
+    /// A single failed read attempt, also when a retry succeeds
+    pub fn record_read_failure(&self, serial_number: u32) {
+        self.lock().entry(serial_number).or_default().read_failures += 1;
+    }
+
+    pub fn record_bluetooth_restart(&self, serial_number: u32) {
+        self.lock()
+            .entry(serial_number)
+            .or_default()
+            .bluetooth_restarts += 1;
+    }
+
+    /// Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let devices = self.lock();
+        let mut output = String::new();
+
+        // A gauge per metric, devices without the sensor have no sample
+        for &metric in Metric::ALL {
+            let name = metric.sensor_key();
+            let help = format!("{} in {}", metric.name(), metric.unit());
+            write_family(&mut output, name, &help, "gauge");
+            for (serial_number, device) in devices.iter() {
+                let value = device
+                    .latest
+                    .as_ref()
+                    .and_then(|sensor_data| sensor_data.value(metric));
+                if let Some(value) = value {
+                    write_sample(&mut output, name, *serial_number, None, value);
+                }
+            }
+        }
+
+        write_family(
+            &mut output,
+            "quality_level",
+            "Quality of the latest value: 0 good, 1 bad, 2 terrible, -1 depends on the context",
+            "gauge",
+        );
+        for (serial_number, device) in devices.iter() {
+            if let Some(sensor_data) = &device.latest {
+                for &metric in Metric::ALL {
+                    if let Some(quality) = sensor_data.quality(metric) {
+                        write_sample(
+                            &mut output,
+                            "quality_level",
+                            *serial_number,
+                            Some(metric.sensor_key()),
+                            quality_level(quality),
+                        );
+                    }
+                }
+            }
+        }
+
+        write_family(
+            &mut output,
+            "last_reading_timestamp_seconds",
+            "Unix time of the latest reading",
+            "gauge",
+        );
+        for (serial_number, device) in devices.iter() {
+            if let Some(sensor_data) = &device.latest {
+                write_sample(
+                    &mut output,
+                    "last_reading_timestamp_seconds",
+                    *serial_number,
+                    None,
+                    sensor_data.timestamp().timestamp(),
+                );
+            }
+        }
+
+        write_family(
+            &mut output,
+            "read_failures_total",
+            "Failed read attempts, including the ones that were retried",
+            "counter",
+        );
+        for (serial_number, device) in devices.iter() {
+            write_sample(
+                &mut output,
+                "read_failures_total",
+                *serial_number,
+                None,
+                device.read_failures,
+            );
+        }
+
+        write_family(
+            &mut output,
+            "bluetooth_restarts_total",
+            "Bluetooth service restarts after a failed read",
+            "counter",
+        );
+        for (serial_number, device) in devices.iter() {
+            write_sample(
+                &mut output,
+                "bluetooth_restarts_total",
+                *serial_number,
+                None,
+                device.bluetooth_restarts,
+            );
+        }
+
+        output
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<u32, DeviceMetrics>> {
+        // The data stays usable when a thread panicked while holding the lock
+        self.devices
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn write_family(output: &mut String, name: &str, help: &str, metric_type: &str) {
+    let _ = writeln!(output, "# HELP {}_{} {}", METRIC_PREFIX, name, help);
+    let _ = writeln!(output, "# TYPE {}_{} {}", METRIC_PREFIX, name, metric_type);
+}
+
+fn write_sample(
+    output: &mut String,
+    name: &str,
+    serial_number: u32,
+    metric: Option<&str>,
+    value: impl std::fmt::Display,
+) {
+    let metric_label = metric
+        .map(|metric| format!(",metric=\"{}\"", metric))
+        .unwrap_or_default();
+    let _ = writeln!(
+        output,
+        "{}_{}{{serial_number=\"{}\"{}}} {}",
+        METRIC_PREFIX, name, serial_number, metric_label, value
+    );
+}
+
+fn quality_level(quality: SensorQuality) -> i8 {
+    match quality {
+        SensorQuality::Good => 0,
+        SensorQuality::Bad => 1,
+        SensorQuality::Terrible => 2,
+        SensorQuality::DependsOnContext => -1,
+    }
+}
+
+/// Serve `GET /metrics` on a background thread. Returns the address the server listens on,
+/// which differs from `address` when port 0 was given.
+pub fn start_metrics_server(
+    address: SocketAddr,
+    metrics: Arc<RunnerMetrics>,
+) -> Result<SocketAddr, String> {
+    let server = Server::http(address).map_err(|error| error.to_string())?;
+    let local_address = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| "the metrics server is not listening on an IP address".to_owned())?;
+    info!("Serving metrics on http://{}/metrics", local_address);
+
+    thread::spawn(move || {
+        let content_type = Header::from_bytes(
+            &b"Content-Type"[..],
+            &b"text/plain; version=0.0.4; charset=utf-8"[..],
+        )
+        .expect("Valid header");
+
+        for request in server.incoming_requests() {
+            let path = request.url().split('?').next().unwrap_or_default();
+            let response = match (request.method(), path) {
+                (Method::Get, "/metrics") => {
+                    Response::from_string(metrics.render()).with_header(content_type.clone())
+                }
+                _ => Response::from_string("Not found").with_status_code(404),
+            };
+
+            if let Err(error) = request.respond(response) {
+                warn!("Could not answer a metrics request: {}", error);
+            }
+        }
+    });
+
+    Ok(local_address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    /// At noon, with poor CO2 and radon
+    fn sensor_data() -> SensorData {
+        SensorData::test_reading(SensorData::test_day_start() + Duration::hours(12))
+            .with_value(Metric::Co2, 1200.0)
+            .with_value(Metric::RadonShortTermAverage, 120.0)
+            .with_value(Metric::RadonLongTermAverage, 80.0)
+    }
+
+    #[test]
+    fn test_render() {
+        let metrics = RunnerMetrics::new(&[2930027508, 2930012345]);
+        metrics.record_reading(2930027508, &sensor_data());
+        metrics.record_read_failure(2930012345);
+        metrics.record_read_failure(2930012345);
+        metrics.record_bluetooth_restart(2930012345);
+
+        let output = metrics.render();
+
+        assert!(output.contains("# TYPE air_quality_co2 gauge\n"));
+        assert!(output.contains("air_quality_co2{serial_number=\"2930027508\"} 1200\n"));
+        assert!(output.contains("air_quality_radon_lt{serial_number=\"2930027508\"} 80\n"));
+        assert!(output.contains(
+            "air_quality_quality_level{serial_number=\"2930027508\",metric=\"co2\"} 2\n"
+        ));
+        assert!(output.contains(
+            "air_quality_quality_level{serial_number=\"2930027508\",metric=\"radon_st\"} 1\n"
+        ));
+        assert!(output.contains(
+            "air_quality_last_reading_timestamp_seconds{serial_number=\"2930027508\"} 1588334400\n"
+        ));
+        assert!(output.contains("# TYPE air_quality_read_failures_total counter\n"));
+        assert!(
+            output.contains("air_quality_read_failures_total{serial_number=\"2930012345\"} 2\n")
+        );
+        assert!(
+            output.contains("air_quality_read_failures_total{serial_number=\"2930027508\"} 0\n")
+        );
+        assert!(output
+            .contains("air_quality_bluetooth_restarts_total{serial_number=\"2930012345\"} 1\n"));
+        // No reading yet, so no gauges
+        assert!(!output.contains("air_quality_co2{serial_number=\"2930012345\"}"));
+    }
+
+    #[test]
+    fn test_metrics_endpoint() {
+        let metrics = Arc::new(RunnerMetrics::new(&[2930027508]));
+        metrics.record_reading(2930027508, &sensor_data());
+
+        let address = start_metrics_server("127.0.0.1:0".parse().unwrap(), metrics).unwrap();
+
+        let response = ureq::get(&format!("http://{}/metrics", address))
+            .call()
+            .unwrap();
+        assert!(response.content_type().starts_with("text/plain"));
+        let body = response.into_string().unwrap();
+        assert!(body.contains("air_quality_temperature{serial_number=\"2930027508\"} 21\n"));
+
+        let not_found = ureq::get(&format!("http://{}/", address)).call();
+        assert!(matches!(not_found, Err(ureq::Error::Status(404, _))));
+    }
+}
@@ -1,20 +1,20 @@
 use crate::runner::error::RunnerErrorResult;
-use std::path::PathBuf;
+use crate::runner::runner_loop::{Runner, RunnerBuilder};
 
 mod bluetooth;
+mod bluez;
 pub mod error;
-mod file_io;
-mod parser;
-mod python_dependency;
+pub mod metrics;
+pub mod mqtt;
 pub mod runner_loop;
 mod sensor_io;
+pub mod status;
 
-pub fn start_data_generator(data_dir_path: PathBuf, serial_number: u32) -> RunnerErrorResult<()> {
-    Ok(run(data_dir_path, serial_number)?)
+pub fn start_data_generator(builder: RunnerBuilder) -> RunnerErrorResult<()> {
+    run(builder.into())
 }
 
-pub fn run(data_dir_path: PathBuf, serial_number: u32) -> RunnerErrorResult<()> {
-    let runner = runner_loop::Runner::new(data_dir_path, serial_number)?;
+pub fn run(mut runner: Runner) -> RunnerErrorResult<()> {
     runner.run()?;
     Ok(())
 }
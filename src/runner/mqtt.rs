@@ -0,0 +1,416 @@
+use crate::device::{device_of_serial_number, metrics_of_serial_number};
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::SensorData;
+use log::{debug, info, warn};
+use rumqttc::{Client, Event, LastWill, MqttOptions, Outgoing, Packet, QoS};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Wait before connecting again after the broker could not be reached
+#[cfg(not(test))]
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+#[cfg(test)]
+const RECONNECT_DELAY: Duration = Duration::from_millis(100);
+
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
+/// Publishes the readings to an MQTT broker, one retained topic per device and sensor:
+/// `<topic prefix>/<serial number>/<sensor>`. The connection is kept up by a background thread,
+/// so publishing never blocks the runner. Readings are buffered while the broker is down and
+/// sent after the reconnect, until the buffer is full. Dropping the publisher marks the devices
+/// offline and disconnects.
+pub struct MqttPublisher {
+    client: Client,
+    topic_prefix: String,
+    availability_topic: String,
+    /// Tells the connection thread to give up reconnecting
+    stopping: Arc<AtomicBool>,
+}
+
+pub struct MqttPublisherBuilder {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// User name and password
+    pub credentials: Option<(String, String)>,
+    pub topic_prefix: String,
+    /// Home Assistant discovery prefix. No discovery payloads are sent when `None`.
+    pub discovery_prefix: Option<String>,
+    /// The devices that are announced to Home Assistant
+    pub serial_numbers: Vec<u32>,
+    /// Number of messages kept while the broker can't be reached
+    pub buffer_size: usize,
+}
+
+impl From<MqttPublisherBuilder> for MqttPublisher {
+    fn from(builder: MqttPublisherBuilder) -> Self {
+        Self::new(builder)
+    }
+}
+
+/// A message that is sent again after every reconnect
+struct RetainedMessage {
+    topic: String,
+    payload: String,
+}
+
+impl MqttPublisher {
+    pub fn new(builder: MqttPublisherBuilder) -> MqttPublisher {
+        let MqttPublisherBuilder {
+            host,
+            port,
+            client_id,
+            credentials,
+            topic_prefix,
+            discovery_prefix,
+            serial_numbers,
+            buffer_size,
+        } = builder;
+
+        let availability_topic = format!("{}/status", topic_prefix);
+        let mut options = MqttOptions::new(client_id, host.clone(), port);
+        options
+            .set_keep_alive(Duration::from_secs(30))
+            .set_last_will(LastWill::new(
+                availability_topic.clone(),
+                OFFLINE,
+                QoS::AtLeastOnce,
+                true,
+            ));
+        if let Some((username, password)) = credentials {
+            options.set_credentials(username, password);
+        }
+
+        let mut announcements = vec![RetainedMessage {
+            topic: availability_topic.clone(),
+            payload: ONLINE.to_owned(),
+        }];
+        if let Some(discovery_prefix) = discovery_prefix {
+            for &serial_number in &serial_numbers {
+                announcements.extend(discovery_messages(
+                    &discovery_prefix,
+                    &topic_prefix,
+                    &availability_topic,
+                    serial_number,
+                ));
+            }
+        }
+
+        let (client, mut connection) = Client::new(options, buffer_size.max(1));
+        let connection_client = client.clone();
+        let stopping = Arc::new(AtomicBool::new(false));
+        let connection_stopping = Arc::clone(&stopping);
+        let broker = format!("{}:{}", host, port);
+
+        thread::spawn(move || {
+            let mut connected = false;
+
+            // Iterating reconnects after a failure. The request channel stays open as long as the
+            // thread holds a client, so the loop ends on the disconnect of the dropped publisher,
+            // or on the next failure after it was dropped.
+            for event in connection.iter() {
+                match event {
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        info!("Connected to the MQTT broker {}", broker);
+                        connected = true;
+                        // Sent on every connect, the broker may have lost its retained messages
+                        for message in &announcements {
+                            publish(&connection_client, &message.topic, message.payload.clone());
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(_) if connection_stopping.load(Ordering::Relaxed) => break,
+                    Err(error) => {
+                        if connected {
+                            warn!(
+                                "Lost the connection to the MQTT broker {}: {}. Buffering the readings until it is back",
+                                broker, error
+                            );
+                        } else {
+                            debug!("Could not connect to the MQTT broker {}: {}", broker, error);
+                        }
+                        connected = false;
+                        thread::sleep(RECONNECT_DELAY);
+                    }
+                }
+            }
+        });
+
+        MqttPublisher {
+            client,
+            topic_prefix,
+            availability_topic,
+            stopping,
+        }
+    }
+
+    /// Queue the values of a reading without waiting for the broker. Sensors the device
+    /// doesn't have are skipped.
+    pub fn publish_reading(&self, serial_number: u32, sensor_data: &SensorData) {
+        for &metric in Metric::ALL {
+            if let Some(value) = sensor_data.value(metric) {
+                publish(
+                    &self.client,
+                    &state_topic(&self.topic_prefix, serial_number, metric.sensor_key()),
+                    value.to_string(),
+                );
+            }
+        }
+    }
+}
+
+impl Drop for MqttPublisher {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::Relaxed);
+        // The broker only sends the last will when the connection is lost
+        publish(&self.client, &self.availability_topic, OFFLINE.to_owned());
+        if let Err(error) = self.client.try_disconnect() {
+            debug!("Could not disconnect from the MQTT broker: {}", error);
+        }
+    }
+}
+
+fn publish(client: &Client, topic: &str, payload: String) {
+    if let Err(error) = client.try_publish(topic, QoS::AtLeastOnce, true, payload) {
+        warn!("Dropped the MQTT message for {}: {}", topic, error);
+    }
+}
+
+fn state_topic(topic_prefix: &str, serial_number: u32, key: &str) -> String {
+    format!("{}/{}/{}", topic_prefix, serial_number, key)
+}
+
+/// Home Assistant MQTT discovery config of every sensor the model of the device has
+fn discovery_messages(
+    discovery_prefix: &str,
+    topic_prefix: &str,
+    availability_topic: &str,
+    serial_number: u32,
+) -> Vec<RetainedMessage> {
+    let device_id = format!("air_quality_{}", serial_number);
+    let device = device_of_serial_number(serial_number);
+    let model = device.map(|device| device.name()).unwrap_or("Unknown");
+
+    metrics_of_serial_number(serial_number)
+        .iter()
+        .map(|metric| {
+            let key = metric.sensor_key();
+            let mut payload = serde_json::json!({
+                "name": metric.name(),
+                "unique_id": format!("{}_{}", device_id, key),
+                "state_topic": state_topic(topic_prefix, serial_number, key),
+                "availability_topic": availability_topic,
+                "unit_of_measurement": metric.home_assistant_unit(),
+                "state_class": "measurement",
+                "device": {
+                    "identifiers": [device_id],
+                    "name": format!("Airthings {}", serial_number),
+                    "manufacturer": "Airthings",
+                    "model": model,
+                    "serial_number": serial_number.to_string(),
+                },
+            });
+            match metric.home_assistant_device_class() {
+                Some(device_class) => payload["device_class"] = device_class.into(),
+                None => payload["icon"] = "mdi:radioactive".into(),
+            }
+
+            RetainedMessage {
+                topic: format!("{}/sensor/{}/{}/config", discovery_prefix, device_id, key),
+                payload: payload.to_string(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+    use std::time::Instant;
+
+    const SERIAL_NUMBER: u32 = 2930027508;
+
+    #[derive(Debug)]
+    struct ReceivedMessage {
+        topic: String,
+        payload: String,
+        retain: bool,
+    }
+
+    /// Stand-in broker that accepts one client, acknowledges its packets and passes on the
+    /// published messages
+    fn start_broker(listener: TcpListener) -> Receiver<ReceivedMessage> {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let _ = serve_client(stream, sender);
+        });
+
+        receiver
+    }
+
+    fn serve_client(mut stream: TcpStream, sender: Sender<ReceivedMessage>) -> std::io::Result<()> {
+        loop {
+            let mut fixed_header = [0; 1];
+            stream.read_exact(&mut fixed_header)?;
+            let mut remaining_length = 0;
+            for shift in (0..).step_by(7).take(4) {
+                let mut byte = [0; 1];
+                stream.read_exact(&mut byte)?;
+                remaining_length |= ((byte[0] & 0x7f) as usize) << shift;
+                if byte[0] & 0x80 == 0 {
+                    break;
+                }
+            }
+            let mut body = vec![0; remaining_length];
+            stream.read_exact(&mut body)?;
+
+            match fixed_header[0] >> 4 {
+                // CONNECT
+                1 => stream.write_all(&[0x20, 0x02, 0x00, 0x00])?,
+                // PUBLISH
+                3 => {
+                    let qos = (fixed_header[0] >> 1) & 0x03;
+                    let topic_length = u16::from_be_bytes([body[0], body[1]]) as usize;
+                    let topic = String::from_utf8_lossy(&body[2..2 + topic_length]).to_string();
+                    let mut payload_start = 2 + topic_length;
+                    if qos > 0 {
+                        let packet_id = &body[payload_start..payload_start + 2];
+                        stream.write_all(&[0x40, 0x02, packet_id[0], packet_id[1]])?;
+                        payload_start += 2;
+                    }
+                    let message = ReceivedMessage {
+                        topic,
+                        payload: String::from_utf8_lossy(&body[payload_start..]).to_string(),
+                        retain: fixed_header[0] & 0x01 == 1,
+                    };
+                    if sender.send(message).is_err() {
+                        return Ok(());
+                    }
+                }
+                // PINGREQ
+                12 => stream.write_all(&[0xd0, 0x00])?,
+                _ => {}
+            }
+        }
+    }
+
+    fn publisher(port: u16) -> MqttPublisher {
+        MqttPublisherBuilder {
+            host: "127.0.0.1".to_owned(),
+            port,
+            client_id: "air-quality-cli-test".to_owned(),
+            credentials: None,
+            topic_prefix: "air-quality".to_owned(),
+            discovery_prefix: Some("homeassistant".to_owned()),
+            serial_numbers: vec![SERIAL_NUMBER],
+            buffer_size: 100,
+        }
+        .into()
+    }
+
+    fn sensor_data() -> SensorData {
+        SensorData::test_reading(SensorData::test_day_start())
+            .with_value(Metric::Temperature, 22.5)
+            .with_value(Metric::RadonShortTermAverage, 42.0)
+            .with_value(Metric::RadonLongTermAverage, 38.0)
+    }
+
+    /// Messages until the one for `topic` arrived
+    fn receive_until(receiver: &Receiver<ReceivedMessage>, topic: &str) -> Vec<ReceivedMessage> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut messages = Vec::new();
+        while messages
+            .last()
+            .is_none_or(|message: &ReceivedMessage| message.topic != topic)
+        {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            messages.push(
+                receiver
+                    .recv_timeout(timeout)
+                    .unwrap_or_else(|_| panic!("No message for {}", topic)),
+            );
+        }
+
+        messages
+    }
+
+    #[test]
+    fn test_discovery_and_readings() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let receiver = start_broker(listener);
+        let publisher = publisher(port);
+
+        let messages = receive_until(
+            &receiver,
+            "homeassistant/sensor/air_quality_2930027508/radon_lt/config",
+        );
+        assert_eq!(messages[0].topic, "air-quality/status");
+        assert_eq!(messages[0].payload, "online");
+        let radon: serde_json::Value =
+            serde_json::from_str(&messages.last().unwrap().payload).unwrap();
+        assert_eq!(radon["unit_of_measurement"], "Bq/m³");
+        assert_eq!(radon["state_topic"], "air-quality/2930027508/radon_lt");
+        assert!(messages.iter().all(|message| message.retain));
+        assert_eq!(messages.len(), 1 + Metric::COUNT);
+
+        publisher.publish_reading(SERIAL_NUMBER, &sensor_data());
+
+        let messages = receive_until(&receiver, "air-quality/2930027508/radon_lt");
+        let values: Vec<(&str, &str)> = messages
+            .iter()
+            .map(|message| (message.topic.as_str(), message.payload.as_str()))
+            .collect();
+        assert_eq!(values[0], ("air-quality/2930027508/temperature", "22.5"));
+        assert_eq!(values[3], ("air-quality/2930027508/co2", "500"));
+        assert_eq!(values[6], ("air-quality/2930027508/radon_lt", "38"));
+    }
+
+    #[test]
+    fn test_readings_are_buffered_until_the_broker_is_up() {
+        // Find a free port, and publish while nothing listens on it
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let publisher = publisher(port);
+        publisher.publish_reading(SERIAL_NUMBER, &sensor_data());
+        thread::sleep(RECONNECT_DELAY * 3);
+
+        let receiver = start_broker(TcpListener::bind(("127.0.0.1", port)).unwrap());
+
+        let messages = receive_until(&receiver, "air-quality/2930027508/radon_st");
+        assert_eq!(messages.last().unwrap().payload, "42");
+    }
+
+    #[test]
+    fn test_drop_marks_offline_and_disconnects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let receiver = start_broker(listener);
+        let publisher = publisher(port);
+        receive_until(
+            &receiver,
+            "homeassistant/sensor/air_quality_2930027508/radon_lt/config",
+        );
+
+        drop(publisher);
+
+        let messages = receive_until(&receiver, "air-quality/status");
+        assert_eq!(messages.last().unwrap().payload, "offline");
+        // The stand-in broker stops once the client closed the connection
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(10)).unwrap_err(),
+            RecvTimeoutError::Disconnected
+        );
+    }
+}
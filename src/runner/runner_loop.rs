@@ -1,86 +1,356 @@
-use crate::runner::error::RunnerErrorResult;
-use crate::runner::file_io::create_or_append_sensor_data_file;
-use crate::runner::python_dependency::create_python_file;
+use crate::alert::alerter::Alerter;
+use crate::device::{device_of_serial_number, device_or_wave_plus, Device};
+use crate::runner::error::{RunnerError, RunnerErrorResult};
+use crate::runner::metrics::{start_metrics_server, RunnerMetrics};
+use crate::runner::mqtt::MqttPublisher;
 use crate::runner::sensor_io::fetch_sensor_data;
-use crate::shared::types::sensor_data::SensorData;
-use log::info;
-use std::path::{Path, PathBuf};
+use crate::runner::status::{write_status_file, RunnerStatus, StatusRecord};
+use crate::shared::storage::SensorDataStore;
+use chrono::{DateTime, TimeZone, Utc};
+use log::{error, info, warn};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::thread::sleep;
 use std::time::Duration;
 
+/// Wait before the first retry of a device that failed all its read attempts
+const INITIAL_FAILURE_BACKOFF: Duration = Duration::from_secs(30);
+/// Upper limit for the exponential backoff of a failing device
+const MAX_FAILURE_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// Polls every device in turn from a single thread, so only one device uses the Bluetooth
+/// adapter at a time.
 pub struct Runner {
     output_dir_path: PathBuf,
-    python_executable: tempfile::NamedTempFile,
-    device_serial_number: u32,
+    store: Box<dyn SensorDataStore>,
+    device_serial_numbers: Vec<u32>,
+    poll_interval: Duration,
+    read_timeout: Duration,
+    max_retries: u8,
+    max_consecutive_failures: Option<u32>,
+    alerter: Option<Alerter>,
+    metrics: Arc<RunnerMetrics>,
+    metrics_address: Option<SocketAddr>,
+    mqtt_publisher: Option<MqttPublisher>,
 }
 
-impl Runner {
-    pub fn new(output_dir_path: PathBuf, serial_number: u32) -> RunnerErrorResult<Runner> {
-        let python_executable = create_python_file()?;
+pub struct RunnerBuilder {
+    /// Status files are always written to this dir
+    pub output_dir_path: PathBuf,
+    /// Where the readings are appended
+    pub store: Box<dyn SensorDataStore>,
+    pub serial_numbers: Vec<u32>,
+    pub poll_interval: Duration,
+    pub read_timeout: Duration,
+    pub max_retries: u8,
+    /// Stop the runner when a device has failed this many polls in a row. Keep running forever
+    /// when `None`.
+    pub max_consecutive_failures: Option<u32>,
+    /// Checks every new reading for alerts. No alerts are sent when `None`.
+    pub alerter: Option<Alerter>,
+    /// Serve the latest readings and the failure counters on `http://<address>/metrics`
+    pub metrics_address: Option<SocketAddr>,
+    /// Also publish every reading to an MQTT broker
+    pub mqtt_publisher: Option<MqttPublisher>,
+}
 
-        Ok(Runner {
-            output_dir_path,
-            python_executable,
-            device_serial_number: serial_number,
-        })
+impl From<RunnerBuilder> for Runner {
+    fn from(builder: RunnerBuilder) -> Self {
+        Self::new(builder)
     }
+}
 
-    fn python_executable_path(&self) -> &Path {
-        &self.python_executable.path()
+/// Schedule of a single device
+struct DeviceState {
+    serial_number: u32,
+    device: &'static dyn Device,
+    next_read_at: DateTime<Utc>,
+    consecutive_failures: u32,
+}
+
+impl Runner {
+    pub fn new(builder: RunnerBuilder) -> Runner {
+        let RunnerBuilder {
+            output_dir_path,
+            store,
+            serial_numbers,
+            poll_interval,
+            read_timeout,
+            max_retries,
+            max_consecutive_failures,
+            alerter,
+            metrics_address,
+            mqtt_publisher,
+        } = builder;
+
+        Runner {
+            metrics: Arc::new(RunnerMetrics::new(&serial_numbers)),
+            output_dir_path,
+            store,
+            device_serial_numbers: serial_numbers,
+            poll_interval,
+            read_timeout,
+            max_retries,
+            max_consecutive_failures,
+            alerter,
+            metrics_address,
+            mqtt_publisher,
+        }
     }
 
-    pub fn run(&self) -> RunnerErrorResult<()> {
+    pub fn run(&mut self) -> RunnerErrorResult<()> {
         info!(
             "Running Airthings sensor data for devices with serial number: {:?}",
-            self.device_serial_number
+            self.device_serial_numbers
         );
+        info!("Sensor data will be added to: {:?}", self.store);
         info!(
-            "Sensor data will be added to dir: {:?}",
-            self.output_dir_path
+            "Polling every {}s, read timeout {}s, max retries {}",
+            self.poll_interval.as_secs(),
+            self.read_timeout.as_secs(),
+            self.max_retries
         );
-        let device_serial_number = self.device_serial_number;
+
+        if let Some(address) = self.metrics_address {
+            start_metrics_server(address, self.metrics.clone())
+                .map_err(|message| RunnerError::MetricsServer { address, message })?;
+        }
+
+        let start = Utc::now();
+        let mut device_states: Vec<DeviceState> = self
+            .device_serial_numbers
+            .iter()
+            .map(|&serial_number| {
+                let device = device_or_wave_plus(serial_number);
+                match device_of_serial_number(serial_number) {
+                    Some(_) => info!("Device {} is a {}", serial_number, device.name()),
+                    None => warn!(
+                        "Device {} is of an unknown model, reading it as a {}",
+                        serial_number,
+                        device.name()
+                    ),
+                }
+
+                DeviceState {
+                    serial_number,
+                    device,
+                    next_read_at: start,
+                    consecutive_failures: 0,
+                }
+            })
+            .collect();
+
         loop {
-            let sensor_data_raw =
-                fetch_sensor_data(self.python_executable_path(), device_serial_number);
+            for device_state in device_states.iter_mut() {
+                if device_state.next_read_at > Utc::now() {
+                    continue;
+                }
 
-            if let Err(error) = &sensor_data_raw {
+                if let Err(error) = self.poll_device(device_state) {
+                    self.record_stopped(&device_states, &error);
+                    return Err(error);
+                }
+            }
+
+            let next_wake_up = device_states
+                .iter()
+                .map(|device_state| device_state.next_read_at)
+                .min()
+                .unwrap_or_else(|| next_poll_time(Utc::now(), self.poll_interval));
+
+            if let Ok(duration) = (next_wake_up - Utc::now()).to_std() {
+                sleep(duration)
+            }
+        }
+    }
+
+    /// Read and store one device and plan its next read. A failed read is only an error when
+    /// the device has reached the max number of consecutive failures.
+    fn poll_device(&mut self, device_state: &mut DeviceState) -> RunnerErrorResult<()> {
+        let serial_number = device_state.serial_number;
+
+        match self.fetch_and_store_sensor_data(serial_number, device_state.device) {
+            Ok(()) => {
+                if device_state.consecutive_failures > 0 {
+                    info!(
+                        "[serial number: {}] Recovered after {} failed polls",
+                        serial_number, device_state.consecutive_failures
+                    );
+                }
+                device_state.consecutive_failures = 0;
+                // Read again on the next wall-clock boundary of the poll interval (e.g. :00, :05,
+                // :10 for a 5 minute interval), so the time spent reading doesn't push the schedule.
+                device_state.next_read_at = next_poll_time(Utc::now(), self.poll_interval);
+
+                self.write_status(device_state, RunnerStatus::Ok, "");
+                Ok(())
+            }
+            Err(error) => {
+                device_state.consecutive_failures += 1;
                 eprintln!(
-                    "[{}][serial number: {}] Could not fetch sensor data. Is bluetooth enabled/on? Error: {:?}",
-                    chrono::Utc::now(),
-                    device_serial_number,
+                    "[{}][serial number: {}] Could not fetch sensor data ({} failures in a row). Is bluetooth enabled/on? Error: {}",
+                    Utc::now(),
+                    serial_number,
+                    device_state.consecutive_failures,
                     error
                 );
-                return Err(error.clone());
-            }
 
-            if let Ok(sensor_data) = sensor_data_raw {
-                println!(
-                    "[serial number: {}] {}",
-                    device_serial_number,
-                    sensor_data.to_csv()
+                if let Some(max_consecutive_failures) = self.max_consecutive_failures {
+                    if device_state.consecutive_failures >= max_consecutive_failures {
+                        error!(
+                            "[serial number: {}] Giving up after {} failures in a row",
+                            serial_number, device_state.consecutive_failures
+                        );
+                        return Err(error);
+                    }
+                }
+
+                let backoff = failure_backoff(device_state.consecutive_failures);
+                device_state.next_read_at = Utc::now()
+                    + chrono::Duration::from_std(backoff).unwrap_or_else(|_| {
+                        chrono::Duration::seconds(MAX_FAILURE_BACKOFF.as_secs() as i64)
+                    });
+                warn!(
+                    "[serial number: {}] Next attempt in {}s",
+                    serial_number,
+                    backoff.as_secs()
                 );
-                self.create_or_append_sensor_data_file(sensor_data, device_serial_number)?;
+
+                self.write_status(device_state, RunnerStatus::Failing, &error.to_string());
+                Ok(())
             }
-            sleep(Duration::from_secs(60 * 5))
         }
     }
 
-    fn create_or_append_sensor_data_file(
-        &self,
-        sensor_data: SensorData,
+    fn record_stopped(&self, device_states: &[DeviceState], error: &RunnerError) {
+        for device_state in device_states {
+            self.write_status(device_state, RunnerStatus::Stopped, &error.to_string());
+        }
+    }
+
+    fn write_status(&self, device_state: &DeviceState, status: RunnerStatus, message: &str) {
+        let record = StatusRecord {
+            timestamp: Utc::now(),
+            status,
+            consecutive_failures: device_state.consecutive_failures,
+            next_attempt: match status {
+                RunnerStatus::Stopped => None,
+                _ => Some(device_state.next_read_at),
+            },
+            message: message.to_owned(),
+        };
+
+        if let Err(error) =
+            write_status_file(&self.output_dir_path, device_state.serial_number, &record)
+        {
+            warn!(
+                "[serial number: {}] Could not write the status file: {}",
+                device_state.serial_number, error
+            );
+        }
+    }
+
+    fn fetch_and_store_sensor_data(
+        &mut self,
         device_serial_number: u32,
+        device: &dyn Device,
     ) -> RunnerErrorResult<()> {
-        let filename_date_formatted = sensor_data.timestamp().format("%Y-%m-%d");
-        let string = format!(
-            "waveplus_data_sn_{}_{}.txt",
-            device_serial_number, filename_date_formatted
-        );
-        let filepath = &self.output_dir_path.join(&string);
+        let sensor_data = fetch_sensor_data(
+            device_serial_number,
+            device,
+            self.read_timeout,
+            self.max_retries,
+            &self.metrics,
+        )?;
+        self.metrics
+            .record_reading(device_serial_number, &sensor_data);
 
-        Ok(create_or_append_sensor_data_file(
-            &filepath,
-            sensor_data,
+        println!(
+            "[serial number: {}] {}",
             device_serial_number,
-        )?)
+            sensor_data.to_csv()
+        );
+
+        if let Some(mqtt_publisher) = &self.mqtt_publisher {
+            mqtt_publisher.publish_reading(device_serial_number, &sensor_data);
+        }
+        if let Some(alerter) = &mut self.alerter {
+            alerter.on_reading(device_serial_number, &sensor_data);
+        }
+
+        self.store
+            .append(device_serial_number, &sensor_data)
+            .map_err(RunnerError::Storage)
+    }
+}
+
+/// The first multiple of the interval (counted from the Unix epoch) strictly after `now`
+pub fn next_poll_time(now: DateTime<Utc>, interval: Duration) -> DateTime<Utc> {
+    let interval_millis = (interval.as_millis() as i64).max(1);
+    let now_millis = now.timestamp_millis();
+    let next_millis = (now_millis.div_euclid(interval_millis) + 1) * interval_millis;
+
+    Utc.timestamp_millis_opt(next_millis).unwrap()
+}
+
+/// Exponential backoff after the given number of consecutive failures: 30s, 1m, 2m, 4m ... 1h
+pub fn failure_backoff(consecutive_failures: u32) -> Duration {
+    let exponent = consecutive_failures.saturating_sub(1).min(16);
+    INITIAL_FAILURE_BACKOFF
+        .checked_mul(1 << exponent)
+        .unwrap_or(MAX_FAILURE_BACKOFF)
+        .min(MAX_FAILURE_BACKOFF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_poll_time_on_wall_clock_boundary() {
+        let interval = Duration::from_secs(60 * 5);
+
+        let now = Utc.with_ymd_and_hms(2020, 5, 1, 12, 3, 27).unwrap()
+            + chrono::Duration::milliseconds(450);
+        assert_eq!(
+            next_poll_time(now, interval),
+            Utc.with_ymd_and_hms(2020, 5, 1, 12, 5, 0).unwrap()
+        );
+
+        // Exactly on a boundary the next one is a full interval away
+        let now = Utc.with_ymd_and_hms(2020, 5, 1, 12, 5, 0).unwrap();
+        assert_eq!(
+            next_poll_time(now, interval),
+            Utc.with_ymd_and_hms(2020, 5, 1, 12, 10, 0).unwrap()
+        );
+
+        let now = Utc.with_ymd_and_hms(2020, 5, 1, 23, 59, 59).unwrap();
+        assert_eq!(
+            next_poll_time(now, interval),
+            Utc.with_ymd_and_hms(2020, 5, 2, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_next_poll_time_does_not_drift_with_read_duration() {
+        let interval = Duration::from_secs(60 * 5);
+        let start = Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0).unwrap();
+
+        // A read that takes 70 seconds should still schedule the next read at 12:05
+        let after_slow_read = start + chrono::Duration::seconds(70);
+        assert_eq!(
+            next_poll_time(after_slow_read, interval),
+            Utc.with_ymd_and_hms(2020, 5, 1, 12, 5, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_failure_backoff() {
+        assert_eq!(failure_backoff(1), Duration::from_secs(30));
+        assert_eq!(failure_backoff(2), Duration::from_secs(60));
+        assert_eq!(failure_backoff(3), Duration::from_secs(120));
+        assert_eq!(failure_backoff(8), Duration::from_secs(60 * 60));
+        assert_eq!(failure_backoff(u32::MAX), Duration::from_secs(60 * 60));
     }
 }
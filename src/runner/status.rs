@@ -0,0 +1,242 @@
+use crate::runner::error::RunnerErrorResult;
+use chrono::{DateTime, Duration, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const STATUS_FILE_PREFIX: &str = "runner_status_sn_";
+
+/// How long after the announced next attempt a runner that stopped writing its status file
+/// still counts as running, e.g. while a slow read is retried
+const ACTIVE_RUNNER_GRACE_MINUTES: i64 = 10;
+
+/// What happened to the latest read attempt for a device. Written next to the data files so
+/// consumers can tell a runner that is alive but can't reach the device from a runner that is
+/// not running at all (the status file stops being updated).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunnerStatus {
+    Ok,
+    Failing,
+    Stopped,
+}
+
+impl RunnerStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunnerStatus::Ok => "ok",
+            RunnerStatus::Failing => "failing",
+            RunnerStatus::Stopped => "stopped",
+        }
+    }
+
+    pub fn from_name(value: &str) -> Option<Self> {
+        match value {
+            "ok" => Some(RunnerStatus::Ok),
+            "failing" => Some(RunnerStatus::Failing),
+            "stopped" => Some(RunnerStatus::Stopped),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StatusRecord {
+    pub timestamp: DateTime<Utc>,
+    pub status: RunnerStatus,
+    pub consecutive_failures: u32,
+    pub next_attempt: Option<DateTime<Utc>>,
+    pub message: String,
+}
+
+const STATUS_HEADER: &str = "Timestamp,Status,Consecutive failures,Next attempt,Message";
+
+impl StatusRecord {
+    pub fn to_csv(&self) -> String {
+        [
+            self.timestamp.to_rfc3339(),
+            self.status.as_str().to_owned(),
+            self.consecutive_failures.to_string(),
+            self.next_attempt
+                .map(|timestamp| timestamp.to_rfc3339())
+                .unwrap_or_default(),
+            // The message is the last column, so only line breaks need to be escaped
+            self.message.replace('\n', " "),
+        ]
+        .join(",")
+    }
+
+    pub fn from_csv_line(csv_line: &str) -> Option<Self> {
+        let mut columns = csv_line.splitn(5, ',');
+
+        let timestamp = DateTime::parse_from_rfc3339(columns.next()?)
+            .ok()?
+            .with_timezone(&Utc);
+        let status = RunnerStatus::from_name(columns.next()?)?;
+        let consecutive_failures = columns.next()?.parse().ok()?;
+        let next_attempt = match columns.next()? {
+            "" => None,
+            value => Some(
+                DateTime::parse_from_rfc3339(value)
+                    .ok()?
+                    .with_timezone(&Utc),
+            ),
+        };
+        let message = columns.next().unwrap_or_default().to_owned();
+
+        Some(Self {
+            timestamp,
+            status,
+            consecutive_failures,
+            next_attempt,
+            message,
+        })
+    }
+
+    /// Whether the runner that wrote the record may still be polling the device at `now`. A
+    /// runner that was killed doesn't write `Stopped`, it counts as running until its next
+    /// attempt is overdue.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        match (self.status, self.next_attempt) {
+            (RunnerStatus::Stopped, _) | (_, None) => false,
+            (_, Some(next_attempt)) => {
+                now < next_attempt + Duration::minutes(ACTIVE_RUNNER_GRACE_MINUTES)
+            }
+        }
+    }
+}
+
+pub fn status_file_path(output_dir_path: &Path, device_serial_number: u32) -> PathBuf {
+    output_dir_path.join(format!(
+        "{}{}.txt",
+        STATUS_FILE_PREFIX, device_serial_number
+    ))
+}
+
+/// Replace the status file of the device. Written to a temporary file first and renamed, so a
+/// reader never sees a half written record.
+pub fn write_status_file(
+    output_dir_path: &Path,
+    device_serial_number: u32,
+    record: &StatusRecord,
+) -> RunnerErrorResult<()> {
+    let filepath = status_file_path(output_dir_path, device_serial_number);
+    let temp_filepath = filepath.with_extension("txt.tmp");
+
+    fs::write(
+        &temp_filepath,
+        format!("{}\n{}\n", STATUS_HEADER, record.to_csv()),
+    )?;
+    fs::rename(&temp_filepath, &filepath)?;
+
+    Ok(())
+}
+
+/// The last status written for the device. `None` when there is no (valid) status file, e.g.
+/// the runner writes to another dir or is older than the status files.
+pub fn read_status_file(output_dir_path: &Path, device_serial_number: u32) -> Option<StatusRecord> {
+    let content =
+        fs::read_to_string(status_file_path(output_dir_path, device_serial_number)).ok()?;
+
+    content.lines().nth(1).and_then(StatusRecord::from_csv_line)
+}
+
+/// The serial numbers of the devices with a status file in the dir
+pub fn status_file_serial_numbers(output_dir_path: &Path) -> std::io::Result<Vec<u32>> {
+    let mut serial_numbers = Vec::new();
+    for entry in fs::read_dir(output_dir_path)? {
+        let file_name = entry?.file_name();
+        let serial_number = file_name
+            .to_str()
+            .and_then(|file_name| file_name.strip_prefix(STATUS_FILE_PREFIX))
+            .and_then(|file_name| file_name.strip_suffix(".txt"))
+            .and_then(|serial_number| serial_number.parse::<u32>().ok());
+        serial_numbers.extend(serial_number);
+    }
+    serial_numbers.sort_unstable();
+
+    Ok(serial_numbers)
+}
+
+/// A device whose runner may still be storing readings in the dir at `now`, see
+/// `StatusRecord::is_active`. Data files must not be rewritten while it is.
+pub fn active_runner_serial_number(
+    output_dir_path: &Path,
+    now: DateTime<Utc>,
+) -> std::io::Result<Option<u32>> {
+    Ok(status_file_serial_numbers(output_dir_path)?
+        .into_iter()
+        .find(|serial_number| {
+            read_status_file(output_dir_path, *serial_number)
+                .is_some_and(|record| record.is_active(now))
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_status_record_round_trip() {
+        let record = StatusRecord {
+            timestamp: Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0).unwrap(),
+            status: RunnerStatus::Failing,
+            consecutive_failures: 2,
+            next_attempt: Some(Utc.with_ymd_and_hms(2020, 5, 1, 12, 1, 0).unwrap()),
+            message: "Could not find device, is it\nin range?".to_owned(),
+        };
+
+        let parsed = StatusRecord::from_csv_line(&record.to_csv()).unwrap();
+
+        assert_eq!(parsed.timestamp, record.timestamp);
+        assert_eq!(parsed.status, RunnerStatus::Failing);
+        assert_eq!(parsed.consecutive_failures, 2);
+        assert_eq!(parsed.next_attempt, record.next_attempt);
+        assert_eq!(parsed.message, "Could not find device, is it in range?");
+    }
+
+    #[test]
+    fn test_read_status_file() {
+        let output_dir = tempfile::tempdir().unwrap();
+        let record = StatusRecord {
+            timestamp: Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0).unwrap(),
+            status: RunnerStatus::Stopped,
+            consecutive_failures: 12,
+            next_attempt: None,
+            message: "Too many failures".to_owned(),
+        };
+
+        assert!(read_status_file(output_dir.path(), 2930027508).is_none());
+
+        write_status_file(output_dir.path(), 2930027508, &record).unwrap();
+        let read = read_status_file(output_dir.path(), 2930027508).unwrap();
+
+        assert_eq!(read.status, RunnerStatus::Stopped);
+        assert_eq!(read.message, "Too many failures");
+        assert_eq!(
+            status_file_serial_numbers(output_dir.path()).unwrap(),
+            vec![2930027508]
+        );
+    }
+
+    #[test]
+    fn test_runner_is_active_until_the_next_attempt_is_overdue() {
+        let record = StatusRecord {
+            timestamp: Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0).unwrap(),
+            status: RunnerStatus::Ok,
+            consecutive_failures: 0,
+            next_attempt: Some(Utc.with_ymd_and_hms(2020, 5, 1, 12, 5, 0).unwrap()),
+            message: String::new(),
+        };
+
+        assert!(record.is_active(Utc.with_ymd_and_hms(2020, 5, 1, 12, 1, 0).unwrap()));
+        assert!(record.is_active(Utc.with_ymd_and_hms(2020, 5, 1, 12, 14, 0).unwrap()));
+        assert!(!record.is_active(Utc.with_ymd_and_hms(2020, 5, 1, 12, 16, 0).unwrap()));
+
+        let stopped = StatusRecord {
+            status: RunnerStatus::Stopped,
+            next_attempt: None,
+            ..record
+        };
+        assert!(!stopped.is_active(Utc.with_ymd_and_hms(2020, 5, 1, 12, 1, 0).unwrap()));
+    }
+}
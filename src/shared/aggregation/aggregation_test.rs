@@ -0,0 +1,218 @@
+#[cfg(test)]
+mod tests {
+    use crate::shared::aggregation::cache::AggregateCache;
+    use crate::shared::aggregation::{aggregate, Resolution};
+    use crate::shared::storage::csv_store::CsvStore;
+    use crate::shared::storage::day_timezone::DayTimezone;
+    use crate::shared::storage::sqlite_store::SqliteStore;
+    use crate::shared::storage::SensorDataStore;
+    use crate::shared::types::metric::Metric;
+    use crate::shared::types::sensor_data::SensorData;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use std::fs;
+
+    const SERIAL_NUMBER: u32 = 2930027508;
+
+    /// Two hours before midnight, so the readings span two days
+    fn late_evening() -> DateTime<Utc> {
+        SensorData::test_day_start() + Duration::hours(22)
+    }
+
+    fn sensor_data(minute: i64, co2: f32) -> SensorData {
+        SensorData::test_reading(late_evening() + Duration::minutes(minute))
+            .with_value(Metric::Co2, co2)
+    }
+
+    #[test]
+    fn test_hourly_and_daily_buckets() {
+        let readings = vec![
+            sensor_data(0, 400.0),
+            sensor_data(130, 1000.0),
+            sensor_data(30, 600.0),
+            sensor_data(125, 800.0),
+        ];
+
+        let hourly = aggregate(&readings, Resolution::Hourly, DayTimezone::Utc);
+        let starts: Vec<_> = hourly.iter().map(|bucket| bucket.start).collect();
+        assert_eq!(
+            starts,
+            vec![late_evening(), late_evening() + Duration::hours(2)]
+        );
+        assert_eq!(hourly[0].count, 2);
+        assert_eq!(hourly[0].mean(Metric::Co2), Some(500.0));
+        assert_eq!(
+            hourly[1].metrics[Metric::Co2.index()].map(|co2| (co2.min, co2.max, co2.count)),
+            Some((800.0, 1000.0, 2))
+        );
+        assert_eq!(hourly[1].to_sensor_data().value(Metric::Co2), Some(900.0));
+
+        let daily = aggregate(&readings, Resolution::Daily, DayTimezone::Utc);
+        assert_eq!(daily.len(), 2);
+        assert_eq!(
+            daily[1].start,
+            Utc.with_ymd_and_hms(2020, 5, 2, 0, 0, 0).unwrap()
+        );
+        assert_eq!(daily[1].count, 2);
+    }
+
+    #[test]
+    fn test_daily_buckets_of_the_day_timezone() {
+        let oslo: DayTimezone = "Europe/Oslo".parse().unwrap();
+        // 00:00 and 02:10 on the 2nd in Oslo, the hourly buckets stay UTC hours
+        let readings = vec![sensor_data(0, 400.0), sensor_data(130, 1000.0)];
+
+        let daily = aggregate(&readings, Resolution::Daily, oslo);
+        assert_eq!(daily.len(), 1);
+        assert_eq!(
+            daily[0].start,
+            Utc.with_ymd_and_hms(2020, 5, 1, 22, 0, 0).unwrap()
+        );
+        let hourly = aggregate(&readings, Resolution::Hourly, oslo);
+        assert_eq!(hourly[0].start, late_evening());
+    }
+
+    #[test]
+    fn test_every_timezone_has_its_own_daily_cache() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let store = CsvStore::new(data_dir.path().to_path_buf(), DayTimezone::Utc);
+        for minute in [0, 130] {
+            store
+                .append(SERIAL_NUMBER, &sensor_data(minute, 400.0))
+                .unwrap();
+        }
+
+        let daily_count = |day_timezone| {
+            AggregateCache::new(data_dir.path(), day_timezone)
+                .buckets(&store, SERIAL_NUMBER, Resolution::Daily, None)
+                .unwrap()
+                .len()
+        };
+        assert_eq!(daily_count(DayTimezone::Utc), 2);
+        assert_eq!(daily_count("Europe/Oslo".parse().unwrap()), 1);
+        assert!(data_dir
+            .path()
+            .join("aggregates/csv/sn_2930027508_daily_Europe_Oslo.csv")
+            .exists());
+        assert_eq!(daily_count(DayTimezone::Utc), 2);
+    }
+
+    #[test]
+    fn test_cache_is_updated_with_new_readings() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let store = CsvStore::new(data_dir.path().to_path_buf(), DayTimezone::Utc);
+        let cache = AggregateCache::new(data_dir.path(), DayTimezone::Utc);
+        store.append(SERIAL_NUMBER, &sensor_data(0, 400.0)).unwrap();
+        store
+            .append(SERIAL_NUMBER, &sensor_data(10, 600.0))
+            .unwrap();
+
+        let buckets = cache
+            .buckets(&store, SERIAL_NUMBER, Resolution::Hourly, None)
+            .unwrap();
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].mean(Metric::Co2), Some(500.0));
+        let cache_file = data_dir
+            .path()
+            .join("aggregates/csv/sn_2930027508_hourly.csv");
+        assert!(cache_file.exists());
+
+        // The last bucket is completed and a new one is added
+        store
+            .append(SERIAL_NUMBER, &sensor_data(20, 800.0))
+            .unwrap();
+        store
+            .append(SERIAL_NUMBER, &sensor_data(70, 1000.0))
+            .unwrap();
+        let buckets = cache
+            .buckets(&store, SERIAL_NUMBER, Resolution::Hourly, None)
+            .unwrap();
+        let means: Vec<_> = buckets
+            .iter()
+            .map(|bucket| bucket.mean(Metric::Co2))
+            .collect();
+        assert_eq!(means, vec![Some(600.0), Some(1000.0)]);
+
+        let buckets = cache
+            .buckets(
+                &store,
+                SERIAL_NUMBER,
+                Resolution::Hourly,
+                Some(late_evening() + Duration::minutes(90)),
+            )
+            .unwrap();
+        assert_eq!(buckets.len(), 1);
+
+        // Older buckets come from the cache file, not from the readings
+        fs::remove_file(
+            data_dir
+                .path()
+                .join("waveplus_data_sn_2930027508_2020-05-01.txt"),
+        )
+        .unwrap();
+        store
+            .append(SERIAL_NUMBER, &sensor_data(75, 1200.0))
+            .unwrap();
+        let buckets = cache
+            .buckets(&store, SERIAL_NUMBER, Resolution::Hourly, None)
+            .unwrap();
+        let means: Vec<_> = buckets
+            .iter()
+            .map(|bucket| bucket.mean(Metric::Co2))
+            .collect();
+        assert_eq!(means, vec![Some(600.0), Some(1200.0)]);
+
+        AggregateCache::clear(data_dir.path()).unwrap();
+        assert!(!cache_file.exists());
+    }
+
+    #[test]
+    fn test_malformed_cache_file_is_rebuilt() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let store = CsvStore::new(data_dir.path().to_path_buf(), DayTimezone::Utc);
+        let cache = AggregateCache::new(data_dir.path(), DayTimezone::Utc);
+        store.append(SERIAL_NUMBER, &sensor_data(0, 400.0)).unwrap();
+        fs::create_dir_all(data_dir.path().join("aggregates/csv")).unwrap();
+        fs::write(
+            data_dir
+                .path()
+                .join("aggregates/csv/sn_2930027508_daily.csv"),
+            "not a cache file\n",
+        )
+        .unwrap();
+
+        let buckets = cache
+            .buckets(&store, SERIAL_NUMBER, Resolution::Daily, None)
+            .unwrap();
+
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].count, 1);
+    }
+
+    #[test]
+    fn test_every_backend_has_its_own_cache() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let csv_store = CsvStore::new(data_dir.path().to_path_buf(), DayTimezone::Utc);
+        let sqlite_store = SqliteStore::open(&data_dir.path().join("test.sqlite3")).unwrap();
+        let cache = AggregateCache::new(data_dir.path(), DayTimezone::Utc);
+        // The first hour is cached, only the last one is computed again on every update
+        for minute in [0, 70] {
+            csv_store
+                .append(SERIAL_NUMBER, &sensor_data(minute, 400.0))
+                .unwrap();
+            sqlite_store
+                .append(SERIAL_NUMBER, &sensor_data(minute, 800.0))
+                .unwrap();
+        }
+
+        let mean_co2 = |store: &dyn SensorDataStore| {
+            cache
+                .buckets(store, SERIAL_NUMBER, Resolution::Hourly, None)
+                .unwrap()[0]
+                .mean(Metric::Co2)
+        };
+        assert_eq!(mean_co2(&csv_store), Some(400.0));
+        // Switching the backend doesn't pick up the buckets of the CSV files
+        assert_eq!(mean_co2(&sqlite_store), Some(800.0));
+        assert_eq!(mean_co2(&csv_store), Some(400.0));
+    }
+}
@@ -0,0 +1,245 @@
+use crate::shared::aggregation::{aggregate, Bucket, MetricAggregate, Resolution};
+use crate::shared::storage::day_timezone::DayTimezone;
+use crate::shared::storage::error::{StorageError, StorageResult};
+use crate::shared::storage::SensorDataStore;
+use crate::shared::types::metric::Metric;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Dir in the data dir with the cached buckets
+pub const AGGREGATE_DIR_NAME: &str = "aggregates";
+
+/// Hourly and daily buckets of every device, cached as CSV files in
+/// `<data dir>/aggregates/<backend>`, so long time ranges don't need all the raw readings. The
+/// cache is brought up to date when it is read: the last cached bucket and the buckets of newer
+/// readings are computed again. Every backend has its own files, the CSV files and the SQLite
+/// database may hold different readings. The days are those of `day_timezone`, with separate
+/// files for every timezone.
+#[derive(Debug, Clone)]
+pub struct AggregateCache {
+    dir: PathBuf,
+    day_timezone: DayTimezone,
+}
+
+impl AggregateCache {
+    pub fn new(data_dir: &Path, day_timezone: DayTimezone) -> Self {
+        Self {
+            dir: aggregate_dir(data_dir),
+            day_timezone,
+        }
+    }
+
+    fn backend_dir(&self, store: &dyn SensorDataStore) -> PathBuf {
+        self.dir.join(store.backend().name())
+    }
+
+    fn file_path(
+        &self,
+        store: &dyn SensorDataStore,
+        serial_number: u32,
+        resolution: Resolution,
+    ) -> PathBuf {
+        let file_name = match (resolution, self.day_timezone) {
+            (Resolution::Daily, DayTimezone::Local(timezone)) => format!(
+                "sn_{}_{}_{}.csv",
+                serial_number,
+                resolution.name(),
+                timezone.name().replace('/', "_")
+            ),
+            _ => format!("sn_{}_{}.csv", serial_number, resolution.name()),
+        };
+
+        self.backend_dir(store).join(file_name)
+    }
+
+    /// The up to date buckets from the one that contains `since`
+    pub fn buckets(
+        &self,
+        store: &dyn SensorDataStore,
+        serial_number: u32,
+        resolution: Resolution,
+        since: Option<DateTime<Utc>>,
+    ) -> StorageResult<Vec<Bucket>> {
+        let mut buckets = self.update(store, serial_number, resolution)?;
+
+        if let Some(since) = since {
+            let first_start = resolution.bucket_start(since, self.day_timezone);
+            buckets.retain(|bucket| bucket.start >= first_start);
+        }
+
+        Ok(buckets)
+    }
+
+    /// Add the readings since the last update to the cached buckets of the device. Only
+    /// writes the file when a bucket changed.
+    pub fn update(
+        &self,
+        store: &dyn SensorDataStore,
+        serial_number: u32,
+        resolution: Resolution,
+    ) -> StorageResult<Vec<Bucket>> {
+        let path = self.file_path(store, serial_number, resolution);
+        let mut buckets = read_cache_file(&path)?;
+
+        // The last bucket may have been cached before all of its readings were stored
+        let last_bucket = buckets.pop();
+        let readings = store.readings(
+            serial_number,
+            last_bucket.as_ref().map(|bucket| bucket.start),
+            None,
+        )?;
+        let new_buckets = aggregate(&readings, resolution, self.day_timezone);
+
+        let unchanged = match (&last_bucket, new_buckets.as_slice()) {
+            (Some(last_bucket), [new_bucket]) => last_bucket == new_bucket,
+            (None, []) => true,
+            _ => false,
+        };
+        buckets.extend(new_buckets);
+
+        if !unchanged {
+            write_cache_file(&self.backend_dir(store), &path, &buckets).map_err(|source| {
+                StorageError::Io {
+                    path: path.clone(),
+                    source,
+                }
+            })?;
+        }
+
+        Ok(buckets)
+    }
+
+    /// Remove the cached buckets of all backends and timezones in the data dir. Needed
+    /// whenever readings before the last update are added or rewritten, e.g. by an import, a
+    /// migration or a file sync, because only newer readings are picked up.
+    pub fn clear(data_dir: &Path) -> StorageResult<()> {
+        let dir = aggregate_dir(data_dir);
+
+        match fs::remove_dir_all(&dir) {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+            Err(source) => Err(StorageError::Io { path: dir, source }),
+        }
+    }
+}
+
+fn aggregate_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(AGGREGATE_DIR_NAME)
+}
+
+fn cache_header() -> String {
+    let mut columns = vec!["start".to_owned(), "count".to_owned()];
+    for metric in Metric::ALL.iter() {
+        for suffix in ["min", "max", "sum", "count"].iter() {
+            columns.push(format!("{}_{}", metric.key(), suffix));
+        }
+    }
+
+    columns.join(",")
+}
+
+/// A missing file, or one written by another version, gives no buckets, so they are all
+/// computed again
+fn read_cache_file(path: &Path) -> StorageResult<Vec<Bucket>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => {
+            return Err(StorageError::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+        }
+    };
+
+    let mut lines = content.lines();
+    if lines.next() != Some(cache_header().as_str()) {
+        log::warn!("Rebuilding {}, unknown header", path.to_string_lossy());
+        return Ok(Vec::new());
+    }
+
+    let buckets: Option<Vec<Bucket>> = lines.map(parse_bucket).collect();
+    Ok(buckets.unwrap_or_else(|| {
+        log::warn!("Rebuilding {}, malformed line", path.to_string_lossy());
+        Vec::new()
+    }))
+}
+
+fn parse_bucket(line: &str) -> Option<Bucket> {
+    let mut columns = line.split(',');
+
+    let start = DateTime::parse_from_rfc3339(columns.next()?)
+        .ok()?
+        .with_timezone(&Utc);
+    let count = columns.next()?.parse().ok()?;
+
+    // All four columns are empty for a sensor without values
+    let mut metrics = [None; Metric::COUNT];
+    for metric in metrics.iter_mut() {
+        let values = [
+            columns.next()?,
+            columns.next()?,
+            columns.next()?,
+            columns.next()?,
+        ];
+        if values.iter().all(|value| value.is_empty()) {
+            continue;
+        }
+
+        *metric = Some(MetricAggregate {
+            min: values[0].parse().ok()?,
+            max: values[1].parse().ok()?,
+            sum: values[2].parse().ok()?,
+            count: values[3].parse().ok()?,
+        });
+    }
+
+    if columns.next().is_some() {
+        return None;
+    }
+
+    Some(Bucket {
+        start,
+        count,
+        metrics,
+    })
+}
+
+fn bucket_line(bucket: &Bucket) -> String {
+    let mut columns = vec![
+        bucket.start.to_rfc3339_opts(SecondsFormat::Secs, true),
+        bucket.count.to_string(),
+    ];
+    for metric in bucket.metrics.iter() {
+        match metric {
+            Some(metric) => {
+                columns.push(metric.min.to_string());
+                columns.push(metric.max.to_string());
+                columns.push(metric.sum.to_string());
+                columns.push(metric.count.to_string());
+            }
+            None => columns.extend(std::iter::repeat_n(String::new(), 4)),
+        }
+    }
+
+    columns.join(",")
+}
+
+/// Replace the file in one go, so the dashboard and a report can update the cache at the same
+/// time
+fn write_cache_file(dir: &Path, path: &Path, buckets: &[Bucket]) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+
+    let mut content = cache_header();
+    content.push('\n');
+    for bucket in buckets {
+        content.push_str(&bucket_line(bucket));
+        content.push('\n');
+    }
+
+    let temp_path = path.with_extension(format!("csv.{}.tmp", std::process::id()));
+    fs::write(&temp_path, content)?;
+    fs::rename(&temp_path, path)
+}
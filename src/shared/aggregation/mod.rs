@@ -0,0 +1,163 @@
+use crate::shared::storage::day_timezone::DayTimezone;
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::SensorData;
+use chrono::{DateTime, TimeZone, Utc};
+
+mod aggregation_test;
+pub mod cache;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Resolution {
+    Hourly,
+    Daily,
+}
+
+impl Resolution {
+    pub fn name(self) -> &'static str {
+        match self {
+            Resolution::Hourly => "hourly",
+            Resolution::Daily => "daily",
+        }
+    }
+
+    /// Usual length of a bucket, a day with a clock change in the day timezone is an hour shorter or
+    /// longer
+    pub fn seconds(self) -> i64 {
+        match self {
+            Resolution::Hourly => 60 * 60,
+            Resolution::Daily => 24 * 60 * 60,
+        }
+    }
+
+    /// Start of the UTC hour, or of the day in the day timezone, of the timestamp. Hours stay
+    /// UTC hours, so they also line up in timezones with a half hour offset.
+    pub fn bucket_start(
+        self,
+        timestamp: DateTime<Utc>,
+        day_timezone: DayTimezone,
+    ) -> DateTime<Utc> {
+        match self {
+            Resolution::Hourly => {
+                let seconds = self.seconds();
+                let start = timestamp.timestamp() - timestamp.timestamp().rem_euclid(seconds);
+
+                Utc.timestamp_opt(start, 0).unwrap()
+            }
+            Resolution::Daily => day_timezone.start_of_day(day_timezone.date_of(timestamp)),
+        }
+    }
+}
+
+/// Min, max and sum of a sensor in a bucket. The sum instead of the mean, so buckets can be
+/// merged without losing precision. The count is of the readings with a value of the sensor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MetricAggregate {
+    pub min: f32,
+    pub max: f32,
+    pub sum: f64,
+    pub count: u32,
+}
+
+impl MetricAggregate {
+    fn new(value: f32) -> Self {
+        Self {
+            min: value,
+            max: value,
+            sum: value as f64,
+            count: 1,
+        }
+    }
+
+    fn add(&mut self, value: f32) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value as f64;
+        self.count += 1;
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.sum / self.count as f64
+    }
+}
+
+/// The readings of a device in one hour or day
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bucket {
+    pub start: DateTime<Utc>,
+    pub count: u32,
+    /// In the order of `Metric::ALL`, `None` for the sensors without any value
+    pub metrics: [Option<MetricAggregate>; Metric::COUNT],
+}
+
+impl Bucket {
+    fn new(start: DateTime<Utc>, sensor_data: &SensorData) -> Self {
+        Self {
+            start,
+            count: 1,
+            metrics: sensor_values(sensor_data).map(|value| value.map(MetricAggregate::new)),
+        }
+    }
+
+    fn add(&mut self, sensor_data: &SensorData) {
+        self.count += 1;
+        for (metric, value) in self.metrics.iter_mut().zip(sensor_values(sensor_data)) {
+            match (metric.as_mut(), value) {
+                (Some(metric), Some(value)) => metric.add(value),
+                (None, Some(value)) => *metric = Some(MetricAggregate::new(value)),
+                (_, None) => {}
+            }
+        }
+    }
+
+    pub fn mean(&self, metric: Metric) -> Option<f64> {
+        self.metrics[metric.index()]
+            .as_ref()
+            .map(MetricAggregate::mean)
+    }
+
+    /// A reading with the means of the bucket at the start of the bucket, for the charts
+    pub fn to_sensor_data(&self) -> SensorData {
+        SensorData::from_values(
+            self.start,
+            std::array::from_fn(|index| self.mean(Metric::ALL[index]).map(|mean| mean as f32)),
+        )
+    }
+}
+
+fn sensor_values(sensor_data: &SensorData) -> [Option<f32>; Metric::COUNT] {
+    std::array::from_fn(|index| sensor_data.value(Metric::ALL[index]))
+}
+
+/// Mean of the values, `None` without any value
+pub fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/// Roll the readings up into buckets, ordered by start. The readings don't need to be sorted.
+pub fn aggregate<'a, I>(
+    readings: I,
+    resolution: Resolution,
+    day_timezone: DayTimezone,
+) -> Vec<Bucket>
+where
+    I: IntoIterator<Item = &'a SensorData>,
+{
+    let mut buckets: Vec<Bucket> = Vec::new();
+
+    for sensor_data in readings {
+        let start = resolution.bucket_start(*sensor_data.timestamp(), day_timezone);
+        match buckets.last_mut() {
+            Some(bucket) if bucket.start == start => bucket.add(sensor_data),
+            _ => match buckets.binary_search_by_key(&start, |bucket| bucket.start) {
+                Ok(index) => buckets[index].add(sensor_data),
+                Err(index) => buckets.insert(index, Bucket::new(start, sensor_data)),
+            },
+        }
+    }
+
+    buckets
+}
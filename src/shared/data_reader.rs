@@ -0,0 +1,280 @@
+use crate::device::DEVICES;
+use crate::shared::types::data_file_header::DataFileHeader;
+use crate::shared::types::sensor_data::{SensorData, SensorDataParseError};
+use chrono::NaiveDate;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// A successfully read line of a data file
+#[derive(Debug, Clone)]
+pub enum DataLine {
+    /// A header line: the metadata or the column titles. The serial number is the one known
+    /// after the line, from the header or the file name.
+    Header {
+        serial_number: Option<u32>,
+    },
+    Reading(DataRecord),
+}
+
+#[derive(Debug, Clone)]
+pub struct DataRecord {
+    pub path: PathBuf,
+    pub line_number: usize,
+    /// From the header of the file, or the file name when the header is missing
+    pub serial_number: Option<u32>,
+    pub sensor_data: SensorData,
+}
+
+#[derive(Debug)]
+pub enum DataLineErrorKind {
+    Io(std::io::Error),
+    Malformed(SensorDataParseError),
+    /// The last line of the file has no line break, e.g. the runner was stopped or the power was
+    /// cut while writing it
+    PartialLine,
+}
+
+#[derive(Debug)]
+pub struct DataLineError {
+    pub path: PathBuf,
+    /// 1-based, or 0 when the file could not be opened
+    pub line_number: usize,
+    pub kind: DataLineErrorKind,
+}
+
+impl fmt::Display for DataLineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = format!("{}:{}", self.path.to_string_lossy(), self.line_number);
+        match &self.kind {
+            DataLineErrorKind::Io(error) => write!(f, "{}: could not read: {}", location, error),
+            DataLineErrorKind::Malformed(error) => {
+                write!(f, "{}: malformed line: {}", location, error)
+            }
+            DataLineErrorKind::PartialLine => write!(f, "{}: incomplete last line", location),
+        }
+    }
+}
+
+impl Error for DataLineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            DataLineErrorKind::Io(error) => Some(error),
+            DataLineErrorKind::Malformed(error) => Some(error),
+            DataLineErrorKind::PartialLine => None,
+        }
+    }
+}
+
+pub type DataLineResult = Result<DataLine, DataLineError>;
+
+struct OpenFile {
+    path: PathBuf,
+    reader: BufReader<File>,
+    line_number: usize,
+    header: DataFileHeader,
+}
+
+/// Streams the lines of one or more data files, one file at a time and in file name order (so
+/// in date order for the files of a device). Every line gives a `Result`, so a single broken
+/// line doesn't stop the reading of the rest.
+pub struct SensorDataReader {
+    pending_paths: VecDeque<PathBuf>,
+    current: Option<OpenFile>,
+}
+
+impl SensorDataReader {
+    pub fn from_paths(mut paths: Vec<PathBuf>) -> Self {
+        paths.sort();
+
+        Self {
+            pending_paths: paths.into(),
+            current: None,
+        }
+    }
+
+    /// All data files in the dir, optionally only the ones of a single device
+    #[cfg(test)]
+    pub fn from_dir(data_dir: &Path, serial_number: Option<u32>) -> std::io::Result<Self> {
+        Ok(Self::from_paths(data_file_paths(data_dir, serial_number)?))
+    }
+
+    /// Only the sensor readings. Headers are skipped and bad lines are logged and skipped.
+    pub fn readings(self) -> impl Iterator<Item = DataRecord> {
+        self.filter_map(|line| match line {
+            Ok(DataLine::Reading(record)) => Some(record),
+            Ok(DataLine::Header { .. }) => None,
+            Err(error) => {
+                log::warn!("Skipping line in data file. {}", error);
+                None
+            }
+        })
+    }
+
+    fn open_next_file(&mut self) -> Option<Result<(), DataLineError>> {
+        let path = self.pending_paths.pop_front()?;
+
+        Some(match File::open(&path) {
+            Ok(file) => {
+                self.current = Some(OpenFile {
+                    header: DataFileHeader::legacy(serial_number_from_file_name(&path)),
+                    path,
+                    reader: BufReader::new(file),
+                    line_number: 0,
+                });
+                Ok(())
+            }
+            Err(error) => Err(DataLineError {
+                path,
+                line_number: 0,
+                kind: DataLineErrorKind::Io(error),
+            }),
+        })
+    }
+}
+
+impl Iterator for SensorDataReader {
+    type Item = DataLineResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.current.is_none() {
+                if let Err(error) = self.open_next_file()? {
+                    return Some(Err(error));
+                }
+            }
+
+            let file = self.current.as_mut()?;
+            let mut line = String::new();
+
+            match file.reader.read_line(&mut line) {
+                Ok(0) => {
+                    self.current = None;
+                    continue;
+                }
+                Ok(_) => {
+                    file.line_number += 1;
+                    return Some(parse_line(file, &line));
+                }
+                Err(error) => {
+                    file.line_number += 1;
+                    let error = DataLineError {
+                        path: file.path.clone(),
+                        line_number: file.line_number,
+                        kind: DataLineErrorKind::Io(error),
+                    };
+                    // Don't keep reading a file that fails, e.g. with invalid UTF-8
+                    self.current = None;
+                    return Some(Err(error));
+                }
+            }
+        }
+    }
+}
+
+fn parse_line(file: &mut OpenFile, line: &str) -> DataLineResult {
+    let (path, line_number) = (&file.path, file.line_number);
+    let line_error = |kind| DataLineError {
+        path: path.clone(),
+        line_number,
+        kind,
+    };
+
+    if !line.ends_with('\n') {
+        return Err(line_error(DataLineErrorKind::PartialLine));
+    }
+    let line = line.trim_end_matches('\n').trim_end_matches('\r');
+
+    if file.header.read_line(line) {
+        return Ok(DataLine::Header {
+            serial_number: file.header.serial_number,
+        });
+    }
+
+    file.header
+        .parse_reading(line)
+        .map(|sensor_data| {
+            DataLine::Reading(DataRecord {
+                path: file.path.clone(),
+                line_number: file.line_number,
+                serial_number: file.header.serial_number,
+                sensor_data,
+            })
+        })
+        .map_err(|error| line_error(DataLineErrorKind::Malformed(error)))
+}
+
+/// The file name without the prefix of the device model, e.g.
+/// `wavemini_data_sn_2920012345_2020-05-01.txt` -> `2920012345_2020-05-01.txt`
+fn strip_data_file_prefix(file_name: &str) -> Option<&str> {
+    DEVICES
+        .iter()
+        .find_map(|device| file_name.strip_prefix(device.file_prefix()))
+}
+
+/// `waveplus_data_sn_2930027508_2020-05-01.txt` -> `2930027508`
+pub fn serial_number_from_file_name(path: &Path) -> Option<u32> {
+    strip_data_file_prefix(path.file_name()?.to_str()?)?
+        .split('_')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// `waveplus_data_sn_2930027508_2020-05-01.txt` -> `2020-05-01`
+pub fn date_from_file_name(path: &Path) -> Option<NaiveDate> {
+    let date = strip_data_file_prefix(path.file_name()?.to_str()?)?
+        .split('_')
+        .nth(1)?
+        .strip_suffix(".txt")?;
+
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// `<prefix><serial>_<YYYY-MM-DD>.txt` of a known device model. Anything else, e.g. the
+/// `.txt.tmp` file of an interrupted merge or migration, is not read.
+pub fn is_data_file(path: &Path) -> bool {
+    serial_number_from_file_name(path).is_some() && date_from_file_name(path).is_some()
+}
+
+/// Paths of the data files in the dir sorted by device and day, optionally only for a single
+/// device
+pub fn data_file_paths(
+    data_dir: &Path,
+    serial_number: Option<u32>,
+) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = data_dir
+        .read_dir()?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|entry| entry.path())
+        .filter(|path| is_data_file(path))
+        .filter(|path| {
+            serial_number.is_none() || serial_number_from_file_name(path) == serial_number
+        })
+        .collect::<Vec<_>>();
+
+    paths.sort_by_cached_key(|path| {
+        (
+            serial_number_from_file_name(path),
+            date_from_file_name(path),
+            path.clone(),
+        )
+    });
+    Ok(paths)
+}
+
+/// Serial numbers of the devices with data files in the dir, sorted
+pub fn serial_numbers_in_dir(data_dir: &Path) -> std::io::Result<Vec<u32>> {
+    let mut serial_numbers: Vec<u32> = data_file_paths(data_dir, None)?
+        .iter()
+        .filter_map(|path| serial_number_from_file_name(path))
+        .collect();
+    serial_numbers.sort_unstable();
+    serial_numbers.dedup();
+
+    Ok(serial_numbers)
+}
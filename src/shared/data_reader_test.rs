@@ -0,0 +1,232 @@
+#[cfg(test)]
+mod tests {
+    use crate::shared::data_reader::{
+        data_file_paths, date_from_file_name, is_data_file, serial_number_from_file_name, DataLine,
+        DataLineErrorKind, SensorDataReader,
+    };
+    use crate::shared::types::metric::Metric;
+    use crate::shared::types::sensor_data::SensorData;
+    use chrono::{Duration, NaiveDate};
+    use std::fs;
+    use std::path::Path;
+
+    const SERIAL_NUMBER: u32 = 2930027508;
+
+    fn sensor_data(hour: i64) -> SensorData {
+        SensorData::test_reading(SensorData::test_day_start() + Duration::hours(hour))
+    }
+
+    fn write_data_file(dir: &Path, date: &str, content: &str) {
+        let path = dir.join(format!("waveplus_data_sn_{}_{}.txt", SERIAL_NUMBER, date));
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_reads_files_written_by_the_runner() {
+        let dir = tempfile::tempdir().unwrap();
+        // Same layout as `runner::file_io::create_or_append_sensor_data_file`
+        let content = format!(
+            "{}\n{}\n",
+            sensor_data(12).to_csv_with_header(SERIAL_NUMBER),
+            sensor_data(13).to_csv()
+        );
+        write_data_file(dir.path(), "2020-05-01", &content);
+
+        let lines: Vec<_> = SensorDataReader::from_dir(dir.path(), None)
+            .unwrap()
+            .collect();
+
+        assert_eq!(lines.len(), 4);
+        assert!(matches!(
+            lines[0],
+            Ok(DataLine::Header {
+                serial_number: Some(SERIAL_NUMBER),
+            })
+        ));
+
+        let readings: Vec<_> = SensorDataReader::from_dir(dir.path(), None)
+            .unwrap()
+            .readings()
+            .collect();
+        assert_eq!(readings.len(), 2);
+        assert_eq!(readings[0].serial_number, Some(SERIAL_NUMBER));
+        assert_eq!(readings[0].line_number, 3);
+        assert_eq!(
+            readings[1].sensor_data.timestamp(),
+            sensor_data(13).timestamp()
+        );
+        assert_eq!(readings[1].sensor_data.value(Metric::Co2), Some(500.0));
+    }
+
+    #[test]
+    fn test_reports_malformed_and_partial_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let full_line = sensor_data(13).to_csv();
+        let content = format!(
+            "{}\n{}\nnot a reading\n{}\n{}",
+            sensor_data(12).to_csv_with_header(SERIAL_NUMBER),
+            &full_line[..full_line.len() / 2],
+            full_line,
+            &full_line[..full_line.len() - 3],
+        );
+        write_data_file(dir.path(), "2020-05-01", &content);
+
+        let lines: Vec<_> = SensorDataReader::from_dir(dir.path(), None)
+            .unwrap()
+            .collect();
+
+        assert_eq!(lines.len(), 7);
+        assert!(matches!(lines[2], Ok(DataLine::Reading(_))));
+        assert!(matches!(
+            &lines[3],
+            Err(error) if matches!(error.kind, DataLineErrorKind::Malformed(_)) && error.line_number == 4
+        ));
+        assert!(matches!(
+            &lines[4],
+            Err(error) if matches!(error.kind, DataLineErrorKind::Malformed(_))
+        ));
+        assert!(matches!(lines[5], Ok(DataLine::Reading(_))));
+        assert!(matches!(
+            &lines[6],
+            Err(error) if matches!(error.kind, DataLineErrorKind::PartialLine)
+        ));
+    }
+
+    #[test]
+    fn test_reads_the_metrics_a_device_measures() {
+        let dir = tempfile::tempdir().unwrap();
+        // A line of a Wave Mini, which has no pressure, CO2 and radon sensors
+        let content = format!(
+            "{}\n2020-05-01 12:00:00 UTC,21.5,35,,,95,,\n",
+            sensor_data(11).to_csv_with_header(SERIAL_NUMBER)
+        );
+        write_data_file(dir.path(), "2020-05-01", &content);
+
+        let readings: Vec<_> = SensorDataReader::from_dir(dir.path(), None)
+            .unwrap()
+            .readings()
+            .collect();
+
+        assert_eq!(readings.len(), 2);
+        assert_eq!(
+            readings[1].sensor_data.values().collect::<Vec<_>>(),
+            vec![
+                (Metric::Temperature, 21.5),
+                (Metric::Humidity, 35.0),
+                (Metric::Voc, 95.0)
+            ]
+        );
+        assert_eq!(readings[0].sensor_data.values().count(), Metric::ALL.len());
+    }
+
+    #[test]
+    fn test_reads_files_of_the_first_schema_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "Timestamp,Temperature (C),Humidity (%),Atmospheric pressure (mbar),\
+            CO2 (ppm),TVOC (ppb),Radon short-term average (Bq/m3),\
+            Radon long-term average (Bq/m3),2930000001\n\
+            2020-05-01 12:00:00 UTC,22.58,22.5,1022.08,476,152,1,6,\n";
+        write_data_file(dir.path(), "2020-05-01", content);
+
+        let lines: Vec<_> = SensorDataReader::from_dir(dir.path(), None)
+            .unwrap()
+            .collect();
+
+        assert!(matches!(
+            lines[0],
+            Ok(DataLine::Header {
+                serial_number: Some(2930000001),
+            })
+        ));
+        match &lines[1] {
+            Ok(DataLine::Reading(record)) => {
+                assert_eq!(record.serial_number, Some(2930000001));
+                assert_eq!(
+                    record.sensor_data.value(Metric::RadonLongTermAverage),
+                    Some(6.0)
+                );
+            }
+            line => panic!("expected a reading, got {:?}", line),
+        }
+    }
+
+    #[test]
+    fn test_streams_daily_files_in_date_order() {
+        let dir = tempfile::tempdir().unwrap();
+        write_data_file(
+            dir.path(),
+            "2020-05-02",
+            &format!("{}\n", sensor_data(14).to_csv_with_header(SERIAL_NUMBER)),
+        );
+        write_data_file(
+            dir.path(),
+            "2020-05-01",
+            &format!("{}\n", sensor_data(12).to_csv_with_header(SERIAL_NUMBER)),
+        );
+        fs::write(dir.path().join("runner_status_sn_2930027508.txt"), "x\n").unwrap();
+
+        let hours: Vec<_> = SensorDataReader::from_dir(dir.path(), Some(SERIAL_NUMBER))
+            .unwrap()
+            .readings()
+            .map(|record| record.sensor_data.timestamp().format("%H").to_string())
+            .collect();
+
+        assert_eq!(hours, vec!["12", "14"]);
+        assert_eq!(
+            SensorDataReader::from_dir(dir.path(), Some(1))
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_only_daily_data_files_are_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = format!("{}\n", sensor_data(12).to_csv_with_header(SERIAL_NUMBER));
+        write_data_file(dir.path(), "2020-05-01", &content);
+        // Left behind by an interrupted merge or migration
+        fs::write(
+            dir.path()
+                .join("waveplus_data_sn_2930027508_2020-05-01.txt.tmp"),
+            &content,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("waveplus_data_sn_2930027508_latest.txt"),
+            &content,
+        )
+        .unwrap();
+
+        let paths = data_file_paths(dir.path(), None).unwrap();
+
+        assert_eq!(paths.len(), 1);
+        assert!(is_data_file(&paths[0]));
+    }
+
+    #[test]
+    fn test_serial_number_from_data_file_name() {
+        assert_eq!(
+            serial_number_from_file_name(Path::new(
+                "/data/waveplus_data_sn_2930027508_2020-05-01.txt"
+            )),
+            Some(2930027508)
+        );
+        assert_eq!(
+            serial_number_from_file_name(Path::new("runner_status_sn_2930027508.txt")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_date_from_data_file_name() {
+        assert_eq!(
+            date_from_file_name(Path::new("waveplus_data_sn_2930027508_2020-05-01.txt")),
+            NaiveDate::from_ymd_opt(2020, 5, 1)
+        );
+        assert_eq!(
+            date_from_file_name(Path::new("waveplus_data_sn_2930027508_latest.txt")),
+            None
+        );
+    }
+}
@@ -1 +1,5 @@
+pub mod aggregation;
+pub mod data_reader;
+mod data_reader_test;
+pub mod storage;
 pub mod types;
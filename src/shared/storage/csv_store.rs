@@ -0,0 +1,255 @@
+use crate::device::file_prefix_of_serial_number;
+use crate::shared::data_reader::{
+    data_file_paths, date_from_file_name, serial_numbers_in_dir, SensorDataReader,
+};
+use crate::shared::storage::day_timezone::DayTimezone;
+use crate::shared::storage::error::{StorageError, StorageResult};
+use crate::shared::storage::{SensorDataStore, StorageBackend};
+use crate::shared::types::data_file_header::DataFileHeader;
+use crate::shared::types::sensor_data::SensorData;
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// One CSV file per device and day in the data dir, with the file prefix of the device model:
+/// e.g. `waveplus_data_sn_<serial>_<YYYY-MM-DD>.txt` for a Wave Plus. The days are UTC days
+/// unless another timezone is given.
+#[derive(Debug)]
+pub struct CsvStore {
+    data_dir: PathBuf,
+    day_timezone: DayTimezone,
+}
+
+impl CsvStore {
+    pub fn new(data_dir: PathBuf, day_timezone: DayTimezone) -> Self {
+        Self {
+            data_dir,
+            day_timezone,
+        }
+    }
+
+    fn data_file_path(&self, serial_number: u32, sensor_data: &SensorData) -> PathBuf {
+        self.data_file_path_of_day(
+            serial_number,
+            self.day_timezone.date_of(*sensor_data.timestamp()),
+        )
+    }
+
+    fn data_file_path_of_day(&self, serial_number: u32, day: NaiveDate) -> PathBuf {
+        let filename_date_formatted = day.format("%Y-%m-%d");
+        self.data_dir.join(format!(
+            "{}{}_{}.txt",
+            file_prefix_of_serial_number(serial_number),
+            serial_number,
+            filename_date_formatted
+        ))
+    }
+
+    fn data_file_paths(&self, serial_number: u32) -> StorageResult<Vec<PathBuf>> {
+        data_file_paths(&self.data_dir, Some(serial_number)).map_err(|source| StorageError::Io {
+            path: self.data_dir.clone(),
+            source,
+        })
+    }
+}
+
+impl SensorDataStore for CsvStore {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Csv
+    }
+
+    fn append(&self, serial_number: u32, sensor_data: &SensorData) -> StorageResult<()> {
+        let filepath = self.data_file_path(serial_number, sensor_data);
+
+        create_or_append_sensor_data_file(&filepath, sensor_data, serial_number).map_err(|source| {
+            StorageError::Io {
+                path: filepath,
+                source,
+            }
+        })
+    }
+
+    /// The new readings of a day are merged into its file in timestamp order
+    fn insert_missing(&self, serial_number: u32, readings: &[SensorData]) -> StorageResult<usize> {
+        let mut readings_per_day: BTreeMap<NaiveDate, Vec<&SensorData>> = BTreeMap::new();
+        for sensor_data in readings {
+            readings_per_day
+                .entry(self.day_timezone.date_of(*sensor_data.timestamp()))
+                .or_default()
+                .push(sensor_data);
+        }
+
+        let mut inserted = 0;
+        for (day, readings) in readings_per_day {
+            let filepath = self.data_file_path_of_day(serial_number, day);
+            inserted +=
+                merge_into_data_file(&filepath, serial_number, &readings).map_err(|source| {
+                    StorageError::Io {
+                        path: filepath,
+                        source,
+                    }
+                })?;
+        }
+
+        Ok(inserted)
+    }
+
+    fn serial_numbers(&self) -> StorageResult<Vec<u32>> {
+        serial_numbers_in_dir(&self.data_dir).map_err(|source| StorageError::Io {
+            path: self.data_dir.clone(),
+            source,
+        })
+    }
+
+    /// The last reading in the file of the most recent day
+    fn latest(&self, serial_number: u32) -> StorageResult<Option<SensorData>> {
+        let filepath = match self.data_file_paths(serial_number)?.pop() {
+            Some(filepath) => filepath,
+            None => return Ok(None),
+        };
+
+        SensorDataReader::from_paths(vec![filepath.clone()])
+            .readings()
+            .last()
+            .map(|record| Some(record.sensor_data))
+            .ok_or(StorageError::Unreadable { path: filepath })
+    }
+
+    /// Only the daily files of the days in the range are read. A day more is read on both ends,
+    /// as the files may have been written with another timezone.
+    fn readings(
+        &self,
+        serial_number: u32,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> StorageResult<Vec<SensorData>> {
+        let first_day = since.and_then(|since| {
+            self.day_timezone
+                .date_of(since)
+                .checked_sub_days(Days::new(1))
+        });
+        let last_day = until.and_then(|until| {
+            self.day_timezone
+                .date_of(until)
+                .checked_add_days(Days::new(1))
+        });
+
+        let paths = self
+            .data_file_paths(serial_number)?
+            .into_iter()
+            .filter(|path| match date_from_file_name(path) {
+                Some(date) => {
+                    first_day.is_none_or(|first_day| date >= first_day)
+                        && last_day.is_none_or(|last_day| date <= last_day)
+                }
+                None => true,
+            })
+            .collect();
+
+        Ok(SensorDataReader::from_paths(paths)
+            .readings()
+            .map(|record| record.sensor_data)
+            .filter(|sensor_data| {
+                since.is_none_or(|since| *sensor_data.timestamp() >= since)
+                    && until.is_none_or(|until| *sensor_data.timestamp() < until)
+            })
+            .collect())
+    }
+}
+
+/// Append a line to the daily file, and start a new file with the header
+pub fn create_or_append_sensor_data_file(
+    filepath: &Path,
+    sensor_data: &SensorData,
+    device_serial_number: u32,
+) -> std::io::Result<()> {
+    let append_file = OpenOptions::new()
+        .create_new(false)
+        .append(true)
+        .open(filepath);
+
+    match append_file {
+        Ok(mut file) => {
+            file.write_all(format!("{}\n", sensor_data.to_csv()).as_bytes())?;
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            let mut file = OpenOptions::new()
+                .create_new(true)
+                .write(true)
+                .open(filepath)?;
+            file.write_all(
+                format!("{}\n", sensor_data.to_csv_with_header(device_serial_number)).as_bytes(),
+            )?;
+        }
+        Err(error) => return Err(error),
+    };
+
+    Ok(())
+}
+
+/// Add the readings with a timestamp that isn't in the file yet, and rewrite the file sorted by
+/// timestamp. The header is kept as it is and lines that can't be parsed are kept after the line
+/// they followed.
+fn merge_into_data_file(
+    filepath: &Path,
+    device_serial_number: u32,
+    readings: &[&SensorData],
+) -> std::io::Result<usize> {
+    let content = match fs::read_to_string(filepath) {
+        Ok(content) => content,
+        Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+        Err(error) => return Err(error),
+    };
+
+    let mut header = DataFileHeader::legacy(Some(device_serial_number));
+    let mut header_lines = Vec::new();
+    let mut lines: Vec<(Option<DateTime<Utc>>, String)> = Vec::new();
+    let mut previous_timestamp = None;
+    for line in content.lines() {
+        if lines.is_empty() && header.read_line(line) {
+            header_lines.push(line.to_owned());
+            continue;
+        }
+        if let Ok(sensor_data) = header.parse_reading(line) {
+            previous_timestamp = Some(*sensor_data.timestamp());
+        }
+        lines.push((previous_timestamp, line.to_owned()));
+    }
+
+    let mut timestamps: HashSet<DateTime<Utc>> = lines
+        .iter()
+        .filter_map(|(timestamp, _)| *timestamp)
+        .collect();
+    let mut inserted = 0;
+    for sensor_data in readings {
+        if timestamps.insert(*sensor_data.timestamp()) {
+            lines.push((Some(*sensor_data.timestamp()), sensor_data.to_csv()));
+            inserted += 1;
+        }
+    }
+    if inserted == 0 {
+        return Ok(0);
+    }
+
+    // Stable, so readings with the same key keep their order
+    lines.sort_by_key(|(timestamp, _)| *timestamp);
+
+    if header_lines.is_empty() {
+        header_lines.push(DataFileHeader::new(device_serial_number).to_string());
+    }
+    let mut merged = format!("{}\n", header_lines.join("\n"));
+    for (_, line) in lines {
+        merged.push_str(&line);
+        merged.push('\n');
+    }
+
+    // Replace the file in one go, so a reader never sees half of it
+    let temp_path = filepath.with_extension("txt.tmp");
+    fs::write(&temp_path, merged)?;
+    fs::rename(&temp_path, filepath)?;
+
+    Ok(inserted)
+}
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+/// The timezone of the days of the daily data files: when a new file is started and which date
+/// is in its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(try_from = "String")]
+pub enum DayTimezone {
+    #[default]
+    Utc,
+    /// An IANA timezone, e.g. `Europe/Oslo`. A day starts at local midnight, so the days the
+    /// clocks change have 23 or 25 hours.
+    Local(Tz),
+}
+
+impl DayTimezone {
+    /// The day the reading at `timestamp` belongs to
+    pub fn date_of(self, timestamp: DateTime<Utc>) -> NaiveDate {
+        match self {
+            DayTimezone::Utc => timestamp.date_naive(),
+            DayTimezone::Local(timezone) => timestamp.with_timezone(&timezone).date_naive(),
+        }
+    }
+
+    /// The first moment of the day, e.g. for the days a report or an export covers
+    pub fn start_of_day(self, date: NaiveDate) -> DateTime<Utc> {
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+
+        match self {
+            DayTimezone::Utc => Utc.from_utc_datetime(&midnight),
+            // Some timezones move the clocks forward at midnight, the day starts an hour later
+            DayTimezone::Local(timezone) => [midnight, midnight + Duration::hours(1)]
+                .iter()
+                .find_map(|time| timezone.from_local_datetime(time).earliest())
+                .map(|start| start.with_timezone(&Utc))
+                .unwrap_or_else(|| Utc.from_utc_datetime(&midnight)),
+        }
+    }
+}
+
+impl FromStr for DayTimezone {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.eq_ignore_ascii_case("utc") {
+            return Ok(DayTimezone::Utc);
+        }
+
+        value.parse().map(DayTimezone::Local).map_err(|_| {
+            format!(
+                "Unknown timezone '{}', expected UTC or an IANA timezone like Europe/Oslo",
+                value
+            )
+        })
+    }
+}
+
+impl TryFrom<String> for DayTimezone {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for DayTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DayTimezone::Utc => write!(f, "UTC"),
+            DayTimezone::Local(timezone) => write!(f, "{}", timezone.name()),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+#[cfg(test)]
+mod tests {
+    use crate::shared::storage::day_timezone::DayTimezone;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    fn date_in_oslo(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDate {
+        let timezone: DayTimezone = "Europe/Oslo".parse().unwrap();
+
+        timezone.date_of(
+            Utc.with_ymd_and_hms(year, month, day, hour, minute, 0)
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_evening_readings_stay_on_the_local_day() {
+        let utc = DayTimezone::Utc;
+
+        assert_eq!(
+            utc.date_of(Utc.with_ymd_and_hms(2020, 5, 1, 22, 30, 0).unwrap()),
+            NaiveDate::from_ymd_opt(2020, 5, 1).unwrap()
+        );
+        // 00:30 in Oslo, summer time
+        assert_eq!(
+            date_in_oslo(2020, 5, 1, 22, 30),
+            NaiveDate::from_ymd_opt(2020, 5, 2).unwrap()
+        );
+        // 23:30 in Oslo, winter time
+        assert_eq!(
+            date_in_oslo(2020, 12, 1, 22, 30),
+            NaiveDate::from_ymd_opt(2020, 12, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_days_with_a_clock_change() {
+        // The clocks go forward on 2020-03-29 at 02:00, the day starts at 23:00 UTC
+        assert_eq!(
+            date_in_oslo(2020, 3, 28, 22, 59),
+            NaiveDate::from_ymd_opt(2020, 3, 28).unwrap()
+        );
+        assert_eq!(
+            date_in_oslo(2020, 3, 28, 23, 0),
+            NaiveDate::from_ymd_opt(2020, 3, 29).unwrap()
+        );
+        // and it ends at 22:00 UTC, in summer time
+        assert_eq!(
+            date_in_oslo(2020, 3, 29, 21, 59),
+            NaiveDate::from_ymd_opt(2020, 3, 29).unwrap()
+        );
+        assert_eq!(
+            date_in_oslo(2020, 3, 29, 22, 0),
+            NaiveDate::from_ymd_opt(2020, 3, 30).unwrap()
+        );
+
+        // The clocks go back on 2020-10-25 at 03:00, the day lasts from 22:00 to 23:00 UTC
+        assert_eq!(
+            date_in_oslo(2020, 10, 24, 22, 0),
+            NaiveDate::from_ymd_opt(2020, 10, 25).unwrap()
+        );
+        assert_eq!(
+            date_in_oslo(2020, 10, 25, 22, 59),
+            NaiveDate::from_ymd_opt(2020, 10, 25).unwrap()
+        );
+        assert_eq!(
+            date_in_oslo(2020, 10, 25, 23, 0),
+            NaiveDate::from_ymd_opt(2020, 10, 26).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_start_of_day() {
+        let oslo: DayTimezone = "Europe/Oslo".parse().unwrap();
+        let start_of_day = |timezone: DayTimezone, month, day| {
+            timezone.start_of_day(NaiveDate::from_ymd_opt(2020, month, day).unwrap())
+        };
+
+        assert_eq!(
+            start_of_day(DayTimezone::Utc, 3, 29),
+            Utc.with_ymd_and_hms(2020, 3, 29, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            start_of_day(oslo, 3, 29),
+            Utc.with_ymd_and_hms(2020, 3, 28, 23, 0, 0).unwrap()
+        );
+        assert_eq!(
+            start_of_day(oslo, 3, 30),
+            Utc.with_ymd_and_hms(2020, 3, 29, 22, 0, 0).unwrap()
+        );
+        assert_eq!(
+            start_of_day(oslo, 10, 26),
+            Utc.with_ymd_and_hms(2020, 10, 25, 23, 0, 0).unwrap()
+        );
+
+        // Havana moved the clocks forward at midnight, 2020-03-08 started at 01:00 local time
+        let havana: DayTimezone = "America/Havana".parse().unwrap();
+        assert_eq!(
+            start_of_day(havana, 3, 8),
+            Utc.with_ymd_and_hms(2020, 3, 8, 5, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!("UTC".parse(), Ok(DayTimezone::Utc));
+        assert_eq!("utc".parse(), Ok(DayTimezone::Utc));
+        assert_eq!(
+            "Europe/Oslo".parse::<DayTimezone>().unwrap().to_string(),
+            "Europe/Oslo"
+        );
+        assert!("Mars/Olympus_Mons".parse::<DayTimezone>().is_err());
+    }
+}
@@ -0,0 +1,45 @@
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+#[derive(Debug)]
+pub enum StorageError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// A data file without a single readable reading
+    Unreadable { path: PathBuf },
+    Sqlite {
+        path: PathBuf,
+        source: rusqlite::Error,
+    },
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Io { path, .. } => {
+                write!(f, "Could not read or write {}", path.to_string_lossy())
+            }
+            StorageError::Unreadable { path } => {
+                write!(f, "No readable sensor data in {}", path.to_string_lossy())
+            }
+            StorageError::Sqlite { path, .. } => {
+                write!(f, "SQLite database {} failed", path.to_string_lossy())
+            }
+        }
+    }
+}
+
+impl Error for StorageError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StorageError::Io { source, .. } => Some(source),
+            StorageError::Sqlite { source, .. } => Some(source),
+            StorageError::Unreadable { .. } => None,
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::shared::storage::csv_store::CsvStore;
+use crate::shared::storage::day_timezone::DayTimezone;
+use crate::shared::storage::error::StorageResult;
+use crate::shared::storage::sqlite_store::SqliteStore;
+use crate::shared::types::sensor_data::SensorData;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub mod csv_store;
+pub mod day_timezone;
+mod day_timezone_test;
+pub mod error;
+pub mod sqlite_store;
+mod storage_test;
+
+/// File name of the SQLite database in the data dir, unless another path is configured
+pub const DEFAULT_SQLITE_FILE_NAME: &str = "air-quality.sqlite3";
+
+/// Where the runner stores the readings and where the dashboard and the reports read them from
+pub trait SensorDataStore: fmt::Debug {
+    fn backend(&self) -> StorageBackend;
+
+    fn append(&self, serial_number: u32, sensor_data: &SensorData) -> StorageResult<()>;
+
+    /// Add the readings that aren't stored yet, a reading of the device with the same timestamp
+    /// counts as stored. Returns the number of added readings.
+    fn insert_missing(&self, serial_number: u32, readings: &[SensorData]) -> StorageResult<usize>;
+
+    /// Devices with stored readings, sorted
+    fn serial_numbers(&self) -> StorageResult<Vec<u32>>;
+
+    fn latest(&self, serial_number: u32) -> StorageResult<Option<SensorData>>;
+
+    /// Readings of a device from `since` up to, but not including, `until`, oldest first
+    fn readings(
+        &self,
+        serial_number: u32,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> StorageResult<Vec<SensorData>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// Daily CSV files per device
+    #[default]
+    Csv,
+    Sqlite,
+}
+
+impl StorageBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StorageBackend::Csv => "csv",
+            StorageBackend::Sqlite => "sqlite",
+        }
+    }
+}
+
+impl FromStr for StorageBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "csv" => Ok(StorageBackend::Csv),
+            "sqlite" => Ok(StorageBackend::Sqlite),
+            _ => Err(format!(
+                "Unknown storage backend '{}', expected csv or sqlite",
+                value
+            )),
+        }
+    }
+}
+
+/// Open the backend for the data dir. The SQLite database is created when it doesn't exist.
+/// `day_timezone` decides the days of the CSV files, SQLite stores the timestamps only.
+pub fn open_store(
+    backend: StorageBackend,
+    data_dir: &Path,
+    sqlite_path: Option<PathBuf>,
+    day_timezone: DayTimezone,
+) -> StorageResult<Box<dyn SensorDataStore>> {
+    Ok(match backend {
+        StorageBackend::Csv => Box::new(CsvStore::new(data_dir.to_path_buf(), day_timezone)),
+        StorageBackend::Sqlite => {
+            let sqlite_path =
+                sqlite_path.unwrap_or_else(|| data_dir.join(DEFAULT_SQLITE_FILE_NAME));
+            Box::new(SqliteStore::open(&sqlite_path)?)
+        }
+    })
+}
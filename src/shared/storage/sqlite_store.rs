@@ -0,0 +1,240 @@
+use crate::shared::storage::error::{StorageError, StorageResult};
+use crate::shared::storage::{SensorDataStore, StorageBackend};
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::SensorData;
+use chrono::{DateTime, TimeZone, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Bumped when the table layout changes, stored in `PRAGMA user_version`
+const SCHEMA_VERSION: i32 = 1;
+
+/// Timestamps are stored as Unix time in milliseconds, so range queries use the primary key.
+/// The sensors the model of the device doesn't have are NULL.
+fn create_table() -> String {
+    let metric_columns: String = Metric::ALL
+        .iter()
+        .map(|metric| format!("{} REAL, ", metric.key()))
+        .collect();
+
+    format!(
+        "CREATE TABLE IF NOT EXISTS sensor_data (
+            serial_number INTEGER NOT NULL,
+            timestamp INTEGER NOT NULL,
+            {}
+            PRIMARY KEY (serial_number, timestamp)
+        ) WITHOUT ROWID",
+        metric_columns
+    )
+}
+
+/// The timestamp and the metrics in the order of `Metric::ALL`
+fn select_columns() -> String {
+    let mut columns = vec!["timestamp"];
+    columns.extend(Metric::ALL.iter().map(|metric| metric.key()));
+
+    columns.join(", ")
+}
+
+fn insert_statement(conflict: &str) -> String {
+    let metrics = Metric::ALL.iter().map(|metric| metric.key());
+    let columns: Vec<_> = ["serial_number", "timestamp"]
+        .iter()
+        .copied()
+        .chain(metrics)
+        .collect();
+    let placeholders = vec!["?"; columns.len()];
+
+    format!(
+        "INSERT OR {} INTO sensor_data ({}) VALUES ({})",
+        conflict,
+        columns.join(", "),
+        placeholders.join(", ")
+    )
+}
+
+fn insert_values(serial_number: u32, sensor_data: &SensorData) -> Vec<Value> {
+    let mut values = vec![
+        Value::from(serial_number),
+        Value::from(sensor_data.timestamp().timestamp_millis()),
+    ];
+    values.extend(
+        Metric::ALL
+            .iter()
+            .map(|metric| Value::from(sensor_data.value(*metric))),
+    );
+
+    values
+}
+
+/// All devices in a single SQLite database, e.g. `air-quality.sqlite3` in the data dir
+#[derive(Debug)]
+pub struct SqliteStore {
+    path: PathBuf,
+    connection: Connection,
+}
+
+impl SqliteStore {
+    /// Opens the database and creates the table when it is new
+    pub fn open(path: &Path) -> StorageResult<Self> {
+        let sqlite_error = |source| StorageError::Sqlite {
+            path: path.to_path_buf(),
+            source,
+        };
+
+        let connection = Connection::open(path).map_err(sqlite_error)?;
+        // The runner writes while the dashboard and the reports read from other processes
+        connection
+            .busy_timeout(Duration::from_secs(5))
+            .map_err(sqlite_error)?;
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .map_err(sqlite_error)?;
+        migrate(&connection).map_err(sqlite_error)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            connection,
+        })
+    }
+
+    fn sqlite_error(&self, source: rusqlite::Error) -> StorageError {
+        StorageError::Sqlite {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
+
+/// Create the table of a new database. A metric added after the table was created gets a new
+/// column, without values in the older rows.
+fn migrate(connection: &Connection) -> rusqlite::Result<()> {
+    let transaction = connection.unchecked_transaction()?;
+    transaction.execute(&create_table(), [])?;
+
+    let columns = {
+        let mut statement =
+            transaction.prepare("SELECT name FROM pragma_table_info('sensor_data')")?;
+        let columns = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        columns
+    };
+    for metric in Metric::ALL.iter() {
+        if !columns.iter().any(|column| column == metric.key()) {
+            transaction.execute(
+                &format!("ALTER TABLE sensor_data ADD COLUMN {} REAL", metric.key()),
+                [],
+            )?;
+        }
+    }
+    transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+
+    transaction.commit()
+}
+
+impl SensorDataStore for SqliteStore {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Sqlite
+    }
+
+    /// A reading with the same device and timestamp replaces the stored one
+    fn append(&self, serial_number: u32, sensor_data: &SensorData) -> StorageResult<()> {
+        self.connection
+            .execute(
+                &insert_statement("REPLACE"),
+                params_from_iter(insert_values(serial_number, sensor_data)),
+            )
+            .map(|_| ())
+            .map_err(|source| self.sqlite_error(source))
+    }
+
+    /// All readings are added in a single transaction
+    fn insert_missing(&self, serial_number: u32, readings: &[SensorData]) -> StorageResult<usize> {
+        let insert = || -> rusqlite::Result<usize> {
+            let transaction = self.connection.unchecked_transaction()?;
+            let mut inserted = 0;
+            {
+                let mut statement = transaction.prepare(&insert_statement("IGNORE"))?;
+                for sensor_data in readings {
+                    inserted += statement
+                        .execute(params_from_iter(insert_values(serial_number, sensor_data)))?;
+                }
+            }
+            transaction.commit()?;
+            Ok(inserted)
+        };
+
+        insert().map_err(|source| self.sqlite_error(source))
+    }
+
+    fn serial_numbers(&self) -> StorageResult<Vec<u32>> {
+        let query = || -> rusqlite::Result<Vec<u32>> {
+            let mut statement = self
+                .connection
+                .prepare("SELECT DISTINCT serial_number FROM sensor_data ORDER BY serial_number")?;
+            let serial_numbers = statement
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<u32>>>()?;
+            Ok(serial_numbers)
+        };
+
+        query().map_err(|source| self.sqlite_error(source))
+    }
+
+    fn latest(&self, serial_number: u32) -> StorageResult<Option<SensorData>> {
+        self.connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM sensor_data WHERE serial_number = ?1 \
+                     ORDER BY timestamp DESC LIMIT 1",
+                    select_columns()
+                ),
+                params![serial_number],
+                sensor_data_from_row,
+            )
+            .optional()
+            .map_err(|source| self.sqlite_error(source))
+    }
+
+    fn readings(
+        &self,
+        serial_number: u32,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> StorageResult<Vec<SensorData>> {
+        let since = since.map_or(i64::MIN, |since| since.timestamp_millis());
+        let until = until.map_or(i64::MAX, |until| until.timestamp_millis());
+
+        let query = || -> rusqlite::Result<Vec<SensorData>> {
+            let mut statement = self.connection.prepare(&format!(
+                "SELECT {} FROM sensor_data \
+                 WHERE serial_number = ?1 AND timestamp >= ?2 AND timestamp < ?3 \
+                 ORDER BY timestamp",
+                select_columns()
+            ))?;
+            let readings = statement
+                .query_map(params![serial_number, since, until], sensor_data_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(readings)
+        };
+
+        query().map_err(|source| self.sqlite_error(source))
+    }
+}
+
+fn sensor_data_from_row(row: &Row) -> rusqlite::Result<SensorData> {
+    let timestamp_millis: i64 = row.get(0)?;
+    let timestamp = Utc.timestamp_millis_opt(timestamp_millis).single().ok_or(
+        rusqlite::Error::IntegralValueOutOfRange(0, timestamp_millis),
+    )?;
+
+    let mut values = [None; Metric::COUNT];
+    for (index, value) in values.iter_mut().enumerate() {
+        *value = row.get(index + 1)?;
+    }
+
+    Ok(SensorData::from_values(timestamp, values))
+}
@@ -0,0 +1,247 @@
+#[cfg(test)]
+mod tests {
+    use crate::shared::storage::csv_store::CsvStore;
+    use crate::shared::storage::day_timezone::DayTimezone;
+    use crate::shared::storage::error::StorageError;
+    use crate::shared::storage::sqlite_store::SqliteStore;
+    use crate::shared::storage::{open_store, SensorDataStore, StorageBackend};
+    use crate::shared::types::metric::Metric;
+    use crate::shared::types::sensor_data::{SensorData, SensorDataBuilder};
+    use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+    use std::fs;
+
+    fn sensor_data(timestamp: DateTime<Utc>, co2: f32) -> SensorData {
+        SensorData::test_reading(timestamp).with_value(Metric::Co2, co2)
+    }
+
+    const WAVE_MINI_SERIAL_NUMBER: u32 = 2920012345;
+
+    fn wave_mini_sensor_data(timestamp: DateTime<Utc>) -> SensorData {
+        SensorDataBuilder {
+            timestamp,
+            values: vec![
+                (Metric::Temperature, 21.5),
+                (Metric::Humidity, 35.0),
+                (Metric::Voc, 95.0),
+            ],
+        }
+        .into()
+    }
+
+    fn co2_values(readings: &[SensorData]) -> Vec<f32> {
+        readings
+            .iter()
+            .map(|reading| reading.value(Metric::Co2).unwrap())
+            .collect()
+    }
+
+    /// The same behaviour is expected from every backend
+    fn check_store(store: &dyn SensorDataStore) {
+        let day = |day, hour| Utc.with_ymd_and_hms(2020, 5, day, hour, 0, 0).unwrap();
+
+        assert!(store.serial_numbers().unwrap().is_empty());
+        assert!(store.latest(2930027508).unwrap().is_none());
+
+        store
+            .append(2930027508, &sensor_data(day(1, 12), 400.0))
+            .unwrap();
+        store
+            .append(2930027508, &sensor_data(day(1, 13), 500.0))
+            .unwrap();
+        store
+            .append(2930027508, &sensor_data(day(2, 12), 600.0))
+            .unwrap();
+        store
+            .append(2930000001, &sensor_data(day(3, 12), 700.0))
+            .unwrap();
+
+        assert_eq!(
+            store.serial_numbers().unwrap(),
+            vec![2930000001, 2930027508]
+        );
+
+        let latest = store.latest(2930027508).unwrap().unwrap();
+        assert_eq!(latest.value(Metric::Co2), Some(600.0));
+        assert_eq!(*latest.timestamp(), day(2, 12));
+        assert_eq!(latest.value(Metric::RadonLongTermAverage), Some(20.0));
+
+        let all = store.readings(2930027508, None, None).unwrap();
+        assert_eq!(co2_values(&all), vec![400.0, 500.0, 600.0]);
+
+        let range = store
+            .readings(2930027508, Some(day(1, 13)), Some(day(2, 12)))
+            .unwrap();
+        assert_eq!(co2_values(&range), vec![500.0]);
+
+        let since = store.readings(2930027508, Some(day(2, 0)), None).unwrap();
+        assert_eq!(co2_values(&since), vec![600.0]);
+
+        // A Wave Mini has no CO2 and radon sensors
+        store
+            .append(WAVE_MINI_SERIAL_NUMBER, &wave_mini_sensor_data(day(1, 12)))
+            .unwrap();
+        let latest = store.latest(WAVE_MINI_SERIAL_NUMBER).unwrap().unwrap();
+        assert_eq!(latest.value(Metric::Voc), Some(95.0));
+        assert_eq!(latest.value(Metric::Co2), None);
+        assert_eq!(latest.value(Metric::RadonShortTermAverage), None);
+    }
+
+    #[test]
+    fn test_csv_store() {
+        let dir = tempfile::tempdir().unwrap();
+
+        check_store(&CsvStore::new(dir.path().to_path_buf(), DayTimezone::Utc));
+
+        let content = fs::read_to_string(
+            dir.path()
+                .join("waveplus_data_sn_2930027508_2020-05-01.txt"),
+        )
+        .unwrap();
+        assert!(content.starts_with("#schema_version=2,model=Wave Plus,serial_number=2930027508\n"));
+        assert_eq!(content.lines().count(), 4);
+
+        let content = fs::read_to_string(
+            dir.path()
+                .join("wavemini_data_sn_2920012345_2020-05-01.txt"),
+        )
+        .unwrap();
+        assert!(content.ends_with(",21.5,35,,,95,,\n"));
+    }
+
+    #[test]
+    fn test_csv_store_with_local_days() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = CsvStore::new(dir.path().to_path_buf(), "Europe/Oslo".parse().unwrap());
+        let timestamp = |day, hour| Utc.with_ymd_and_hms(2020, 5, day, hour, 30, 0).unwrap();
+
+        // 23:30 and 00:30 in Oslo
+        store
+            .append(2930027508, &sensor_data(timestamp(1, 21), 400.0))
+            .unwrap();
+        store
+            .append(2930027508, &sensor_data(timestamp(1, 22), 500.0))
+            .unwrap();
+        store
+            .insert_missing(2930027508, &[sensor_data(timestamp(2, 21), 600.0)])
+            .unwrap();
+
+        let content = fs::read_to_string(
+            dir.path()
+                .join("waveplus_data_sn_2930027508_2020-05-01.txt"),
+        )
+        .unwrap();
+        assert!(content.ends_with("\n2020-05-01T21:30:00Z,21,40,1000,400,100,20,20\n"));
+        let content = fs::read_to_string(
+            dir.path()
+                .join("waveplus_data_sn_2930027508_2020-05-02.txt"),
+        )
+        .unwrap();
+        assert_eq!(content.lines().count(), 4);
+
+        let range = store
+            .readings(2930027508, Some(timestamp(1, 22)), Some(timestamp(2, 22)))
+            .unwrap();
+        assert_eq!(co2_values(&range), vec![500.0, 600.0]);
+
+        // Files written with UTC days are still found
+        let store = CsvStore::new(dir.path().to_path_buf(), DayTimezone::Utc);
+        let range = store
+            .readings(2930027508, Some(timestamp(1, 21)), Some(timestamp(1, 23)))
+            .unwrap();
+        assert_eq!(co2_values(&range), vec![400.0, 500.0]);
+    }
+
+    #[test]
+    fn test_csv_store_with_local_days_across_a_clock_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let oslo: DayTimezone = "Europe/Oslo".parse().unwrap();
+        let store = CsvStore::new(dir.path().to_path_buf(), oslo);
+        let timestamp = |day, hour| Utc.with_ymd_and_hms(2020, 10, day, hour, 30, 0).unwrap();
+
+        // The clocks go back from 03:00 to 02:00 on 2020-10-25, at 01:00 UTC
+        for (co2, (day, hour)) in [(24, 22), (25, 0), (25, 1), (25, 22), (25, 23)]
+            .iter()
+            .enumerate()
+        {
+            store
+                .append(2930027508, &sensor_data(timestamp(*day, *hour), co2 as f32))
+                .unwrap();
+        }
+
+        let file_lines = |date| {
+            fs::read_to_string(
+                dir.path()
+                    .join(format!("waveplus_data_sn_2930027508_{}.txt", date)),
+            )
+            .unwrap()
+            .lines()
+            .skip(2)
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+        };
+        // 00:30 summer time, so the reading is in the file of the next day
+        assert_eq!(
+            file_lines("2020-10-25")[0],
+            "2020-10-24T22:30:00Z,21,40,1000,0,100,20,20"
+        );
+        assert_eq!(file_lines("2020-10-25").len(), 4);
+        assert_eq!(file_lines("2020-10-26").len(), 1);
+        assert!(!dir
+            .path()
+            .join("waveplus_data_sn_2930027508_2020-10-24.txt")
+            .exists());
+
+        // The 25 hours of the day
+        let date = NaiveDate::from_ymd_opt(2020, 10, 25).unwrap();
+        let day = store
+            .readings(
+                2930027508,
+                Some(oslo.start_of_day(date)),
+                Some(oslo.start_of_day(date.succ_opt().unwrap())),
+            )
+            .unwrap();
+        assert_eq!(co2_values(&day), vec![0.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_csv_store_unreadable_latest_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path()
+                .join("waveplus_data_sn_2930027508_2020-05-01.txt"),
+            "",
+        )
+        .unwrap();
+
+        let result = CsvStore::new(dir.path().to_path_buf(), DayTimezone::Utc).latest(2930027508);
+
+        assert!(matches!(result, Err(StorageError::Unreadable { .. })));
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        let dir = tempfile::tempdir().unwrap();
+
+        check_store(&SqliteStore::open(&dir.path().join("test.sqlite3")).unwrap());
+    }
+
+    #[test]
+    fn test_sqlite_store_keeps_data_and_replaces_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let timestamp = Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0).unwrap();
+
+        let store = open_store(StorageBackend::Sqlite, dir.path(), None, DayTimezone::Utc).unwrap();
+        store
+            .append(2930027508, &sensor_data(timestamp, 400.0))
+            .unwrap();
+        store
+            .append(2930027508, &sensor_data(timestamp, 450.0))
+            .unwrap();
+        drop(store);
+
+        let store = open_store(StorageBackend::Sqlite, dir.path(), None, DayTimezone::Utc).unwrap();
+        let readings = store.readings(2930027508, None, None).unwrap();
+        assert_eq!(co2_values(&readings), vec![450.0]);
+        assert!(dir.path().join("air-quality.sqlite3").exists());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::device::device_of_serial_number;
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_data::{SensorData, SensorDataParseError};
+use std::fmt;
+
+/// Layout of the data files written by this version. Version 1 files start with the column
+/// titles and the serial number in an unnamed last column, and every line ends with an empty
+/// field to match it.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+const METADATA_PREFIX: &str = "#";
+const TIMESTAMP_TITLE: &str = "Timestamp";
+
+/// The header of a daily data file. Since version 2 it has two lines: the metadata, e.g.
+/// `#schema_version=2,model=Wave Plus,serial_number=2930027508`, and the column titles with
+/// the units, e.g. `Timestamp,Temperature (C),Humidity (%),...`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFileHeader {
+    pub schema_version: u32,
+    pub model: Option<String>,
+    pub serial_number: Option<u32>,
+    /// The metric of every column after the timestamp, `None` for a column this version
+    /// doesn't know
+    pub columns: Vec<Option<Metric>>,
+}
+
+impl DataFileHeader {
+    /// The header of a new data file of the device
+    pub fn new(serial_number: u32) -> Self {
+        Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            model: device_of_serial_number(serial_number).map(|device| device.name().to_owned()),
+            serial_number: Some(serial_number),
+            columns: Metric::ALL.iter().copied().map(Some).collect(),
+        }
+    }
+
+    /// What is known about a file before its header is read. Without a header the file is read
+    /// as a version 1 file.
+    pub fn legacy(serial_number: Option<u32>) -> Self {
+        Self {
+            schema_version: 1,
+            model: None,
+            serial_number,
+            columns: Metric::ALL.iter().copied().map(Some).collect(),
+        }
+    }
+
+    pub fn is_current(&self) -> bool {
+        self.schema_version >= CURRENT_SCHEMA_VERSION
+    }
+
+    /// Take over what a header line says about the file. Returns `false` for any other line.
+    pub fn read_line(&mut self, line: &str) -> bool {
+        if let Some(metadata) = line.strip_prefix(METADATA_PREFIX) {
+            self.read_metadata(metadata);
+            true
+        } else if is_column_titles_line(line) {
+            self.read_column_titles(line);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Unknown keys are skipped, so a newer version can add some
+    fn read_metadata(&mut self, metadata: &str) {
+        for (key, value) in metadata
+            .split(',')
+            .filter_map(|entry| entry.trim().split_once('='))
+        {
+            match key {
+                "schema_version" => {
+                    if let Ok(schema_version) = value.parse() {
+                        self.schema_version = schema_version;
+                    }
+                }
+                "model" => self.model = Some(value.to_owned()).filter(|model| !model.is_empty()),
+                "serial_number" => {
+                    if let Ok(serial_number) = value.parse() {
+                        self.serial_number = Some(serial_number);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// A version 1 file has its serial number in the last column. The columns of a newer file
+    /// are matched by their titles.
+    fn read_column_titles(&mut self, line: &str) {
+        if !self.is_current() {
+            if let Some(serial_number) = line.rsplit(',').next().and_then(|last| last.parse().ok())
+            {
+                self.serial_number = Some(serial_number);
+            }
+            return;
+        }
+
+        self.columns = line
+            .split(',')
+            .skip(1)
+            .map(|title| {
+                Metric::ALL
+                    .iter()
+                    .copied()
+                    .find(|metric| metric.csv_title() == title)
+            })
+            .collect();
+    }
+
+    /// Parse a data line of the file
+    pub fn parse_reading(&self, line: &str) -> Result<SensorData, SensorDataParseError> {
+        if self.is_current() {
+            SensorData::from_csv_columns(line, &self.columns)
+        } else {
+            SensorData::from_csv_line(line)
+        }
+    }
+}
+
+/// Both header lines, without a line break at the end
+impl fmt::Display for DataFileHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut metadata = vec![format!("schema_version={}", self.schema_version)];
+        if let Some(model) = &self.model {
+            metadata.push(format!("model={}", model));
+        }
+        if let Some(serial_number) = self.serial_number {
+            metadata.push(format!("serial_number={}", serial_number));
+        }
+
+        let mut titles = vec![TIMESTAMP_TITLE.to_owned()];
+        titles.extend(
+            self.columns
+                .iter()
+                .map(|metric| metric.map(Metric::csv_title).unwrap_or_default()),
+        );
+
+        write!(
+            f,
+            "{}{}\n{}",
+            METADATA_PREFIX,
+            metadata.join(","),
+            titles.join(",")
+        )
+    }
+}
+
+fn is_column_titles_line(line: &str) -> bool {
+    line.strip_prefix(TIMESTAMP_TITLE)
+        .is_some_and(|titles| titles.starts_with(','))
+}
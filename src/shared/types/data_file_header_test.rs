@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+    use crate::shared::types::data_file_header::{DataFileHeader, CURRENT_SCHEMA_VERSION};
+    use crate::shared::types::metric::Metric;
+    use crate::shared::types::sensor_data::{SensorData, SensorDataBuilder};
+    use chrono::{TimeZone, Utc};
+
+    fn read_header(content: &str) -> DataFileHeader {
+        let mut header = DataFileHeader::legacy(None);
+        for line in content.lines() {
+            assert!(header.read_line(line), "not a header line: {}", line);
+        }
+
+        header
+    }
+
+    #[test]
+    fn test_current_header_describes_the_file() {
+        let header = DataFileHeader::new(2920012345);
+
+        let content = header.to_string();
+
+        assert_eq!(
+            content,
+            "#schema_version=2,model=Wave Mini,serial_number=2920012345\n\
+             Timestamp,Temperature (C),Humidity (%),Atmospheric pressure (mbar),CO2 (ppm),\
+             TVOC (ppb),Radon short-term average (Bq/m3),Radon long-term average (Bq/m3)"
+        );
+        assert_eq!(read_header(&content), header);
+    }
+
+    #[test]
+    fn test_legacy_header_has_the_serial_number_in_the_last_column() {
+        let header = read_header(
+            "Timestamp,Temperature (C),Humidity (%),Atmospheric pressure (mbar),CO2 (ppm),\
+             TVOC (ppb),Radon short-term average (Bq/m3),Radon long-term average (Bq/m3),\
+             2930027508",
+        );
+
+        assert_eq!(header.schema_version, 1);
+        assert!(!header.is_current());
+        assert_eq!(header.serial_number, Some(2930027508));
+
+        let sensor_data = header
+            .parse_reading("2020-05-01 12:00:00 UTC,22.58,22.5,1022.08,476,152,1,6,")
+            .unwrap();
+        assert_eq!(sensor_data.value(Metric::RadonLongTermAverage), Some(6.0));
+    }
+
+    #[test]
+    fn test_columns_are_matched_by_their_titles() {
+        let header = read_header(
+            "#schema_version=2,serial_number=2930027508,written_by=a newer version\n\
+             Timestamp,CO2 (ppm),Particulate matter (ug/m3),Temperature (C)",
+        );
+
+        assert_eq!(header.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(header.model, None);
+        assert_eq!(
+            header.columns,
+            vec![Some(Metric::Co2), None, Some(Metric::Temperature)]
+        );
+
+        let sensor_data = header
+            .parse_reading("2020-05-01T12:00:00Z,476,12,22.5")
+            .unwrap();
+        assert_eq!(
+            sensor_data.values().collect::<Vec<_>>(),
+            vec![(Metric::Temperature, 22.5), (Metric::Co2, 476.0)]
+        );
+        assert!(header.parse_reading("2020-05-01T12:00:00Z,476,12").is_err());
+    }
+
+    #[test]
+    fn test_readings_are_written_with_rfc3339_timestamps() {
+        let header = DataFileHeader::new(2930027508);
+        let timestamp = Utc.with_ymd_and_hms(2020, 5, 1, 12, 0, 0).unwrap();
+        let sensor_data = SensorData::new(SensorDataBuilder {
+            timestamp,
+            values: vec![(Metric::Co2, 476.0)],
+        });
+
+        let line = sensor_data.to_csv();
+
+        assert_eq!(line, "2020-05-01T12:00:00Z,,,,476,,,");
+        let parsed = header.parse_reading(&line).unwrap();
+        assert_eq!(parsed.timestamp(), &timestamp);
+        assert_eq!(parsed.value(Metric::Co2), Some(476.0));
+        // Older versions wrote the timestamp as `2020-05-01 12:00:00 UTC`, and other tools may
+        // write an offset
+        for line in [
+            "2020-05-01 12:00:00 UTC,,,,476,,,",
+            "2020-05-01T14:00:00+02:00,,,,476,,,",
+        ] {
+            assert_eq!(header.parse_reading(line).unwrap().timestamp(), &timestamp);
+        }
+    }
+
+    #[test]
+    fn test_data_lines_are_not_header_lines() {
+        let mut header = DataFileHeader::legacy(Some(2930027508));
+
+        assert!(!header.read_line("2020-05-01 12:00:00 UTC,22.58,22.5,1022.08,476,152,1,6"));
+        assert!(!header.read_line("Timestamp"));
+        assert_eq!(header, DataFileHeader::legacy(Some(2930027508)));
+    }
+}
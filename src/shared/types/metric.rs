@@ -0,0 +1,158 @@
+use crate::shared::types::quality_profile::QualityProfile;
+use crate::shared::types::sensor_quality::SensorQuality;
+use std::fmt;
+
+/// A quantity measured by the sensors of a device, with everything the rest of the code needs to
+/// know about it. The data files, the database, the exports, the reports, the dashboard, the
+/// Prometheus gauges and the MQTT topics all follow `Metric::ALL`. A new sensor also needs its
+/// default quality ranges in `QualityProfile::airthings` and a decoder for the models that have
+/// it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Metric {
+    Temperature,
+    Humidity,
+    AtmosphericPressure,
+    Co2,
+    Voc,
+    RadonShortTermAverage,
+    RadonLongTermAverage,
+}
+
+impl Metric {
+    /// In the order of the data file columns. New metrics go at the end, so older data files
+    /// can still be read.
+    pub const ALL: &'static [Metric] = &[
+        Metric::Temperature,
+        Metric::Humidity,
+        Metric::AtmosphericPressure,
+        Metric::Co2,
+        Metric::Voc,
+        Metric::RadonShortTermAverage,
+        Metric::RadonLongTermAverage,
+    ];
+
+    pub const COUNT: usize = Metric::ALL.len();
+
+    /// Position in `Metric::ALL`
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Column name in the database and the exports
+    pub fn key(self) -> &'static str {
+        match self {
+            Metric::Temperature => "temperature_in_celsius",
+            Metric::Humidity => "humidity_in_percent",
+            Metric::AtmosphericPressure => "atmospheric_pressure",
+            Metric::Co2 => "co2",
+            Metric::Voc => "voc",
+            Metric::RadonShortTermAverage => "radon_short_term_average",
+            Metric::RadonLongTermAverage => "radon_long_term_average",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Temperature => "Temperature",
+            Metric::Humidity => "Humidity",
+            Metric::AtmosphericPressure => "Atmospheric pressure",
+            Metric::Co2 => "CO2",
+            Metric::Voc => "TVOC",
+            Metric::RadonShortTermAverage => "Radon short-term average",
+            Metric::RadonLongTermAverage => "Radon long-term average",
+        }
+    }
+
+    /// Column heading on the dashboard
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Metric::Temperature => "TEMP",
+            Metric::Humidity => "HUMIDITY",
+            Metric::AtmosphericPressure => "PRESSURE",
+            Metric::Co2 => "CO2",
+            Metric::Voc => "TVOC",
+            Metric::RadonShortTermAverage => "RADON",
+            Metric::RadonLongTermAverage => "RADON LT",
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            Metric::Temperature => "C",
+            Metric::Humidity => "%",
+            Metric::AtmosphericPressure => "mbar",
+            Metric::Co2 => "ppm",
+            Metric::Voc => "ppb",
+            Metric::RadonShortTermAverage | Metric::RadonLongTermAverage => "Bq/m3",
+        }
+    }
+
+    /// Column title in the data files, e.g. `Temperature (C)`
+    pub fn csv_title(self) -> String {
+        format!("{} ({})", self.name(), self.unit())
+    }
+
+    /// Sensor name in the MQTT topics and the Prometheus gauges, e.g. `radon_st`
+    pub fn sensor_key(self) -> &'static str {
+        match self {
+            Metric::Temperature => "temperature",
+            Metric::Humidity => "humidity",
+            Metric::AtmosphericPressure => "pressure",
+            Metric::Co2 => "co2",
+            Metric::Voc => "voc",
+            Metric::RadonShortTermAverage => "radon_st",
+            Metric::RadonLongTermAverage => "radon_lt",
+        }
+    }
+
+    /// Name of the quality ranges in the config file and of the metric in the alerts. Both
+    /// radon averages share the `radon` ranges.
+    pub fn quality_key(self) -> &'static str {
+        match self {
+            Metric::Temperature => "temperature",
+            Metric::Humidity => "humidity",
+            Metric::AtmosphericPressure => "atmospheric_pressure",
+            Metric::Co2 => "co2",
+            Metric::Voc => "voc",
+            Metric::RadonShortTermAverage | Metric::RadonLongTermAverage => "radon",
+        }
+    }
+
+    /// `unit` as Home Assistant spells it
+    pub fn home_assistant_unit(self) -> &'static str {
+        match self {
+            Metric::Temperature => "°C",
+            Metric::RadonShortTermAverage | Metric::RadonLongTermAverage => "Bq/m³",
+            _ => self.unit(),
+        }
+    }
+
+    /// `None` for the metrics Home Assistant has no device class for
+    pub fn home_assistant_device_class(self) -> Option<&'static str> {
+        match self {
+            Metric::Temperature => Some("temperature"),
+            Metric::Humidity => Some("humidity"),
+            Metric::AtmosphericPressure => Some("atmospheric_pressure"),
+            Metric::Co2 => Some("carbon_dioxide"),
+            Metric::Voc => Some("volatile_organic_compounds_parts"),
+            Metric::RadonShortTermAverage | Metric::RadonLongTermAverage => None,
+        }
+    }
+
+    /// Quality of a value with the active quality profile
+    pub fn quality(self, value: f32) -> SensorQuality {
+        self.quality_with(value, QualityProfile::active())
+    }
+
+    /// Quality of a value with the ranges of the profile. The value is rounded first, as it is
+    /// shown on the dashboard.
+    pub fn quality_with(self, value: f32, profile: &QualityProfile) -> SensorQuality {
+        profile.bands(self).quality(value.round() as f64)
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
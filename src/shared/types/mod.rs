@@ -1,3 +1,8 @@
+pub mod data_file_header;
+mod data_file_header_test;
+pub mod metric;
+pub mod quality_profile;
+mod quality_profile_test;
 pub mod sensor_data;
 pub mod sensor_quality;
 mod sensor_quality_test;
@@ -0,0 +1,141 @@
+use crate::shared::types::metric::Metric;
+use crate::shared::types::sensor_quality::SensorQuality;
+use std::sync::OnceLock;
+
+/// Value ranges of one sensor and their quality. Every band starts at its lower bound and ends
+/// where the next band starts. The first band has no lower bound.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityBands {
+    first: SensorQuality,
+    bands: Vec<(f64, SensorQuality)>,
+}
+
+impl QualityBands {
+    /// `bands` are (lower bound, quality) pairs in ascending order of the bounds
+    pub fn new(first: SensorQuality, bands: Vec<(f64, SensorQuality)>) -> Result<Self, String> {
+        if bands.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err("the lower bounds of the quality bands must be ascending".to_owned());
+        }
+
+        Ok(Self { first, bands })
+    }
+
+    /// Same quality for every value
+    pub fn constant(quality: SensorQuality) -> Self {
+        Self {
+            first: quality,
+            bands: Vec::new(),
+        }
+    }
+
+    pub fn quality(&self, value: f64) -> SensorQuality {
+        self.bands
+            .iter()
+            .rev()
+            .find(|(lower_bound, _)| value >= *lower_bound)
+            .map(|(_, quality)| *quality)
+            .unwrap_or(self.first)
+    }
+}
+
+/// Built-in profiles. Every profile other than `airthings` only changes the sensor its standard
+/// is about and keeps the Airthings ranges for the rest.
+pub const BUILTIN_PROFILE_NAMES: [&str; 4] = ["airthings", "who", "ashrae", "uba"];
+
+/// The quality ranges of every metric
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityProfile {
+    /// Indexed by `Metric::index`
+    bands: [QualityBands; Metric::COUNT],
+}
+
+static ACTIVE_PROFILE: OnceLock<QualityProfile> = OnceLock::new();
+
+impl QualityProfile {
+    /// The ranges from the Airthings documentation
+    pub fn airthings() -> Self {
+        use SensorQuality::*;
+
+        Self {
+            bands: std::array::from_fn(|index| match Metric::ALL[index] {
+                Metric::Temperature => bands(DependsOnContext, &[(18.0, Good), (26.0, Terrible)]),
+                Metric::Humidity => bands(
+                    Terrible,
+                    &[(25.0, Bad), (30.0, Good), (60.0, Bad), (70.0, Terrible)],
+                ),
+                Metric::AtmosphericPressure => QualityBands::constant(DependsOnContext),
+                Metric::Co2 => bands(Good, &[(800.0, Bad), (1000.0, Terrible)]),
+                Metric::Voc => bands(Good, &[(250.0, Bad), (2000.0, Terrible)]),
+                Metric::RadonShortTermAverage | Metric::RadonLongTermAverage => {
+                    bands(Good, &[(100.0, Bad), (150.0, Terrible)])
+                }
+            }),
+        }
+    }
+
+    pub fn bands(&self, metric: Metric) -> &QualityBands {
+        &self.bands[metric.index()]
+    }
+
+    /// Replace the ranges of every metric with the same `Metric::quality_key`, e.g. of both
+    /// radon averages
+    pub fn set_bands(&mut self, metric: Metric, bands: QualityBands) {
+        for other in Metric::ALL {
+            if other.quality_key() == metric.quality_key() {
+                self.bands[other.index()] = bands.clone();
+            }
+        }
+    }
+
+    /// Apply the built-in profile with the name on top of this profile
+    pub fn with_builtin(mut self, name: &str) -> Result<Self, String> {
+        use SensorQuality::*;
+
+        match name {
+            "airthings" => return Ok(Self::airthings()),
+            // WHO handbook on indoor radon: reference level of 100 Bq/m3, and 300 Bq/m3 where
+            // 100 can't be reached
+            "who" => self.set_bands(
+                Metric::RadonShortTermAverage,
+                bands(Good, &[(100.0, Bad), (300.0, Terrible)]),
+            ),
+            // ASHRAE 62.1: no more than about 700 ppm above the outdoor level (~400 ppm)
+            "ashrae" => self.set_bands(
+                Metric::Co2,
+                bands(Good, &[(1100.0, Bad), (2000.0, Terrible)]),
+            ),
+            // UBA TVOC levels in ug/m3 converted to ppb with 4.5 ug/m3 per ppb: level 1 and 2
+            // (< 1000 ug/m3) good, level 3 (< 3000 ug/m3) bad, level 4 and up terrible
+            "uba" => self.set_bands(Metric::Voc, bands(Good, &[(222.0, Bad), (667.0, Terrible)])),
+            _ => {
+                return Err(format!(
+                    "unknown quality profile {:?}, expected one of {}",
+                    name,
+                    BUILTIN_PROFILE_NAMES.join(", ")
+                ))
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// The profile used by the `SensorQuality` functions. Airthings until `set_active` is called.
+    pub fn active() -> &'static QualityProfile {
+        ACTIVE_PROFILE.get_or_init(QualityProfile::airthings)
+    }
+
+    /// Can only be set once, at startup. Returns false when a profile was already active.
+    pub fn set_active(profile: QualityProfile) -> bool {
+        ACTIVE_PROFILE.set(profile).is_ok()
+    }
+}
+
+impl Default for QualityProfile {
+    fn default() -> Self {
+        Self::airthings()
+    }
+}
+
+fn bands(first: SensorQuality, bands: &[(f64, SensorQuality)]) -> QualityBands {
+    QualityBands::new(first, bands.to_vec()).expect("Built-in bands are ascending")
+}
@@ -0,0 +1,123 @@
+#[cfg(test)]
+mod tests {
+    use crate::shared::types::metric::Metric;
+    use crate::shared::types::quality_profile::{
+        QualityBands, QualityProfile, BUILTIN_PROFILE_NAMES,
+    };
+    use crate::shared::types::sensor_quality::SensorQuality;
+
+    #[test]
+    fn test_quality_bands() {
+        let bands = QualityBands::new(
+            SensorQuality::Terrible,
+            vec![(25.0, SensorQuality::Bad), (30.0, SensorQuality::Good)],
+        )
+        .unwrap();
+
+        assert_eq!(bands.quality(f64::MIN), SensorQuality::Terrible);
+        assert_eq!(bands.quality(24.9), SensorQuality::Terrible);
+        assert_eq!(bands.quality(25.0), SensorQuality::Bad);
+        assert_eq!(bands.quality(30.0), SensorQuality::Good);
+        assert_eq!(bands.quality(f64::MAX), SensorQuality::Good);
+    }
+
+    #[test]
+    fn test_quality_bands_must_be_ascending() {
+        let bands = QualityBands::new(
+            SensorQuality::Good,
+            vec![(30.0, SensorQuality::Bad), (30.0, SensorQuality::Terrible)],
+        );
+
+        assert!(bands.is_err());
+    }
+
+    #[test]
+    fn test_who_radon_reference_level() {
+        let profile = QualityProfile::airthings().with_builtin("who").unwrap();
+
+        assert_eq!(
+            profile.bands(Metric::RadonShortTermAverage).quality(99.0),
+            SensorQuality::Good
+        );
+        assert_eq!(
+            profile.bands(Metric::RadonShortTermAverage).quality(100.0),
+            SensorQuality::Bad
+        );
+        assert_eq!(
+            profile.bands(Metric::RadonShortTermAverage).quality(299.0),
+            SensorQuality::Bad
+        );
+        assert_eq!(
+            profile.bands(Metric::RadonShortTermAverage).quality(300.0),
+            SensorQuality::Terrible
+        );
+        assert_eq!(
+            profile.bands(Metric::RadonLongTermAverage),
+            profile.bands(Metric::RadonShortTermAverage)
+        );
+        assert_eq!(
+            profile.bands(Metric::Co2),
+            QualityProfile::airthings().bands(Metric::Co2)
+        );
+    }
+
+    #[test]
+    fn test_ashrae_co2() {
+        let profile = QualityProfile::airthings().with_builtin("ashrae").unwrap();
+
+        assert_eq!(
+            profile.bands(Metric::Co2).quality(1099.0),
+            SensorQuality::Good
+        );
+        assert_eq!(
+            profile.bands(Metric::Co2).quality(1100.0),
+            SensorQuality::Bad
+        );
+        assert_eq!(
+            profile.bands(Metric::Co2).quality(2000.0),
+            SensorQuality::Terrible
+        );
+    }
+
+    #[test]
+    fn test_uba_tvoc() {
+        let profile = QualityProfile::airthings().with_builtin("uba").unwrap();
+
+        assert_eq!(
+            profile.bands(Metric::Voc).quality(221.0),
+            SensorQuality::Good
+        );
+        assert_eq!(
+            profile.bands(Metric::Voc).quality(222.0),
+            SensorQuality::Bad
+        );
+        assert_eq!(
+            profile.bands(Metric::Voc).quality(667.0),
+            SensorQuality::Terrible
+        );
+    }
+
+    #[test]
+    fn test_builtin_profiles_can_be_combined() {
+        let profile = BUILTIN_PROFILE_NAMES[1..]
+            .iter()
+            .try_fold(QualityProfile::airthings(), |profile, name| {
+                profile.with_builtin(name)
+            })
+            .unwrap();
+
+        assert_eq!(
+            profile.bands(Metric::RadonShortTermAverage).quality(150.0),
+            SensorQuality::Bad
+        );
+        assert_eq!(
+            profile.bands(Metric::Co2).quality(900.0),
+            SensorQuality::Good
+        );
+        assert_eq!(
+            profile.bands(Metric::Voc).quality(300.0),
+            SensorQuality::Bad
+        );
+        assert!(QualityProfile::airthings().with_builtin("osha").is_err());
+    }
+}
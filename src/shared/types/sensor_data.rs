@@ -1,57 +1,51 @@
+use crate::shared::types::data_file_header::DataFileHeader;
+use crate::shared::types::metric::Metric;
 use crate::shared::types::sensor_quality::SensorQuality;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 
+use std::error::Error;
+use std::fmt;
 use std::str::FromStr;
 
 type DateTimeUtc = DateTime<Utc>;
 
+/// Metric columns of the version 1 data files. A line always has these, metrics added to
+/// `Metric::ALL` later are missing in older files.
+const REQUIRED_METRIC_COLUMNS: usize = 7;
+
+/// A reading of a device. Metrics the model of the device doesn't measure are `None`.
 #[derive(Debug, Clone)]
 pub struct SensorData {
     timestamp: DateTimeUtc,
-    temperature_in_celsius: f32,
-    humidity_in_percent: f32,
-    atmospheric_pressure: f32,
-    co2: f32,
-    voc: f32,
-    radon_short_term_average: f32,
-    radon_long_term_average: f32,
+    /// Indexed by `Metric::index`
+    values: [Option<f32>; Metric::COUNT],
 }
 
 #[derive(Debug, Clone)]
 pub struct SensorDataBuilder {
     pub timestamp: DateTimeUtc,
-    pub temperature_in_celsius: f32,
-    pub humidity_in_percent: f32,
-    pub atmospheric_pressure: f32,
-    pub co2: f32,
-    pub voc: f32,
-    pub radon_short_term_average: f32,
-    pub radon_long_term_average: f32,
+    /// The values of the metrics that were measured
+    pub values: Vec<(Metric, f32)>,
 }
 
 impl SensorData {
     pub fn new(builder: SensorDataBuilder) -> SensorData {
-        let SensorDataBuilder {
-            timestamp,
-            temperature_in_celsius,
-            humidity_in_percent,
-            atmospheric_pressure,
-            co2,
-            voc,
-            radon_short_term_average,
-            radon_long_term_average,
-        } = builder;
-
-        SensorData {
+        let SensorDataBuilder { timestamp, values } = builder;
+
+        let mut sensor_data = SensorData {
             timestamp,
-            temperature_in_celsius,
-            humidity_in_percent,
-            atmospheric_pressure,
-            co2,
-            voc,
-            radon_short_term_average,
-            radon_long_term_average,
+            values: [None; Metric::COUNT],
+        };
+        for (metric, value) in values {
+            sensor_data.values[metric.index()] = Some(value);
         }
+
+        sensor_data
+    }
+
+    /// A reading from a value, or `None`, per metric in the order of `Metric::ALL`
+    pub fn from_values(timestamp: DateTimeUtc, values: [Option<f32>; Metric::COUNT]) -> Self {
+        Self { timestamp, values }
     }
 }
 
@@ -61,82 +55,73 @@ impl From<SensorDataBuilder> for SensorData {
     }
 }
 
-/// Getters
+/// Test fixtures
+#[cfg(test)]
 impl SensorData {
-    pub fn timestamp(&self) -> &DateTimeUtc {
-        &self.timestamp
-    }
-
-    pub fn temperature_in_celsius(&self) -> f32 {
-        self.temperature_in_celsius
-    }
+    /// Midnight UTC of the day of the test readings, 2020-05-01
+    pub fn test_day_start() -> DateTimeUtc {
+        use chrono::TimeZone;
 
-    pub fn humidity_in_percent(&self) -> f32 {
-        self.humidity_in_percent
+        Utc.with_ymd_and_hms(2020, 5, 1, 0, 0, 0).unwrap()
     }
 
-    pub fn atmospheric_pressure(&self) -> f32 {
-        self.atmospheric_pressure
+    /// A reading of a Wave Plus with a plausible value for every metric, all of them of good
+    /// quality
+    pub fn test_reading(timestamp: DateTimeUtc) -> Self {
+        SensorDataBuilder {
+            timestamp,
+            values: vec![
+                (Metric::Temperature, 21.0),
+                (Metric::Humidity, 40.0),
+                (Metric::AtmosphericPressure, 1000.0),
+                (Metric::Co2, 500.0),
+                (Metric::Voc, 100.0),
+                (Metric::RadonShortTermAverage, 20.0),
+                (Metric::RadonLongTermAverage, 20.0),
+            ],
+        }
+        .into()
     }
 
-    pub fn co2(&self) -> f32 {
-        self.co2
+    /// The same reading with another value of the metric
+    pub fn with_value(mut self, metric: Metric, value: f32) -> Self {
+        self.values[metric.index()] = Some(value);
+        self
     }
+}
 
-    pub fn voc(&self) -> f32 {
-        self.voc
+/// Getters
+impl SensorData {
+    pub fn timestamp(&self) -> &DateTimeUtc {
+        &self.timestamp
     }
 
-    pub fn radon_short_term_average(&self) -> f32 {
-        self.radon_short_term_average
+    pub fn value(&self, metric: Metric) -> Option<f32> {
+        self.values[metric.index()]
     }
 
-    #[allow(dead_code)]
-    pub fn radon_long_term_average(&self) -> f32 {
-        self.radon_long_term_average
+    /// The measured metrics and their values
+    pub fn values(&self) -> impl Iterator<Item = (Metric, f32)> + '_ {
+        Metric::ALL
+            .iter()
+            .filter_map(move |&metric| Some((metric, self.value(metric)?)))
     }
 }
 
 /// Quality
 impl SensorData {
-    pub fn temperature_quality(&self) -> SensorQuality {
-        SensorQuality::temperature_quality(self.temperature_in_celsius.round() as i32)
-    }
-
-    pub fn humidity_quality(&self) -> SensorQuality {
-        SensorQuality::humidity_quality(self.humidity_in_percent.round() as u32)
-    }
-
-    pub fn atmospheric_pressure_quality(&self) -> SensorQuality {
-        SensorQuality::atmospheric_pressure_quality(self.atmospheric_pressure.round() as u32)
-    }
-
-    pub fn co2_quality(&self) -> SensorQuality {
-        SensorQuality::co2_quality(self.co2.round() as u32)
-    }
-
-    pub fn voc_quality(&self) -> SensorQuality {
-        SensorQuality::voc_quality(self.voc.round() as u32)
-    }
-
-    pub fn radon_short_term_quality(&self) -> SensorQuality {
-        SensorQuality::radon_quality(self.radon_short_term_average.round() as u32)
-    }
-
-    #[allow(dead_code)]
-    pub fn radon_long_term_quality(&self) -> SensorQuality {
-        SensorQuality::radon_quality(self.radon_long_term_average.round() as u32)
+    /// `None` when the metric wasn't measured
+    pub fn quality(&self, metric: Metric) -> Option<SensorQuality> {
+        self.value(metric).map(|value| metric.quality(value))
     }
 
+    /// The long-term radon average follows the short-term one, so it is left out
     pub fn worst_sensor_quality(&self) -> SensorQuality {
-        let list = vec![
-            self.temperature_quality(),
-            self.radon_short_term_quality(),
-            self.voc_quality(),
-            self.humidity_quality(),
-            self.co2_quality(),
-            self.atmospheric_pressure_quality(),
-        ];
+        let list = self
+            .values()
+            .filter(|(metric, _)| *metric != Metric::RadonLongTermAverage)
+            .map(|(metric, value)| metric.quality(value))
+            .collect();
 
         SensorQuality::worst_sensor_quality(list)
     }
@@ -144,66 +129,112 @@ impl SensorData {
 
 /// CSV functions
 impl SensorData {
+    /// A data line with the RFC 3339 timestamp, e.g. `2020-05-01T12:00:00Z`, and the values in
+    /// the order of `Metric::ALL`
     pub fn to_csv(&self) -> String {
-        let elements: [String; 9] = [
-            format!("{}", self.timestamp),
-            self.temperature_in_celsius.to_string(),
-            self.humidity_in_percent.to_string(),
-            self.atmospheric_pressure.to_string(),
-            self.co2.to_string(),
-            self.voc.to_string(),
-            self.radon_short_term_average.to_string(),
-            self.radon_long_term_average.to_string(),
-            "".to_string(),
-        ];
+        let mut elements = vec![self.timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true)];
+        elements.extend(self.values.iter().map(|value| csv_value(*value)));
 
-        elements.join(",").to_string()
+        elements.join(",")
     }
 
     pub fn to_csv_with_header(&self, device_serial_number: u32) -> String {
-        [csv_header(device_serial_number), self.to_csv()]
-            .join("\n")
-            .to_string()
+        format!(
+            "{}\n{}",
+            DataFileHeader::new(device_serial_number),
+            self.to_csv()
+        )
+    }
+
+    /// Parse a data line with the values in the order of `Metric::ALL`, as in the version 1 data
+    /// files. Missing or invalid columns are reported as an error instead of panicking, e.g. for
+    /// a line that was cut off by a power failure. An empty value is a metric the device doesn't
+    /// measure.
+    pub fn from_csv_line(csv_line: &str) -> Result<Self, SensorDataParseError> {
+        let mut columns = csv_line.trim_end_matches('\r').split(',');
+        let timestamp = parse_timestamp(columns.next())?;
+
+        let mut values = [None; Metric::COUNT];
+        for (value, metric) in values.iter_mut().zip(Metric::ALL.iter()) {
+            *value = match columns.next() {
+                Some(column) => parse_csv_value(column, *metric)?,
+                None if metric.index() < REQUIRED_METRIC_COLUMNS => {
+                    return Err(SensorDataParseError::MissingColumn(metric.name()))
+                }
+                None => None,
+            };
+        }
+
+        Ok(Self { timestamp, values })
+    }
+
+    /// Parse a data line with the metric of every column after the timestamp, as given by the
+    /// header of the file. The columns of unknown metrics are skipped.
+    pub fn from_csv_columns(
+        csv_line: &str,
+        metrics: &[Option<Metric>],
+    ) -> Result<Self, SensorDataParseError> {
+        let mut columns = csv_line.trim_end_matches('\r').split(',');
+        let timestamp = parse_timestamp(columns.next())?;
+
+        let mut values = [None; Metric::COUNT];
+        for metric in metrics {
+            let column = columns.next().ok_or_else(|| {
+                SensorDataParseError::MissingColumn(metric.map_or("Unknown", Metric::name))
+            })?;
+            if let Some(metric) = metric {
+                values[metric.index()] = parse_csv_value(column, *metric)?;
+            }
+        }
+
+        Ok(Self { timestamp, values })
     }
+}
 
-    pub fn from_csv_line(csv_line: &str) -> Option<Self> {
-        let mut splitted_data = csv_line.split(",");
-        let timestamp: DateTimeUtc =
-            chrono::DateTime::from_str(splitted_data.next().unwrap()).unwrap();
+/// RFC 3339, or the `2020-05-01 12:00:00 UTC` form of the files of older versions
+fn parse_timestamp(value: Option<&str>) -> Result<DateTimeUtc, SensorDataParseError> {
+    let value = value
+        .filter(|value| !value.is_empty())
+        .ok_or(SensorDataParseError::MissingColumn("Timestamp"))?;
 
-        Some(Self {
-            timestamp,
-            temperature_in_celsius: splitted_data.next().unwrap().parse::<f32>().unwrap(),
-            humidity_in_percent: splitted_data.next().unwrap().parse::<f32>().unwrap(),
-            atmospheric_pressure: splitted_data.next().unwrap().parse::<f32>().unwrap(),
-            co2: splitted_data.next().unwrap().parse::<f32>().unwrap(),
-            voc: splitted_data.next().unwrap().parse::<f32>().unwrap(),
-            radon_short_term_average: splitted_data.next().unwrap().parse::<f32>().unwrap(),
-            radon_long_term_average: splitted_data.next().unwrap().parse::<f32>().unwrap(),
-        })
+    DateTime::parse_from_rfc3339(value)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .or_else(|_| DateTimeUtc::from_str(value))
+        .map_err(|_| SensorDataParseError::InvalidValue("Timestamp", value.to_owned()))
+}
+
+fn csv_value(value: Option<f32>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn parse_csv_value(value: &str, metric: Metric) -> Result<Option<f32>, SensorDataParseError> {
+    if value.is_empty() {
+        return Ok(None);
     }
+
+    value
+        .parse::<f32>()
+        .map(Some)
+        .map_err(|_| SensorDataParseError::InvalidValue(metric.name(), value.to_owned()))
 }
 
-pub fn csv_header(device_serial_number: u32) -> String {
-    [
-        "Timestamp",
-        "Temperature (C)",
-        "Humidity (%)",
-        "Atmospheric pressure (mbar)",
-        "CO2 (ppm)",
-        "TVOC (ppb)",
-        "Radon short-term average (Bq/m3)",
-        "Radon long-term average (Bq/m3)",
-        device_serial_number.to_string().as_str(),
-    ]
-    .join(",")
-    .to_string()
+#[derive(Debug, Clone, PartialEq)]
+pub enum SensorDataParseError {
+    MissingColumn(&'static str),
+    InvalidValue(&'static str, String),
 }
 
-pub fn latest_entry_from_file(raw_data_file: &str) -> Option<SensorData> {
-    raw_data_file
-        .lines()
-        .last()
-        .map(|line| SensorData::from_csv_line(line))
-        .flatten()
+impl fmt::Display for SensorDataParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SensorDataParseError::MissingColumn(column_name) => {
+                write!(f, "missing column: {}", column_name)
+            }
+            SensorDataParseError::InvalidValue(column_name, value) => {
+                write!(f, "invalid value in column {}: {:?}", column_name, value)
+            }
+        }
+    }
 }
+
+impl Error for SensorDataParseError {}
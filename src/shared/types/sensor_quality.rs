@@ -1,4 +1,8 @@
-#[derive(PartialEq, Eq, Debug)]
+use serde::Deserialize;
+
+/// The quality of a sensor value, following the ranges of the active `QualityProfile`
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SensorQuality {
     Good,
     Bad,
@@ -7,52 +11,6 @@ pub enum SensorQuality {
 }
 
 impl SensorQuality {
-    pub fn temperature_quality(value: i32) -> SensorQuality {
-        match value {
-            26..=i32::MAX => SensorQuality::Terrible,
-            18..=25 => SensorQuality::Good,
-            i32::MIN..=17 => SensorQuality::DependsOnContext,
-        }
-    }
-
-    pub fn humidity_quality(value: u32) -> SensorQuality {
-        match value {
-            70..=u32::MAX => SensorQuality::Terrible,
-            60..=69 => SensorQuality::Bad,
-            30..=59 => SensorQuality::Good,
-            25..=29 => SensorQuality::Bad,
-            u32::MIN..=24 => SensorQuality::Terrible,
-        }
-    }
-
-    pub fn atmospheric_pressure_quality(_value: u32) -> SensorQuality {
-        SensorQuality::DependsOnContext
-    }
-
-    pub fn co2_quality(value: u32) -> SensorQuality {
-        match value {
-            1000..=u32::MAX => SensorQuality::Terrible,
-            800..=999 => SensorQuality::Bad,
-            u32::MIN..=799 => SensorQuality::Good,
-        }
-    }
-
-    pub fn voc_quality(value: u32) -> SensorQuality {
-        match value {
-            2000..=u32::MAX => SensorQuality::Terrible,
-            250..=1999 => SensorQuality::Bad,
-            u32::MIN..=249 => SensorQuality::Good,
-        }
-    }
-
-    pub fn radon_quality(value: u32) -> SensorQuality {
-        match value {
-            150..=u32::MAX => SensorQuality::Terrible,
-            100..=149 => SensorQuality::Bad,
-            u32::MIN..=99 => SensorQuality::Good,
-        }
-    }
-
     pub fn worst_sensor_quality(sensor_data_list: Vec<SensorQuality>) -> SensorQuality {
         if sensor_data_list
             .iter()
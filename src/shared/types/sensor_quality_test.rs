@@ -1,17 +1,18 @@
 #[cfg(test)]
 mod tests {
+    use crate::shared::types::metric::Metric;
     use crate::shared::types::sensor_quality::SensorQuality;
 
     #[test]
     fn temperature_quality() {
-        let terrible_low = SensorQuality::temperature_quality(26);
-        let terrible_high = SensorQuality::temperature_quality(i32::MAX);
+        let terrible_low = Metric::Temperature.quality(26.0);
+        let terrible_high = Metric::Temperature.quality(f32::MAX);
 
-        let good_low = SensorQuality::temperature_quality(18);
-        let good_high = SensorQuality::temperature_quality(25);
+        let good_low = Metric::Temperature.quality(18.0);
+        let good_high = Metric::Temperature.quality(25.0);
 
-        let depends_low = SensorQuality::temperature_quality(i32::MIN);
-        let depends_high = SensorQuality::temperature_quality(17);
+        let depends_low = Metric::Temperature.quality(f32::MIN);
+        let depends_high = Metric::Temperature.quality(17.0);
 
         assert_eq!(terrible_low, SensorQuality::Terrible);
         assert_eq!(terrible_high, SensorQuality::Terrible);
@@ -23,8 +24,8 @@ mod tests {
 
     #[test]
     fn atmospheric_pressure_quality() {
-        let depends_low = SensorQuality::atmospheric_pressure_quality(u32::MIN);
-        let depends_high = SensorQuality::atmospheric_pressure_quality(u32::MAX);
+        let depends_low = Metric::AtmosphericPressure.quality(0.0);
+        let depends_high = Metric::AtmosphericPressure.quality(f32::MAX);
 
         assert_eq!(depends_low, SensorQuality::DependsOnContext);
         assert_eq!(depends_high, SensorQuality::DependsOnContext);
@@ -32,14 +33,14 @@ mod tests {
 
     #[test]
     fn co2_quality() {
-        let terrible_low = SensorQuality::co2_quality(1000);
-        let terrible_high = SensorQuality::co2_quality(u32::MAX);
+        let terrible_low = Metric::Co2.quality(1000.0);
+        let terrible_high = Metric::Co2.quality(f32::MAX);
 
-        let bad_low = SensorQuality::co2_quality(800);
-        let bad_high = SensorQuality::co2_quality(999);
+        let bad_low = Metric::Co2.quality(800.0);
+        let bad_high = Metric::Co2.quality(999.0);
 
-        let good_low = SensorQuality::co2_quality(u32::MIN);
-        let good_high = SensorQuality::co2_quality(799);
+        let good_low = Metric::Co2.quality(0.0);
+        let good_high = Metric::Co2.quality(799.0);
 
         assert_eq!(terrible_low, SensorQuality::Terrible);
         assert_eq!(terrible_high, SensorQuality::Terrible);
@@ -53,14 +54,14 @@ mod tests {
 
     #[test]
     fn voc_quality() {
-        let terrible_low = SensorQuality::voc_quality(2000);
-        let terrible_high = SensorQuality::voc_quality(u32::MAX);
+        let terrible_low = Metric::Voc.quality(2000.0);
+        let terrible_high = Metric::Voc.quality(f32::MAX);
 
-        let bad_low = SensorQuality::voc_quality(250);
-        let bad_high = SensorQuality::voc_quality(1999);
+        let bad_low = Metric::Voc.quality(250.0);
+        let bad_high = Metric::Voc.quality(1999.0);
 
-        let good_low = SensorQuality::voc_quality(249);
-        let good_high = SensorQuality::voc_quality(149);
+        let good_low = Metric::Voc.quality(249.0);
+        let good_high = Metric::Voc.quality(149.0);
 
         assert_eq!(terrible_low, SensorQuality::Terrible);
         assert_eq!(terrible_high, SensorQuality::Terrible);
@@ -72,20 +73,20 @@ mod tests {
 
     #[test]
     fn humidity_quality() {
-        let terrible_low_a = SensorQuality::humidity_quality(70);
-        let terrible_high_a = SensorQuality::humidity_quality(u32::MAX);
+        let terrible_low_a = Metric::Humidity.quality(70.0);
+        let terrible_high_a = Metric::Humidity.quality(f32::MAX);
 
-        let bad_low_a = SensorQuality::humidity_quality(60);
-        let bad_high_a = SensorQuality::humidity_quality(69);
+        let bad_low_a = Metric::Humidity.quality(60.0);
+        let bad_high_a = Metric::Humidity.quality(69.0);
 
-        let good_low = SensorQuality::humidity_quality(30);
-        let good_high = SensorQuality::humidity_quality(59);
+        let good_low = Metric::Humidity.quality(30.0);
+        let good_high = Metric::Humidity.quality(59.0);
 
-        let bad_low_b = SensorQuality::humidity_quality(25);
-        let bad_high_b = SensorQuality::humidity_quality(29);
+        let bad_low_b = Metric::Humidity.quality(25.0);
+        let bad_high_b = Metric::Humidity.quality(29.0);
 
-        let terrible_low_b = SensorQuality::humidity_quality(u32::MIN);
-        let terrible_high_b = SensorQuality::humidity_quality(24);
+        let terrible_low_b = Metric::Humidity.quality(0.0);
+        let terrible_high_b = Metric::Humidity.quality(24.0);
 
         assert_eq!(terrible_low_a, SensorQuality::Terrible);
         assert_eq!(terrible_high_a, SensorQuality::Terrible);
@@ -105,14 +106,14 @@ mod tests {
 
     #[test]
     fn radon_quality() {
-        let terrible_low = SensorQuality::radon_quality(150);
-        let terrible_high = SensorQuality::radon_quality(u32::MAX);
+        let terrible_low = Metric::RadonShortTermAverage.quality(150.0);
+        let terrible_high = Metric::RadonShortTermAverage.quality(f32::MAX);
 
-        let bad_low = SensorQuality::radon_quality(100);
-        let bad_high = SensorQuality::radon_quality(149);
+        let bad_low = Metric::RadonShortTermAverage.quality(100.0);
+        let bad_high = Metric::RadonShortTermAverage.quality(149.0);
 
-        let good_low = SensorQuality::radon_quality(u32::MIN);
-        let good_high = SensorQuality::radon_quality(99);
+        let good_low = Metric::RadonShortTermAverage.quality(0.0);
+        let good_high = Metric::RadonShortTermAverage.quality(99.0);
 
         assert_eq!(terrible_low, SensorQuality::Terrible);
         assert_eq!(terrible_high, SensorQuality::Terrible);